@@ -0,0 +1,375 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+#![feature(unix_sigpipe)]
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use linkspace::{
+    conventions::{
+        exchange::{lk_exchange_session, LkExchange, LkExchangeSession},
+        status::{lk_status_set, LkStatus},
+    },
+    lk_linkpoint,
+};
+use linkspace_common::{
+    anyhow::{self, Context},
+    cli::{
+        clap,
+        clap::{Args, Parser, Subcommand},
+        keys::KeyOpts,
+        opts::CommonOpts,
+        tracing,
+    },
+    core::crypto::{channel::FrameCipher, Hash},
+    pkt_reader::NetPktDecoder,
+    prelude::{lmdb::BTreeEnv, *},
+    protocols::handshake::*,
+};
+use tracing_subscriber::EnvFilter;
+
+#[derive(Parser)]
+/**
+Exchange the packets of a group with another instance.
+
+After the handshake both sides:
+- send every packet in GROUP created locally (hop=0)
+- forward the local pull requests for GROUP (see linkspace::conventions::pull) as keypoints in GROUP
+- answer the pull requests of the other side by watching the query and sending the results until the pull is closed
+- reply to the status requests 'exchange GROUP process', 'exchange GROUP connection PUBKEY', and 'exchange GROUP pull PULL_HASH'
+
+Received packets outside of GROUP are ignored.
+Two local instances can exchange without a network, e.g.
+
+LK_DIR=/tmp/a lk-exchange [#:test] serve --unix /tmp/lk.sock
+
+LK_DIR=/tmp/b lk-exchange [#:test] connect --unix /tmp/lk.sock
+**/
+pub struct Opts {
+    #[command(flatten)]
+    common: CommonOpts,
+    #[command(flatten)]
+    key: KeyOpts,
+    /// the group to exchange
+    group: GroupExpr,
+    #[command(subcommand)]
+    mode: Mode,
+    #[arg(long)]
+    max_diff_secs: Option<usize>,
+    /// only answer pull requests accepted by the most recent pull access list signed by this key (see linkspace::conventions::exchange_acl)
+    #[arg(long)]
+    acl_key: Option<PubKeyExpr>,
+    /// the instance name used in status replies
+    #[arg(long, default_value = "lk-exchange")]
+    instance: String,
+}
+
+#[derive(Subcommand)]
+pub enum Mode {
+    /// connect to a server and exchange until either side closes
+    Connect(Endpoint),
+    /// accept connections and exchange with every peer
+    Serve(Endpoint),
+}
+
+#[derive(Args, Debug, Clone)]
+#[group(required = true, multiple = false)]
+pub struct Endpoint {
+    /// e.g. 127.0.0.1:5020
+    #[arg(long)]
+    tcp: Option<String>,
+    #[arg(long)]
+    unix: Option<PathBuf>,
+}
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.tcp, &self.unix) {
+            (Some(addr), _) => write!(f, "tcp:{addr}"),
+            (_, Some(path)) => write!(f, "unix:{}", path.display()),
+            _ => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Ctx {
+    env: BTreeEnv,
+    id: SigningKey,
+    group: GroupID,
+    max_diff_secs: Option<usize>,
+    acl_key: Option<PubKey>,
+    instance: Vec<u8>,
+}
+
+/// A socket we can split into a reader and a writer
+trait Socket: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> std::io::Result<Self>;
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()>;
+}
+impl Socket for TcpStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+}
+impl Socket for UnixStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        UnixStream::shutdown(self, how)
+    }
+}
+
+#[unix_sigpipe = "sig_dfl"]
+fn main() -> anyhow::Result<()> {
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(tracing::metadata::LevelFilter::WARN.into())
+        .from_env()?;
+    tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
+        .init();
+    let Opts {
+        common,
+        key,
+        group,
+        mode,
+        max_diff_secs,
+        acl_key,
+        instance,
+    } = Opts::parse();
+    let scope = common.eval_scope();
+    let group = group.eval(&scope)?;
+    anyhow::ensure!(group != PRIVATE, "the private group is never exchanged");
+    let acl_key = acl_key.map(|k| k.eval(&scope)).transpose()?;
+    let id = key.identity(&common, false).context("Decrypting pass")?;
+    let rx = common.runtime()?;
+    let ctx = Ctx {
+        env: rx.env().clone(),
+        id: id.clone(),
+        group,
+        max_diff_secs,
+        acl_key,
+        instance: instance.into_bytes(),
+    };
+
+    let done = Arc::new(AtomicBool::new(false));
+    let (serve, endpoint) = match mode {
+        Mode::Connect(e) => (false, e),
+        Mode::Serve(e) => (true, e),
+    };
+    let info = format!(
+        "OK\nPID:{}\n{} {endpoint}\n",
+        std::process::id(),
+        if serve { "serve" } else { "connect" }
+    );
+    let conn = {
+        let (ctx, done) = (ctx.clone(), done.clone());
+        std::thread::spawn(move || {
+            let r = if serve {
+                listen(&ctx, &endpoint)
+            } else {
+                connect(&ctx, &endpoint)
+            };
+            done.store(true, Ordering::Relaxed);
+            r
+        })
+    };
+
+    let lk: linkspace::Linkspace = rx.clone().into();
+    let status = LkStatus {
+        domain: EXCHANGE_DOMAIN,
+        group,
+        objtype: b"process",
+        instance: Some(&ctx.instance[..]),
+        qid: b"status-process",
+    };
+    lk_status_set(&lk, status, move |_, domain, group, space, link| {
+        lk_linkpoint(info.as_bytes(), domain, group, space, &[link], None)
+    })?;
+    while !done.load(Ordering::Relaxed) {
+        rx.run_while(Some(Instant::now() + Duration::from_secs(1)), None)?;
+    }
+    conn.join().expect("connection thread panicked")
+}
+
+fn connect(ctx: &Ctx, endpoint: &Endpoint) -> anyhow::Result<()> {
+    match (&endpoint.tcp, &endpoint.unix) {
+        (Some(addr), _) => exchange(ctx, TcpStream::connect(addr)?, false),
+        (_, Some(path)) => exchange(ctx, UnixStream::connect(path)?, false),
+        _ => anyhow::bail!("missing --tcp or --unix"),
+    }
+}
+
+fn listen(ctx: &Ctx, endpoint: &Endpoint) -> anyhow::Result<()> {
+    match (&endpoint.tcp, &endpoint.unix) {
+        (Some(addr), _) => accept(ctx, TcpListener::bind(addr)?.incoming()),
+        (_, Some(path)) => accept(ctx, UnixListener::bind(path)?.incoming()),
+        _ => anyhow::bail!("missing --tcp or --unix"),
+    }
+}
+
+fn accept<S: Socket>(
+    ctx: &Ctx,
+    incoming: impl Iterator<Item = std::io::Result<S>>,
+) -> anyhow::Result<()> {
+    for sock in incoming {
+        let sock = sock?;
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = exchange(&ctx, sock, true) {
+                tracing::warn!(?e, "session ended");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handshake<S: Socket>(ctx: &Ctx, sock: &mut S, serve: bool) -> anyhow::Result<Session> {
+    let id = &ctx.id;
+    let mut inp = NetPktDecoder::new(sock.try_clone()?);
+    let mut next = |expected: &'static str| -> anyhow::Result<NetPktBox> {
+        Ok(inp.next().context(expected)??)
+    };
+    let session = if serve {
+        let phase0 = Phase0(next("client hung up immediately")?);
+        let phase1 = phase1_server_signs(&phase0, id, ctx.max_diff_secs, Some(ctx.group))?;
+        phase1.0.byte_segments().write_into(&mut *sock)?;
+        let phase2 = Phase2(next("Missing phase2")?);
+        phase3_server_verify(&phase0, &phase1, &phase2, id)?
+    } else {
+        let phase0 = phase0_client_init(id, &Offer::new(Some(ctx.group)));
+        phase0.0.byte_segments().write_into(&mut *sock)?;
+        let phase1 = Phase1(next("Missing phase1")?);
+        let (phase2, session) = phase2_client_signs(&phase0, &phase1, id, ctx.max_diff_secs)?;
+        phase2.0.byte_segments().write_into(&mut *sock)?;
+        session
+    };
+    anyhow::ensure!(
+        session.group == Some(ctx.group),
+        "peer agreed on a different group"
+    );
+    Ok(session)
+}
+
+/// One frame per packet. Sends the closing frame once every outbox sender is dropped.
+fn send(key: Hash, mut sock: impl Write, outbox: mpsc::Receiver<Box<[u8]>>) -> anyhow::Result<()> {
+    let mut cipher = FrameCipher::new(&key);
+    for bytes in outbox {
+        cipher.write_frame(&mut sock, &bytes)?;
+    }
+    cipher.write_frame(&mut sock, &[])?;
+    Ok(())
+}
+
+fn receive(env: BTreeEnv, key: Hash, group: GroupID, mut sock: impl Read) -> anyhow::Result<()> {
+    let mut cipher = FrameCipher::new(&key);
+    while let Some(frame) = cipher.read_frame(&mut sock)? {
+        for pkt in NetPktDecoder::new(frame.as_slice()) {
+            let pkt = pkt?;
+            if !pkt.is_datapoint() && *pkt.get_group() != group {
+                tracing::warn!(hash=%pkt.hash(), "ignoring packet outside of group");
+                continue;
+            }
+            let state = env.save_ptr_one(&pkt)?;
+            if state == SaveState::Rejected {
+                tracing::warn!(hash=%pkt.hash(), "rejected by quota");
+            }
+            tracing::debug!(hash=%pkt.hash(), %state, "rx");
+        }
+    }
+    Ok(())
+}
+
+fn exchange<S: Socket>(ctx: &Ctx, mut sock: S, serve: bool) -> anyhow::Result<()> {
+    let session = handshake(ctx, &mut sock, serve)?;
+    let their_key = session.their_key;
+    tracing::info!(%their_key, "connected");
+    let (send_key, recv_key) = session.channel_keys()?;
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let receiver = {
+        let (env, group, closed) = (ctx.env.clone(), ctx.group, closed.clone());
+        let sock = sock.try_clone()?;
+        std::thread::spawn(move || {
+            let r = receive(env, recv_key, group, sock);
+            closed.store(true, Ordering::Relaxed);
+            r
+        })
+    };
+    let (tx, outbox) = mpsc::channel();
+    let sender = {
+        let closed = closed.clone();
+        let sock = sock.try_clone()?;
+        std::thread::spawn(move || {
+            let r = send(send_key, sock, outbox);
+            closed.store(true, Ordering::Relaxed);
+            r
+        })
+    };
+
+    let r = run_session(ctx, their_key, tx, &closed);
+    let sent = sender.join().expect("sender panicked");
+    // if the peer did not close its side, stop reading
+    let received_all = receiver.is_finished();
+    sock.shutdown(Shutdown::Both).ok();
+    let received = receiver.join().expect("receiver panicked");
+    let mut session = r?;
+    sent.context("sending")?;
+    // only now is every pushed packet flushed to the peer
+    let lk: linkspace::Linkspace =
+        Linkspace::new_opt_rt(ctx.env.clone(), Default::default()).into();
+    session.save_txlog(&lk)?;
+    if received_all {
+        received.context("receiving")?;
+    }
+    tracing::info!(%their_key, "disconnected");
+    Ok(())
+}
+
+/// Setup the watches and process them until the connection closes.
+/// Returning drops the runtime and thereby every outbox sender.
+/// The session is returned so its txlog is saved once the sender has flushed.
+fn run_session(
+    ctx: &Ctx,
+    their_key: PubKey,
+    tx: mpsc::Sender<Box<[u8]>>,
+    closed: &AtomicBool,
+) -> anyhow::Result<LkExchangeSession> {
+    let rx = Linkspace::new_opt_rt(ctx.env.clone(), Default::default());
+    let lk: linkspace::Linkspace = rx.clone().into();
+    let exchange = LkExchange {
+        key: &ctx.id,
+        group: ctx.group,
+        their_key,
+        acl_key: ctx.acl_key,
+    };
+    let mut session = lk_exchange_session(&lk, exchange, move |pkt| {
+        tx.send(pkt.byte_segments().to_bytes())
+            .context("sender closed")
+    })?;
+
+    let mut last_save = Instant::now();
+    while !closed.load(Ordering::Relaxed) {
+        rx.run_while(Some(Instant::now() + Duration::from_secs(1)), None)?;
+        if last_save.elapsed() > Duration::from_secs(60) {
+            last_save = Instant::now();
+            session.save_txlog(&lk)?;
+        }
+    }
+    Ok(session)
+}
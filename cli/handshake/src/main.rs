@@ -4,17 +4,24 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 #![feature(unix_sigpipe)]
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
 use linkspace_common::{
     anyhow::{self, Context},
     cli::{clap::Parser, keys::KeyOpts, opts::CommonOpts, reader::PktReadOpts, *},
-    pkt_reader,
-    prelude::NetPktFatPtr,
+    core::crypto::channel::{decrypt_stream, encrypt_stream},
+    prelude::{GroupExpr, NetPktBox, NetPktFatPtr},
 };
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 /**
-Exchange keypoints to prove each side holds a key.
+Exchange keypoints to prove each side holds a key, and negotiate a group and session key.
+
+With --exec the remaining input and stdout become an encrypted channel for the stdin/stdout of CMD.
 **/
 pub struct Opts {
     #[command(flatten)]
@@ -26,6 +33,13 @@ pub struct Opts {
     forward: Vec<WriteDestSpec>,
     #[arg(long)]
     max_diff_secs: Option<usize>,
+    /// the group to request (connect) or the only group to serve (serve)
+    #[arg(long)]
+    group: Option<GroupExpr>,
+    /// after connect/serve run CMD with 'sh -c' and tunnel its stdin/stdout through the encrypted channel.
+    /// THEIR_KEY and LK_GROUP are set for CMD.
+    #[arg(long, value_name = "CMD")]
+    exec: Option<String>,
     #[command(flatten)]
     key: KeyOpts,
     #[command(subcommand)]
@@ -64,6 +78,8 @@ fn main() -> anyhow::Result<()> {
         write,
         forward,
         max_diff_secs,
+        group,
+        exec,
         key,
         mode,
         inp,
@@ -72,64 +88,98 @@ fn main() -> anyhow::Result<()> {
     let mut forward = common.open(&forward)?;
     let mut write = common.open(&write)?;
     let id = key.identity(&common, false).context("Decrypting pass")?;
+    let group = group.map(|g| g.eval(&common.eval_scope())).transpose()?;
+    anyhow::ensure!(
+        exec.is_none() || matches!(mode, Handshake::Connect | Handshake::Serve),
+        "--exec requires connect or serve"
+    );
     let c2 = common.clone();
     let mut writer = |pkt: &NetPktFatPtr| c2.write_multi_dest(&mut write, &**pkt, None);
     use linkspace_common::protocols::handshake::*;
     let mut pkt_inp = common.inp_reader(&inp)?;
-    let mut inp = std::iter::from_fn(move || {
-        Some(match pkt_inp.next()? {
-            Ok(p) => common
-                .write_multi_dest(&mut forward, &**p, None)
-                .map(move |()| p)
-                .map_err(pkt_reader::Error::from),
-            Err(e) => Err(e),
-        })
-    });
-    match mode {
-        Handshake::Phase0 => writer(&phase0_client_init(id).0)?,
-        Handshake::Phase1 => writer(
-            &phase1_server_signs(
-                &Phase0(inp.next().context("Expected phase0")??),
-                id,
-                max_diff_secs,
-            )?
-            .0,
-        )?,
+    let mut next = |expected: &'static str| -> anyhow::Result<NetPktBox> {
+        let p = pkt_inp.next().context(expected)??;
+        common.write_multi_dest(&mut forward, &**p, None)?;
+        Ok(p)
+    };
+    let session = match mode {
+        Handshake::Phase0 => {
+            writer(&phase0_client_init(id, &Offer::new(group)).0)?;
+            None
+        }
+        Handshake::Phase1 => {
+            writer(
+                &phase1_server_signs(&Phase0(next("Expected phase0")?), id, max_diff_secs, group)?
+                    .0,
+            )?;
+            None
+        }
         Handshake::Phase2 => {
-            let (phase2, _server_key) = phase2_client_signs(
-                &Phase0(inp.next().context("Missing phase0")??),
-                &Phase1(inp.next().context("Missing phase1")??),
+            let (phase2, _session) = phase2_client_signs(
+                &Phase0(next("Missing phase0")?),
+                &Phase1(next("Missing phase1")?),
                 id,
                 max_diff_secs,
             )?;
-            writer(&phase2.0)?
+            writer(&phase2.0)?;
+            None
         }
         Handshake::Phase3 => {
             phase3_server_verify(
-                &Phase0(inp.next().context("Missing phase0")??),
-                &Phase1(inp.next().context("Missing phase1")??),
-                &Phase2(inp.next().context("Missing phase2")??),
+                &Phase0(next("Missing phase0")?),
+                &Phase1(next("Missing phase1")?),
+                &Phase2(next("Missing phase2")?),
                 id,
             )?;
+            None
         }
         Handshake::Connect => {
-            let phase0 = phase0_client_init(id);
+            let phase0 = phase0_client_init(id, &Offer::new(group));
             writer(&phase0.0)?;
-            let phase1 = Phase1(inp.next().context("Missing phase1")??);
-            let (phase2, _key) = phase2_client_signs(&phase0, &phase1, id, max_diff_secs)?;
+            let phase1 = Phase1(next("Missing phase1")?);
+            let (phase2, session) = phase2_client_signs(&phase0, &phase1, id, max_diff_secs)?;
             writer(&phase2.0)?;
+            Some(session)
         }
         Handshake::Serve => {
             tracing::trace!("Init server");
-            let phase0 = match inp.next() {
-                Some(p) => Phase0(p.context("Reading phase0")?),
-                None => anyhow::bail!("client hung up immediately"),
-            };
-            let phase1 = phase1_server_signs(&phase0, id, max_diff_secs)?;
+            let phase0 = Phase0(next("client hung up immediately")?);
+            let phase1 = phase1_server_signs(&phase0, id, max_diff_secs, group)?;
             writer(&phase1.0)?;
-            let phase2 = Phase2(inp.next().context("Missing phase2")??);
-            let _key = phase3_server_verify(&phase0, &phase1, &phase2, id)?;
+            let phase2 = Phase2(next("Missing phase2")?);
+            Some(phase3_server_verify(&phase0, &phase1, &phase2, id)?)
         }
+    };
+    if let (Some(cmd), Some(session)) = (exec, session) {
+        tunnel(&cmd, session, &mut pkt_inp.reader)?;
+    }
+    Ok(())
+}
+
+/// Run cmd and tunnel its stdin/stdout over the encrypted channel on inp and stdout.
+fn tunnel(cmd: &str, session: Session, inp: &mut dyn std::io::Read) -> anyhow::Result<()> {
+    let (send_key, recv_key) = session.channel_keys()?;
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", cmd])
+        .env("THEIR_KEY", session.their_key.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped());
+    if let Some(group) = session.group {
+        command.env("LK_GROUP", format!("[b:{group}]"));
     }
+    let mut child = command.spawn().with_context(|| format!("spawning {cmd}"))?;
+    let mut child_out = child.stdout.take().context("no stdout")?;
+    let sender = std::thread::spawn(move || {
+        encrypt_stream(&send_key, &mut child_out, &mut std::io::stdout().lock())
+    });
+    let mut child_in = child.stdin.take().context("no stdin")?;
+    let received = decrypt_stream(&recv_key, inp, &mut child_in);
+    child_in.flush().ok();
+    drop(child_in);
+    received.context("receiving")?;
+    sender.join().expect("sender panicked").context("sending")?;
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "{cmd} exited with {status}");
     Ok(())
 }
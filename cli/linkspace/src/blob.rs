@@ -0,0 +1,71 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::{io::Read, path::PathBuf};
+
+use linkspace::conventions::blob::{lk_blob_points, lk_blob_read};
+use linkspace_common::{
+    cli::{
+        clap,
+        clap::{Parser, Subcommand},
+        opts::CommonOpts,
+        WriteDestSpec,
+    },
+    prelude::*,
+};
+
+#[derive(Subcommand)]
+/// convention - store data of any size as a tree of points
+pub enum BlobCmd {
+    /// split a file (or stdin) into points and print the blob hash
+    Put(BlobPut),
+    /// write the data of a blob to stdout
+    Get(BlobGet),
+}
+
+#[derive(Parser)]
+pub struct BlobPut {
+    pub domain: DomainExpr,
+    pub group: GroupExpr,
+    /// read from a file instead of stdin
+    pub file: Option<PathBuf>,
+    /// destination of the points. The blob hash is printed to stdout
+    #[arg(short, long, default_value = "db")]
+    pub write: Vec<WriteDestSpec>,
+}
+pub fn blob_put(common: CommonOpts, bp: BlobPut) -> anyhow::Result<()> {
+    let BlobPut {
+        domain,
+        group,
+        file,
+        write,
+    } = bp;
+    let scope = common.eval_scope();
+    let domain = domain.eval(&scope)?;
+    let group = group.eval(&scope)?;
+    let reader: Box<dyn Read> = match file {
+        Some(path) => Box::new(std::fs::File::open(path)?),
+        None => Box::new(std::io::stdin().lock()),
+    };
+    let mut write = common.open(&write)?;
+    let hash = lk_blob_points(domain, group, reader, &mut |pkt| {
+        common.write_multi_dest(&mut write, &pkt, None)?;
+        Ok(())
+    })?;
+    println!("{hash}");
+    Ok(())
+}
+
+#[derive(Parser)]
+pub struct BlobGet {
+    pub hash: HashExpr,
+}
+pub fn blob_get(common: CommonOpts, bg: BlobGet) -> anyhow::Result<()> {
+    let hash = bg.hash.eval(&common.eval_scope())?;
+    let lk = common.runtime()?.into();
+    let mut reader = lk_blob_read(&lk, hash);
+    std::io::copy(&mut reader, &mut std::io::stdout().lock())?;
+    Ok(())
+}
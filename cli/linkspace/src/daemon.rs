@@ -0,0 +1,296 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::{
+    cell::RefCell,
+    ops::ControlFlow,
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+};
+
+use anyhow::Context;
+use linkspace_common::{
+    cli::{clap, clap::Parser, opts::CommonOpts, tracing},
+    pkt_reader::NetPktDecoder,
+    prelude::{lmdb::BTreeEnv, *},
+    protocols::daemon::{read_frame, write_frame, write_match, DaemonStatus, Kind, SOCKET_NAME},
+    runtime::handlers::{PktStreamHandler, StopReason},
+};
+
+use crate::serve_ws::forward_writes;
+
+/**
+Serve the instance to other processes over a unix socket.
+
+A client only needs access to the socket. See linkspace::daemon for the client and linkspace_common::protocols::daemon for the protocol.
+Packets saved through the daemon do not increment their hop.
+**/
+#[derive(Parser)]
+pub struct Daemon {
+    /// the socket path - defaults to LK_DIR/daemon.sock
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+}
+
+pub fn daemon(common: CommonOpts, opts: Daemon) -> anyhow::Result<()> {
+    let env = common.runtime()?.env().clone();
+    let socket = opts.socket.unwrap_or_else(|| env.dir().join(SOCKET_NAME));
+    // a socket left by a daemon that did not exit cleanly blocks bind
+    if socket.exists() && UnixStream::connect(&socket).is_err() {
+        std::fs::remove_file(&socket)?;
+    }
+    let listener =
+        UnixListener::bind(&socket).with_context(|| format!("could not bind {socket:?}"))?;
+    tracing::info!(?socket, "listening");
+    // writing to a client that closed its socket must not end the daemon
+    unsafe { libc::signal(libc::SIGPIPE, libc::SIG_IGN) };
+    let clients = Arc::new(AtomicU32::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!(?e, "accept");
+                continue;
+            }
+        };
+        let (env, clients) = (env.clone(), clients.clone());
+        std::thread::spawn(move || {
+            clients.fetch_add(1, Ordering::Relaxed);
+            if let Err(e) = serve_client(env, stream, &clients) {
+                tracing::warn!(?e, "client dropped");
+            }
+            clients.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+    Ok(())
+}
+
+/// the qid a client is watching again.
+/// Linkspace::watch closes the previous watch of a qid, which must not be reported as stopped.
+type Rewatch = Rc<RefCell<Option<Vec<u8>>>>;
+
+/// writes the matches of a watch to the client
+struct WatchOut {
+    out: Rc<UnixStream>,
+    qid: Vec<u8>,
+    rewatch: Rewatch,
+}
+impl PktStreamHandler for WatchOut {
+    fn handle_pkt(&mut self, pkt: &dyn NetPkt, _: &Linkspace) -> ControlFlow<()> {
+        match write_match(&*self.out, &self.qid, pkt) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                tracing::info!(?e, qid=%AB(&self.qid), "stop watch");
+                ControlFlow::Break(())
+            }
+        }
+    }
+    fn stopped(&mut self, _: BareWatch, _: &Linkspace, reason: StopReason) {
+        // a replaced watch continues under the same qid
+        let replaced = match reason {
+            StopReason::Replaced => true,
+            StopReason::Closed => self.rewatch.borrow().as_ref() == Some(&self.qid),
+            _ => false,
+        };
+        if !replaced {
+            let _ = write_frame(&*self.out, Kind::Stopped, &[&self.qid]);
+        }
+    }
+}
+
+enum Event {
+    Request(std::io::Result<(Kind, Vec<u8>)>),
+    Closed,
+    Written,
+}
+
+fn serve_client(env: BTreeEnv, stream: UnixStream, clients: &AtomicU32) -> anyhow::Result<()> {
+    let rt = Linkspace::new_opt_rt(env, Default::default());
+    let (tx, events) = mpsc::channel();
+    let mut reader = stream.try_clone()?;
+    let requests = tx.clone();
+    std::thread::spawn(move || loop {
+        let event = match read_frame(&mut reader).transpose() {
+            Some(frame) => Event::Request(frame),
+            None => Event::Closed,
+        };
+        let end = !matches!(event, Event::Request(Ok(_)));
+        if requests.send(event).is_err() || end {
+            return;
+        }
+    });
+    let alive = Arc::new(());
+    forward_writes(
+        rt.env().clone(),
+        tx,
+        || Event::Written,
+        Arc::downgrade(&alive),
+    );
+    let out = Rc::new(stream);
+    let rewatch = Rewatch::default();
+    loop {
+        match events.recv()? {
+            Event::Request(frame) => {
+                let (kind, payload) = frame?;
+                match handle(&rt, &out, &rewatch, kind, &payload, clients) {
+                    Ok(reply) => write_frame(&*out, Kind::Ok, &[&reply])?,
+                    Err(e) => write_frame(&*out, Kind::Err, &[format!("{e:#}").as_bytes()])?,
+                }
+            }
+            Event::Closed => return Ok(()),
+            Event::Written => {}
+        }
+        rt.process();
+    }
+}
+
+fn handle(
+    rt: &Linkspace,
+    out: &Rc<UnixStream>,
+    rewatch: &Rewatch,
+    kind: Kind,
+    payload: &[u8],
+    clients: &AtomicU32,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(match kind {
+        Kind::Save => {
+            let pkts = NetPktDecoder {
+                allow_private: true,
+                reader: payload,
+                hop: false,
+                skip_hash: false,
+            };
+            let pkts: Vec<NetPktBox> = pkts.try_collect()?;
+            let range = rt
+                .env()
+                .save_dyn_iter(pkts.iter().map(|p| &**p as &dyn NetPkt))?;
+            ((range.end - range.start) as u32).to_be_bytes().to_vec()
+        }
+        Kind::Get => {
+            let query = Query::from_ab_frames(payload)?;
+            let mode = query.get_mode()?;
+            let (mut i, mut sent) = (0, 0u32);
+            let reader = rt.get_reader();
+            for pkt in reader.query(mode, &query.predicates, &mut i)? {
+                write_frame(&**out, Kind::Pkt, &[&pkt.byte_segments().to_bytes()])?;
+                sent += 1;
+            }
+            sent.to_be_bytes().to_vec()
+        }
+        Kind::Watch => {
+            let query = Query::from_ab_frames(payload)?;
+            let qid = query
+                .qid()?
+                .flatten()
+                .context("watch requires a ':qid:..' option")?
+                .to_vec();
+            *rewatch.borrow_mut() = Some(qid.clone());
+            let onmatch = WatchOut {
+                out: out.clone(),
+                qid,
+                rewatch: rewatch.clone(),
+            };
+            let span = debug_span!("daemon watch");
+            let watch = rt.watch_query(&query, onmatch, span);
+            *rewatch.borrow_mut() = None;
+            watch?.to_be_bytes().to_vec()
+        }
+        Kind::Stop => {
+            let (range, qid) = payload.split_first().context("missing range byte")?;
+            if *range != 0 {
+                rt.close_range(qid)
+            } else {
+                rt.close(qid)
+            }
+            vec![]
+        }
+        Kind::Status => DaemonStatus {
+            head: rt.process(),
+            clients: clients.load(Ordering::Relaxed),
+            watches: rt.dbg_watches().entries().len() as u32,
+            dir: rt.env().dir().to_string_lossy().into_owned(),
+        }
+        .to_bytes(),
+        e => anyhow::bail!("{e:?} is not a request"),
+    })
+}
+
+#[test]
+fn round_trip() -> anyhow::Result<()> {
+    use linkspace::{daemon::*, lk_query, lk_query_push, Q};
+    use std::time::Duration;
+    let dir = std::env::temp_dir().join(format!("lk-daemon-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let env = BTreeEnv::open(dir.clone(), true)?;
+    let socket = dir.join(SOCKET_NAME);
+    let listener = UnixListener::bind(&socket)?;
+    let server_env = env.clone();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept()?;
+        serve_client(server_env, stream, &AtomicU32::new(1))
+    });
+
+    let point = |data: &[u8]| {
+        linkpoint(
+            PUBLIC,
+            ab(b"test"),
+            &rspace_buf(&[b"a"]),
+            &[],
+            data,
+            now(),
+            (),
+        )
+        .as_netbox()
+    };
+    let q = lk_query_push(lk_query(&Q), "domain", "=", &*ab(b"test"))?;
+    let q = lk_query_push(q, "group", "=", &*PUBLIC)?;
+    let q = lk_query_push(q, "", "qid", b"rt")?;
+    let timeout = Some(Duration::from_secs(5));
+    let next_match = |d: &mut LkDaemon| -> anyhow::Result<LkHash> {
+        match lk_daemon_next(d, timeout)? {
+            Some(DaemonEvent::Match { qid, pkt }) if qid == b"rt" => Ok(pkt.hash()),
+            e => anyhow::bail!("{e:?}"),
+        }
+    };
+
+    let mut d = lk_daemon_connect(&socket)?;
+    assert_eq!(lk_daemon_watch(&mut d, &q)?, 0);
+    // watching the qid again replaces the watch without a Stopped event
+    assert_eq!(lk_daemon_watch(&mut d, &q)?, 0);
+    let saved = point(b"saved");
+    assert_eq!(lk_daemon_save(&mut d, &[&saved])?, 1);
+    assert_eq!(next_match(&mut d)?, saved.hash());
+
+    // a write by another process wakes the daemon without a request
+    let direct = point(b"direct");
+    env.save_dyn_one(&direct)?;
+    assert_eq!(next_match(&mut d)?, direct.hash());
+
+    let mut found = vec![];
+    let q = lk_query_push(q, "", "mode", b"log-asc")?;
+    lk_daemon_get(&mut d, &q, &mut |p| {
+        found.push(p.hash());
+        false
+    })?;
+    assert_eq!(found, [saved.hash(), direct.hash()]);
+
+    lk_daemon_stop(&mut d, b"rt", false)?;
+    assert!(matches!(
+        lk_daemon_next(&mut d, timeout)?,
+        Some(DaemonEvent::Stopped { qid }) if qid == b"rt"
+    ));
+    env.save_dyn_one(&point(b"after stop"))?;
+    assert!(lk_daemon_next(&mut d, Some(Duration::from_millis(100)))?.is_none());
+
+    drop(d);
+    server.join().unwrap()?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -42,7 +42,10 @@ use point::{GenPointOpts, PointOpts};
 use tracing_subscriber::EnvFilter;
 use watch::{CLIQuery, DGPDWatchCLIOpts};
 
+pub mod blob;
 pub mod collect;
+#[cfg(target_family = "unix")]
+pub mod daemon;
 pub mod datapoint;
 pub mod eval;
 pub mod filter;
@@ -50,8 +53,10 @@ pub mod get_links;
 pub mod multi_watch;
 pub mod pktf;
 pub mod point;
+pub mod quota;
 pub mod rewrite;
 pub mod save;
+pub mod serve_ws;
 pub mod status;
 pub mod watch;
 
@@ -237,12 +242,20 @@ enum Command {
     },
     /// runtime - read a stream of queries
     MultiWatch(multi_watch::MultiWatch),
+    /// runtime - relay packets between websocket clients and the database
+    ServeWs(serve_ws::ServeWs),
+    /// runtime - serve the instance to other processes over a unix socket
+    #[cfg(target_family = "unix")]
+    Daemon(daemon::Daemon),
     /// convention - generate / print a signing key
     Key(keys::KeyGenOpts),
     /// convention - create a pull request
     Pull {
         #[arg(short, long, default_value = "db")]
         write: Vec<WriteDestSpec>,
+        /// create the empty pull point that closes a previous pull with the same qid
+        #[arg(long)]
+        close: bool,
         #[command(flatten)]
         watch: DGPDWatchCLIOpts,
     },
@@ -251,6 +264,14 @@ enum Command {
         #[command(subcommand)]
         cmd: status::StatusCmd,
     },
+    Blob {
+        #[command(subcommand)]
+        cmd: blob::BlobCmd,
+    },
+    Quota {
+        #[command(subcommand)]
+        cmd: quota::QuotaCmd,
+    },
 
     /// rewrite packets
     Rewrite(rewrite::Rewrite),
@@ -469,6 +490,9 @@ fn run(command: Command, mut common: CommonOpts) -> anyhow::Result<()> {
         }
         Command::Eval(eval_opts) => eval::eval_cmd(common, eval_opts)?,
         Command::MultiWatch(mv) => multi_watch::multi_watch(common, mv)?,
+        Command::ServeWs(opts) => serve_ws::serve_ws(common, opts)?,
+        #[cfg(target_family = "unix")]
+        Command::Daemon(opts) => daemon::daemon(common, opts)?,
         Command::Route { field_mut, pkt_in } => {
             let muth = NetHeaderMutate::from_lst(&field_mut, &common.eval_scope())?;
             common.enable_private_group();
@@ -501,12 +525,21 @@ fn run(command: Command, mut common: CommonOpts) -> anyhow::Result<()> {
             }
             println!("exec not supported")
         }
-        Command::Pull { write, mut watch } => {
+        Command::Pull {
+            write,
+            close,
+            mut watch,
+        } => {
+            use linkspace::conventions::pull::{lk_pull_close_point, lk_pull_point};
             let scope = common.eval_scope();
             watch.watch_opts.aliases.watch = true;
             ensure!(watch.dgpd.is_some(), "DGSD required for pull request");
-            let query = watch.into_query(&scope)?;
-            let req = linkspace::conventions::pull::lk_pull_point(&query.into())?;
+            let query = watch.into_query(&scope)?.into();
+            let req = if close {
+                lk_pull_close_point(&query)?
+            } else {
+                lk_pull_point(&query)?
+            };
             std::mem::drop(scope);
             *common.mut_write_private() = Some(true);
             let mut write = common.open(&write)?;
@@ -518,6 +551,21 @@ fn run(command: Command, mut common: CommonOpts) -> anyhow::Result<()> {
         Command::Status {
             cmd: status::StatusCmd::Set(w),
         } => status::status_set(common, w)?,
+        Command::Status {
+            cmd: status::StatusCmd::History(h),
+        } => status::status_history(common, h)?,
+        Command::Blob {
+            cmd: blob::BlobCmd::Put(p),
+        } => blob::blob_put(common, p)?,
+        Command::Blob {
+            cmd: blob::BlobCmd::Get(g),
+        } => blob::blob_get(common, g)?,
+        Command::Quota {
+            cmd: quota::QuotaCmd::Set(s),
+        } => quota::quota_set(common, s)?,
+        Command::Quota {
+            cmd: quota::QuotaCmd::List,
+        } => quota::quota_list(common)?,
         Command::Init => {
             common.linkspace.init = true;
             let lk = common.runtime()?.into();
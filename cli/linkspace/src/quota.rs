@@ -0,0 +1,99 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use linkspace_common::{
+    cli::{
+        clap,
+        clap::{Parser, Subcommand},
+        opts::CommonOpts,
+    },
+    core::env::quota::{Quota, QuotaKind},
+    prelude::*,
+};
+
+#[derive(Subcommand)]
+/**
+runtime - limit the packets saved per pubkey, group, or domain
+
+Only packets received from elsewhere (hop > 0) are counted.
+A packet exceeding a quota is not saved and is reported by e.g. `lk save`.
+See linkspace_common::core::env::quota
+**/
+pub enum QuotaCmd {
+    /// set (or with neither limit remove) a quota
+    Set(QuotaSet),
+    /// print all quotas
+    List,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Kind {
+    Pubkey,
+    Group,
+    Domain,
+}
+impl From<Kind> for QuotaKind {
+    fn from(k: Kind) -> Self {
+        match k {
+            Kind::Pubkey => QuotaKind::PubKey,
+            Kind::Group => QuotaKind::Group,
+            Kind::Domain => QuotaKind::Domain,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct QuotaSet {
+    pub kind: Kind,
+    /// e.g. [#:pub] - omit to set the default of every id of the kind without its own quota
+    pub id: Option<String>,
+    /// 0 rejects every packet
+    #[arg(long)]
+    pub pkts_per_sec: Option<u32>,
+    /// 0 rejects every packet
+    #[arg(long)]
+    pub bytes_per_day: Option<u64>,
+}
+
+pub fn quota_set(common: CommonOpts, qs: QuotaSet) -> anyhow::Result<()> {
+    let QuotaSet {
+        kind,
+        id,
+        pkts_per_sec,
+        bytes_per_day,
+    } = qs;
+    let scope = common.eval_scope();
+    let id = match id {
+        None => vec![],
+        Some(id) => match kind {
+            Kind::Pubkey => id.parse::<PubKeyExpr>()?.eval(&scope)?.0.to_vec(),
+            Kind::Group => id.parse::<GroupExpr>()?.eval(&scope)?.0.to_vec(),
+            Kind::Domain => id.parse::<DomainExpr>()?.eval(&scope)?.0.to_vec(),
+        },
+    };
+    let quota = Quota {
+        pkts_per_sec,
+        bytes_per_day,
+    };
+    common.runtime()?.env().quota_set(kind.into(), &id, quota)?;
+    Ok(())
+}
+
+pub fn quota_list(common: CommonOpts) -> anyhow::Result<()> {
+    for (kind, id, quota) in common.runtime()?.env().quota_list()? {
+        let id = match kind {
+            _ if id.is_empty() => "default".to_string(),
+            QuotaKind::Domain => AB(id.as_slice()).to_string(),
+            _ => B64(id.as_slice()).to_string(),
+        };
+        let limit = |v: Option<String>| v.unwrap_or_else(|| "-".into());
+        println!(
+            "{kind:?} {id} pkts_per_sec={} bytes_per_day={}",
+            limit(quota.pkts_per_sec.map(|v| v.to_string())),
+            limit(quota.bytes_per_day.map(|v| v.to_string()))
+        );
+    }
+    Ok(())
+}
@@ -19,6 +19,9 @@ pub struct SaveForward {
     new: Vec<WriteDestSpec>,
     #[arg(long, default_value = "null")]
     old: Vec<WriteDestSpec>,
+    /// packets rejected by a quota (see 'lk quota'). Each rejection is also logged as a warning
+    #[arg(long, default_value = "null")]
+    rejected: Vec<WriteDestSpec>,
     /// add stdout to both --old and --dest
     #[arg(short, long)]
     forward_stdout: bool,
@@ -31,6 +34,7 @@ pub fn save(opts: SaveForward, mut common: CommonOpts) -> anyhow::Result<()> {
     let SaveForward {
         mut new,
         mut old,
+        rejected,
         forward_stdout,
         pkt_in,
     } = opts;
@@ -43,6 +47,7 @@ pub fn save(opts: SaveForward, mut common: CommonOpts) -> anyhow::Result<()> {
     let inp = common.inp_reader(&pkt_in)?;
     let mut new = common.open(&new)?;
     let mut old = common.open(&old)?;
+    let mut rejected = common.open(&rejected)?;
     ensure!(
         new.iter().any(|v| matches!(v.out, Out::Db)),
         "currently not possible to skip saving new packets add a --new-only db"
@@ -54,10 +59,17 @@ pub fn save(opts: SaveForward, mut common: CommonOpts) -> anyhow::Result<()> {
         let pkt = pkt?;
         // TODO: It might be better to spin a thread that will batch writes in a single transaction.
         // Depends on the speed of writing vs checking
-        let is_new = env.save_ptr_one(&pkt)?.is_written();
-        let dest = if is_new { &mut new } else { &mut old };
+        let state = env.save_ptr_one(&pkt)?;
+        let dest = match state {
+            SaveState::Written => &mut new,
+            SaveState::Rejected => {
+                tracing::warn!(hash=%pkt.hash(), "rejected by quota");
+                &mut rejected
+            }
+            _ => &mut old,
+        };
         common.write_multi_dest(dest, &pkt, None)?;
-        tracing::debug!(hash=?pkt.hash(),is_new,"Flush OK");
+        tracing::debug!(hash=?pkt.hash(),%state,"Flush OK");
     }
     Ok(())
 }
@@ -0,0 +1,362 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc, Arc, Weak},
+    time::{Duration, Instant},
+};
+
+use linkspace_common::{
+    cli::{clap, clap::Parser, opts::CommonOpts, tracing},
+    pkt_reader::NetPktDecoder,
+    prelude::{lmdb::BTreeEnv, *},
+};
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    protocol::Role,
+    Message, WebSocket,
+};
+
+use crate::watch::CLIQuery;
+
+// how long a forward_writes thread can outlive its client, and the longest a write racing its wait goes unnoticed
+const LINGER: Duration = Duration::from_secs(1);
+
+/// Send msg() every time the log head of env moves, until tx disconnects or alive is dropped.
+pub fn forward_writes<T: Send + 'static>(
+    env: BTreeEnv,
+    tx: mpsc::Sender<T>,
+    msg: impl Fn() -> T + Send + 'static,
+    alive: Weak<()>,
+) {
+    std::thread::spawn(move || {
+        // starts with a msg - writes can land before the thread runs
+        let mut seen = None;
+        while alive.strong_count() > 0 {
+            let head = env.0.log_head.val();
+            if seen == Some(head) {
+                env.next_deadline(Some(Instant::now() + LINGER));
+                continue;
+            }
+            seen = Some(head);
+            if tx.send(msg()).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+enum Event {
+    Read(tungstenite::Result<Message>),
+    Written,
+}
+
+/// The read side of a client. Control frames are answered by the write side, so writes are dropped.
+struct ReadHalf(TcpStream);
+impl Read for ReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+impl Write for ReadHalf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The reader thread keeps a clone of the socket open. Shutdown ends it and tells the client we are gone.
+struct Shutdown(TcpStream);
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/**
+Accept websocket clients and relay packets between them and the database.
+
+Every binary message a client sends is read as a sequence of packets.
+Packets matching the query are saved, others are ignored.
+All packets matching the query (including those already saved) are sent back as binary messages, one packet per message.
+
+Private packets are ignored and never sent unless --private (or --private-read / --private-write) is set.
+
+Without external tools a browser can talk to the local instance with e.g.
+
+lk serve-ws --path-space -- "group:=:[#:pub]" "domain:=:chat"
+
+and a client connecting to ws://127.0.0.1:9090/room/1 receives and pushes packets in spacename /room/1.
+
+A browser sends the Origin of the page that opens the connection.
+Only pages served from the listen address itself or an --origin are accepted, so other websites can't read and write the local instance.
+**/
+#[derive(Parser)]
+pub struct ServeWs {
+    /// address to accept websocket clients on
+    #[arg(long, default_value = "127.0.0.1:9090")]
+    pub listen: SocketAddr,
+    /// add the request path as a 'spacename:=:PATH' predicate for that client
+    #[arg(long)]
+    pub path_space: bool,
+    /// also accept browser clients from this origin (e.g. http://localhost:8080)
+    #[arg(long)]
+    pub origin: Vec<String>,
+    #[command(flatten)]
+    pub query: CLIQuery,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Rules {
+    /// save private packets pushed by a client
+    pub read_private: bool,
+    /// send private packets to a client
+    pub write_private: bool,
+    pub path_space: bool,
+    /// accepted Origin headers besides the listen address
+    pub origins: Arc<[String]>,
+}
+
+/// A client without an Origin header is not a browser.
+/// Otherwise the origin must be in origins or be the Host it connects to.
+pub fn allow_origin(origins: &[String], req: &Request) -> bool {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+    let Some(origin) = header("Origin") else {
+        return true;
+    };
+    if origins.iter().any(|o| o.eq_ignore_ascii_case(origin)) {
+        return true;
+    }
+    let authority = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    header("Host").is_some_and(|host| host.eq_ignore_ascii_case(authority))
+}
+
+pub fn serve_ws(common: CommonOpts, opts: ServeWs) -> anyhow::Result<()> {
+    let ServeWs {
+        listen,
+        path_space,
+        origin,
+        query,
+    } = opts;
+    let Some(query) = query.into_query(&common)? else {
+        return Ok(());
+    };
+    let rules = Rules {
+        read_private: common.read_private().unwrap_or(false),
+        write_private: common.write_private().unwrap_or(false),
+        path_space,
+        origins: origin.into(),
+    };
+    let env = common.runtime()?.env().clone();
+    let listener = TcpListener::bind(listen)?;
+    tracing::info!(addr=?listener.local_addr()?, "listening");
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!(?e, "accept");
+                continue;
+            }
+        };
+        let (env, query, rules) = (env.clone(), query.clone(), rules.clone());
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            match serve_client(env, query, rules, stream) {
+                Ok(()) => tracing::info!(?peer, "client closed"),
+                Err(e) => tracing::warn!(?peer, ?e, "client dropped"),
+            }
+        });
+    }
+    Ok(())
+}
+
+/// handle a single client until it disconnects.
+pub fn serve_client(
+    env: BTreeEnv,
+    mut query: Query,
+    rules: Rules,
+    stream: TcpStream,
+) -> anyhow::Result<()> {
+    let mut path = String::new();
+    let mut ws = tungstenite::accept_hdr(stream, |req: &Request, resp: Response| {
+        if !allow_origin(&rules.origins, req) {
+            let mut err = ErrorResponse::new(Some("origin not allowed".into()));
+            *err.status_mut() = StatusCode::FORBIDDEN;
+            return Err(err);
+        }
+        path = req.uri().path().to_string();
+        Ok(resp)
+    })
+    .map_err(|e| anyhow::anyhow!("websocket handshake: {e}"))?;
+    let span = debug_span!("ws", %path);
+    if rules.path_space {
+        let space = SpaceBuf::try_from_iter(path.split('/').filter(|c| !c.is_empty()))?;
+        query.predicates.add_predicate(&Predicate::from_slice(
+            FieldEnum::SpaceNameF,
+            TestOp::Equal,
+            space.as_bytes(),
+        ))?;
+    }
+    let mut filter = WatchEntry::new(
+        Default::default(),
+        query.clone(),
+        0,
+        (),
+        debug_span!(parent: &span, "push"),
+    )?;
+
+    let rt = Linkspace::new_opt_rt(env, Default::default());
+    let (tx, outbox) = mpsc::channel::<Box<[u8]>>();
+    query.add_option("qid", &[b"serve-ws"]);
+    let write_private = rules.write_private;
+    rt.watch_query(
+        &query,
+        move |pkt: &dyn NetPkt, _: &Linkspace| -> anyhow::Result<()> {
+            if !write_private && pkt.check_private().is_err() {
+                return Ok(());
+            }
+            tx.send(pkt.byte_segments().to_bytes())?;
+            Ok(())
+        },
+        debug_span!(parent: &span, "watch"),
+    )?;
+
+    let (events, recv) = mpsc::channel();
+    let mut reader =
+        WebSocket::from_raw_socket(ReadHalf(ws.get_ref().try_clone()?), Role::Server, None);
+    let _shutdown = Shutdown(ws.get_ref().try_clone()?);
+    let tx = events.clone();
+    std::thread::spawn(move || loop {
+        let msg = reader.read();
+        let end = msg.is_err();
+        if tx.send(Event::Read(msg)).is_err() || end {
+            return;
+        }
+    });
+    let alive = Arc::new(());
+    forward_writes(
+        rt.env().clone(),
+        events,
+        || Event::Written,
+        Arc::downgrade(&alive),
+    );
+    loop {
+        while let Ok(bytes) = outbox.try_recv() {
+            ws.send(Message::Binary(bytes.into()))?;
+        }
+        match recv.recv()? {
+            Event::Read(Ok(Message::Binary(bytes))) => {
+                let pkts = NetPktDecoder {
+                    allow_private: true,
+                    reader: bytes.as_slice(),
+                    hop: true,
+                    skip_hash: false,
+                };
+                for pkt in pkts {
+                    let pkt = pkt?;
+                    if !rules.read_private && pkt.check_private().is_err() {
+                        tracing::info!(parent: &span, pkt=%PktFmtDebug(&pkt), "ignored private pkt");
+                        continue;
+                    }
+                    let (ok, _) = filter.test(RecvPktPtr {
+                        recv: now(),
+                        pkt: &pkt,
+                    });
+                    if !ok {
+                        tracing::info!(parent: &span, pkt=%PktFmtDebug(&pkt), "ignored pkt outside query");
+                        continue;
+                    }
+                    rt.env().save_ptr_one(&pkt)?;
+                }
+            }
+            Event::Read(Ok(Message::Ping(data))) => ws.send(Message::Pong(data))?,
+            Event::Read(Ok(Message::Close(_))) => {
+                // the close reply; the client then closes the connection
+                let _ = ws.close(None);
+                let _ = ws.flush();
+                return Ok(());
+            }
+            Event::Read(Ok(_)) | Event::Written => {}
+            Event::Read(Err(tungstenite::Error::ConnectionClosed)) => return Ok(()),
+            Event::Read(Err(e)) => return Err(e.into()),
+        }
+        rt.process();
+    }
+}
+
+#[test]
+fn loopback() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("lk-serve-ws-{}", std::process::id()));
+    let env = BTreeEnv::open(dir.clone(), true)?;
+    let mut query = Query::default();
+    query.add_option("mode", &[b"log-asc"]);
+    query.predicates.add_predicate(&Predicate::from_slice(
+        FieldEnum::DomainF,
+        TestOp::Equal,
+        &*ab(b"test"),
+    ))?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let rules = Rules {
+        path_space: true,
+        origins: Arc::new(["http://localhost:8080".to_string()]),
+        ..Default::default()
+    };
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept()?;
+        let cross_origin = serve_client(env.clone(), query.clone(), rules.clone(), stream);
+        anyhow::ensure!(cross_origin.is_err(), "accepted a cross origin client");
+        let (stream, _) = listener.accept()?;
+        serve_client(env, query, rules, stream)
+    });
+
+    use tungstenite::client::IntoClientRequest;
+    let with_origin = |origin: &str| -> anyhow::Result<Request> {
+        let mut req = format!("ws://{addr}/room").into_client_request()?;
+        req.headers_mut().insert("Origin", origin.parse()?);
+        Ok(req)
+    };
+    let origins = ["http://localhost:8080".to_string()];
+    assert!(allow_origin(
+        &origins,
+        &with_origin(&format!("http://{addr}"))?
+    ));
+    assert!(allow_origin(
+        &[],
+        &format!("ws://{addr}/room").into_client_request()?
+    ));
+    assert!(!allow_origin(&[], &with_origin("http://localhost:8080")?));
+    assert!(tungstenite::connect(with_origin("http://evil.example")?).is_err());
+    let (mut client, _) = tungstenite::connect(with_origin("http://localhost:8080")?)?;
+    let point = |domain: &[u8], space: &[&[u8]]| {
+        linkpoint(PUBLIC, ab(domain), &rspace_buf(space), &[], &[], now(), ()).as_netbox()
+    };
+    let ok = point(b"test", &[b"room"]);
+    let mut batch = point(b"test", &[b"other"])
+        .byte_segments()
+        .to_bytes()
+        .to_vec();
+    batch.extend_from_slice(&point(b"other", &[b"room"]).byte_segments().to_bytes());
+    client.send(Message::Binary(batch))?;
+    client.send(Message::Binary(ok.byte_segments().to_bytes().into()))?;
+    // only the matching packet is saved and sent back
+    match client.read()? {
+        Message::Binary(b) => {
+            let pkt = NetPktDecoder::new(b.as_slice()).next().unwrap()?;
+            assert_eq!(pkt.hash(), ok.hash())
+        }
+        e => panic!("{e:?}"),
+    }
+    client.close(None)?;
+    while client.read().is_ok() {}
+    server.join().unwrap()?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
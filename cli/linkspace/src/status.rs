@@ -27,6 +27,8 @@ pub enum StatusCmd {
     Watch(StatusWatch),
     /// reply/set a status watch requests
     Set(StatusSet),
+    /// output the replies of an instance from newest to oldest. Errors if there are none
+    History(StatusHistory),
 }
 
 #[derive(Parser, Debug)]
@@ -99,6 +101,9 @@ pub struct StatusWatch {
     /// wait for this duration (since last request) before returning an error
     #[arg(short, long, default_value = "5s")]
     timeout: DurationStr,
+    /// also accept existing replies up to this age (e.g. to check if a process ever replied)
+    #[arg(long)]
+    max_age: Option<DurationStr>,
     #[arg(short, long, default_value = "stdout")]
     write: Vec<WriteDestSpec>,
     /// Output multiple replies (until last_req+duration)
@@ -115,6 +120,7 @@ pub fn status_watch(mut common: CommonOpts, ps: StatusWatch) -> anyhow::Result<(
     let StatusWatch {
         args,
         timeout,
+        max_age,
         write,
         print_query,
         write_request,
@@ -132,7 +138,10 @@ pub fn status_watch(mut common: CommonOpts, ps: StatusWatch) -> anyhow::Result<(
         qid: b"<lk set status>",
     };
 
-    let query: Query = lk_status_overwatch(status, timeout.stamp()).unwrap().into();
+    let max_age = max_age.map(|age| age.stamp());
+    let query: Query = lk_status_overwatch(status, lk_status_max_age(timeout.stamp(), max_age))
+        .unwrap()
+        .into();
     if print_query {
         println!("{}", query);
         return Ok(());
@@ -155,6 +164,7 @@ pub fn status_watch(mut common: CommonOpts, ps: StatusWatch) -> anyhow::Result<(
         &lk,
         status,
         timeout.stamp(),
+        max_age,
         try_cb(move |pkt, lk| -> ControlFlow<()> {
             if pkt.get_links().is_empty() || pkt.data().is_empty() {
                 panic!()
@@ -173,3 +183,40 @@ pub fn status_watch(mut common: CommonOpts, ps: StatusWatch) -> anyhow::Result<(
     anyhow::ensure!(ok.get(), "no resposne after {:?}", timeout);
     Ok(())
 }
+
+#[derive(Parser, Debug)]
+pub struct StatusHistory {
+    #[command(flatten)]
+    args: StatusArgs,
+    #[arg(short, long, default_value = "stdout")]
+    write: Vec<WriteDestSpec>,
+    /// stop after this many replies
+    #[arg(long)]
+    max: Option<u32>,
+}
+pub fn status_history(mut common: CommonOpts, sh: StatusHistory) -> anyhow::Result<()> {
+    let StatusHistory { args, write, max } = sh;
+    *common.mut_write_private() = Some(true);
+    let scope = common.eval_scope();
+    let (domain, group, objtype, instance) = args.eval(&scope)?;
+    use linkspace::conventions::status::*;
+    let status = LkStatus {
+        domain,
+        group,
+        objtype: &objtype,
+        instance: instance.as_deref(),
+        qid: &[],
+    };
+    let lk: linkspace::Linkspace = common.runtime()?.into();
+    let mut out = common.open(&write)?;
+    let mut result = Ok(());
+    let mut count = 0;
+    lk_status_history(&lk, status, &mut |pkt| {
+        count += 1;
+        result = common.write_multi_dest(&mut out, pkt, None);
+        result.is_err() || max == Some(count)
+    })?;
+    result?;
+    anyhow::ensure!(count > 0, "no status replies");
+    Ok(())
+}
@@ -59,13 +59,18 @@ pub struct PrintABE {
     /// print in ascii-byte-text format (ABE without '[..]' expressions)
     #[arg(long, alias = "text", conflicts_with = "print_expr")]
     pub print_text: bool,
+    /// print counters as decimal aliases (e.g. data_size<1000) - not understood by older versions
+    #[arg(long, alias = "aliases", conflicts_with_all = ["print_expr", "print_text"])]
+    pub print_alias: bool,
 }
 impl PrintABE {
     pub fn do_print(&self) -> bool {
-        self.print_expr || self.print_text
+        self.print_expr || self.print_text || self.print_alias
     }
     pub fn print_query(&self, query: &Query, out: &mut dyn std::io::Write) -> std::io::Result<()> {
-        if self.print_expr {
+        if self.print_alias {
+            writeln!(out, "{}", query.to_alias_str())?
+        } else if self.print_expr {
             writeln!(out, "{}", query.to_str(true))?
         } else if self.print_text {
             writeln!(out, "{}", query.to_str(false))?
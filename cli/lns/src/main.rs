@@ -12,8 +12,12 @@ use linkspace_common::{
     identity,
     prelude::*,
     protocols::lns::{
-        self, claim::Claim, lnstag, name::NameExpr, public_claim::Issue, GROUP_TAG,
-        PUBKEY_AUTH_TAG, PUBKEY_TAG,
+        self,
+        claim::Claim,
+        lnstag,
+        name::{NameExpr, NameType},
+        public_claim::Issue,
+        GROUP_TAG, PUBKEY_AUTH_TAG, PUBKEY_TAG, THRESHOLD_PREFIX, VOTE_TAG,
     },
 };
 use tracing_subscriber::EnvFilter;
@@ -44,6 +48,10 @@ pub enum Cmd {
     LsGroup {
         group: Option<GroupExpr>,
     },
+    /// follow the rotation points of a key (see lk key rotate) and print each successor up to the current key
+    Rotations {
+        pubkey: PubKeyExpr,
+    },
     Ls {
         name: NameExpr,
     },
@@ -55,6 +63,30 @@ pub enum Cmd {
         #[arg(long, default_value = "stdout")]
         write: Vec<WriteDestSpec>,
     },
+    /// list which authorities voted for a claim of a public name and which are missing
+    Votes {
+        name: NameExpr,
+    },
+    /// replace the builtin root claim with a root claim and its votes - e.g. for an isolated LNS tree
+    SetRoots {
+        /// the root claim hash - it is expected to be at lns:{#:pub}:/claims
+        #[arg(required_unless_present("reset"))]
+        claim: Option<HashExpr>,
+        /// hash of a vote by a root authority for the claim
+        #[arg(long)]
+        vote: Vec<HashExpr>,
+        /// restore the builtin roots
+        #[arg(long, conflicts_with_all(["claim", "vote"]))]
+        reset: bool,
+        #[arg(long, default_value = "db")]
+        write: Vec<WriteDestSpec>,
+    },
+    /// check all known public claims against the active roots
+    Revalidate {
+        /// also list claims that are not live
+        #[arg(long)]
+        all: bool,
+    },
     CreateClaim {
         /// name of claim
         name: NameExpr,
@@ -82,6 +114,9 @@ pub enum Cmd {
         until: StampExpr,
         #[arg(long)]
         allow_empty: bool,
+        /// number of authority votes required before a sub claim is live
+        #[arg(long)]
+        threshold: Option<usize>,
 
         #[arg(long, default_value = "stdout")]
         write: Vec<WriteDestSpec>,
@@ -135,7 +170,17 @@ fn main() -> anyhow::Result<()> {
             let mut signatures = common.open(&write_signatures)?;
 
             let (is_ok, liveclaim) = match name {
-                None => (true, lns::public_claim::root_claim()),
+                None => {
+                    let lk = common.runtime()?;
+                    let mut issue_handler = |issue: Issue| {
+                        eprintln!("{:?}", issue);
+                        Ok(())
+                    };
+                    (
+                        true,
+                        lns::public_claim::active_root_claim(&lk.get_reader(), &mut issue_handler)?,
+                    )
+                }
                 Some(name) => {
                     let name = name.eval(&common.eval_scope())?;
                     let mut issue_handler = |issue: Issue| {
@@ -168,6 +213,72 @@ fn main() -> anyhow::Result<()> {
                 bail!("incomplete chain")
             }
         }
+        Cmd::Votes { name } => {
+            let lk = common.runtime()?;
+            let name = name.eval(&common.eval_scope())?;
+            ensure!(
+                name.name_type() == NameType::Public,
+                "only public names are voted on"
+            );
+            let parent = lns::lookup_authority_claim(&lk, &name, &mut |_| Ok(()))?
+                .map_err(|_e| anyhow!("only found upto {}", name))?;
+            let sub = name.space().pop().1.context("the root has no votes")?;
+            let live = lns::lookup_claim(&lk, &name)?.map(|c| c.pkt.hash());
+            let votes = lns::public_claim::authority_votes(&lk.get_reader(), &parent, sub)?;
+            let mut issue_handler = |issue: Issue| {
+                eprintln!("{:?}", issue);
+                Ok(())
+            };
+            match lns::public_claim::required_votes(&parent, &mut issue_handler)? {
+                Some(threshold) => {
+                    println!("{name}\t{threshold} of {} votes required", votes.len())
+                }
+                None => println!("{name}\tinvalid threshold - no sub claim is live"),
+            }
+            for (auth, vote) in votes {
+                let voted = vote
+                    .as_ref()
+                    .and_then(|v| v.get_links().first())
+                    .filter(|l| l.tag == VOTE_TAG);
+                match voted {
+                    Some(l) if Some(l.ptr) == live => println!("{auth}\tlive\t{}", l.ptr),
+                    Some(l) => println!("{auth}\tvoted\t{}", l.ptr),
+                    None => println!("{auth}\tmissing"),
+                }
+            }
+        }
+        Cmd::SetRoots {
+            claim,
+            vote,
+            reset,
+            write,
+        } => {
+            let mut write = common.open(&write)?;
+            let scope = common.eval_scope();
+            let pkt = if reset {
+                lns::public_claim::root_set_pkt(None, None)
+            } else {
+                let claim = claim.context("missing claim")?.eval(&scope)?;
+                let votes: Vec<LkHash> = vote.iter().map(|v| v.eval(&scope)).try_collect()?;
+                let pkt = lns::public_claim::root_set_pkt(Some((claim, &votes)), None);
+                // check it before it is saved - lookups fallback to the builtin roots on a bad root-set
+                lns::public_claim::read_root_set(&common.runtime()?.get_reader(), &pkt)?;
+                pkt
+            };
+            common.write_multi_dest(&mut write, &pkt, None)?;
+        }
+        Cmd::Revalidate { all } => {
+            let lk = common.runtime()?;
+            let reader = lk.get_reader();
+            for c_ok in lns::utils::revalidate_public_claims(&lk, &reader) {
+                match c_ok {
+                    Result::Ok((claim, true)) => print!("live\t{claim}"),
+                    Result::Ok((claim, false)) if all => print!("dead\t{claim}"),
+                    Result::Ok(_) => {}
+                    Err(e) => eprintln!("{e:#?}"),
+                }
+            }
+        }
         Cmd::CreateClaim {
             name,
             group,
@@ -179,6 +290,7 @@ fn main() -> anyhow::Result<()> {
             no_auth,
             enckey,
             copy_from,
+            threshold,
         } => {
             let mut write = common.open(&write)?;
             let scope = common.eval_scope();
@@ -222,7 +334,13 @@ fn main() -> anyhow::Result<()> {
                 ensure!(allow_empty, "empty claim");
                 links.push(Link::DEFAULT);
             }
+            if let Some(m) = threshold {
+                use std::io::Write;
+                data.extend_from_slice(THRESHOLD_PREFIX);
+                writeln!(&mut data, "{m}")?;
+            }
             let claim = Claim::new(name, until, &mut links, &data)?;
+            claim.threshold()?;
             common.write_multi_dest(&mut write, &claim.pkt, None)?;
         }
         Cmd::Ls { name } => {
@@ -242,6 +360,13 @@ fn main() -> anyhow::Result<()> {
         }
         Cmd::LsGroup { group } => ls_tag(&common, &GROUP_TAG, group)?,
         Cmd::LsPubkey { pubkey } => ls_tag(&common, &PUBKEY_TAG, pubkey)?,
+        Cmd::Rotations { pubkey } => {
+            let pubkey = pubkey.eval(&common.eval_scope())?;
+            let lk = common.runtime()?;
+            for key in lns::claim::rotation_chain(&lk.get_reader(), pubkey)? {
+                println!("{key}")
+            }
+        }
     };
     Ok(())
 }
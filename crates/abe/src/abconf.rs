@@ -37,6 +37,10 @@ impl ABConf {
     pub fn push(&mut self, entry: ABList) {
         self.0.push(entry)
     }
+    /// the entries in the order they were pushed
+    pub fn iter(&self) -> impl Iterator<Item = &ABList> {
+        self.0.iter()
+    }
     pub fn get(&self, b: &[u8]) -> Option<Result<&ABList, &ABList>> {
         self.get_checked(&[b]).next()
     }
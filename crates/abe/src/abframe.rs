@@ -0,0 +1,200 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+'#ab' - a length-delimited binary format for abe.
+
+abtxt has to escape every byte outside 0x20..0x7e. A frame holds bytes as-is.
+A frame starts with [AB_MAGIC] followed by tokens:
+
+- `b` [len:LEB128] [bytes]
+- `:` or `/`
+- `[` and `]` to open and close an expression list
+
+abtxt can not contain a raw '\0' so [crate::parse_abe] can tell them apart.
+
+Frames are created with [ABList::to_ab_frame], [encode_ab_frame], or `[#ab:..]`.
+The '#ab' option for [crate::eval::encode] prints a frame of colon separated bytes as `[#ab:..]`.
+**/
+use crate::{
+    ast::{ASTParseError, Ctr, Expr, ABE},
+    eval::ABList,
+};
+
+pub const AB_MAGIC: &[u8; 4] = b"\0#ab";
+
+pub fn is_ab_frame(bytes: &[u8]) -> bool {
+    bytes.starts_with(AB_MAGIC)
+}
+
+fn write_len(mut len: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+fn read_len(bytes: &mut &[u8]) -> Result<usize, ASTParseError> {
+    let mut len = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (byte, rest) = bytes
+            .split_first()
+            .ok_or(ASTParseError::Frame("truncated length"))?;
+        *bytes = rest;
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+    Err(ASTParseError::Frame("length overflow"))
+}
+fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.push(b'b');
+    write_len(bytes.len(), out);
+    out.extend_from_slice(bytes);
+}
+
+enum Token<'o> {
+    Bytes(&'o [u8]),
+    Ctr(Ctr),
+    Open,
+    Close,
+}
+fn tokens(frame: &[u8]) -> impl Iterator<Item = Result<Token<'_>, ASTParseError>> + '_ {
+    let (mut todo, mut err) = match frame.strip_prefix(AB_MAGIC) {
+        Some(body) => (body, None),
+        None => (
+            &[] as &[u8],
+            Some(ASTParseError::Frame("missing '\\0#ab' prefix")),
+        ),
+    };
+    std::iter::from_fn(move || {
+        if let Some(e) = err.take() {
+            return Some(Err(e));
+        }
+        let (kind, tail) = todo.split_first()?;
+        todo = tail;
+        Some(Ok(match *kind {
+            b':' => Token::Ctr(Ctr::Colon),
+            b'/' => Token::Ctr(Ctr::FSlash),
+            b'[' => Token::Open,
+            b']' => Token::Close,
+            b'b' => {
+                let len = match read_len(&mut todo) {
+                    Ok(len) => len,
+                    Err(e) => return Some(Err(e)),
+                };
+                if todo.len() < len {
+                    return Some(Err(ASTParseError::Frame("truncated bytes")));
+                }
+                let (bytes, tail) = todo.split_at(len);
+                todo = tail;
+                Token::Bytes(bytes)
+            }
+            _ => return Some(Err(ASTParseError::Frame("unknown token"))),
+        }))
+    })
+}
+
+fn write_abe(abe: &[ABE], out: &mut Vec<u8>) {
+    for el in abe {
+        match el {
+            ABE::Ctr(c) => out.push(*c as u8),
+            ABE::Expr(Expr::Bytes(b)) => write_bytes(b, out),
+            ABE::Expr(Expr::Lst(lst)) => {
+                out.push(b'[');
+                write_abe(lst, out);
+                out.push(b']');
+            }
+        }
+    }
+}
+pub fn encode_ab_frame(abe: &[ABE]) -> Vec<u8> {
+    let mut out = AB_MAGIC.to_vec();
+    write_abe(abe, &mut out);
+    out
+}
+/// Parse a frame (including [AB_MAGIC]) into abe. Adjacent bytes are merged like in [crate::parse_abe].
+pub fn parse_ab_frame(frame: &[u8]) -> Result<Vec<ABE>, ASTParseError> {
+    let mut stack: Vec<Vec<ABE>> = vec![vec![]];
+    for token in tokens(frame) {
+        let current = stack.last_mut().unwrap();
+        match token? {
+            Token::Ctr(c) => current.push(ABE::Ctr(c)),
+            Token::Bytes([]) => {}
+            Token::Bytes(b) => match current.last_mut() {
+                Some(ABE::Expr(Expr::Bytes(prev))) => prev.extend_from_slice(b),
+                _ => current.push(ABE::Expr(Expr::Bytes(b.to_vec()))),
+            },
+            Token::Open => stack.push(vec![]),
+            Token::Close => {
+                if stack.len() == 1 {
+                    return Err(ASTParseError::UnmatchedClose);
+                }
+                let lst = stack.pop().unwrap();
+                stack.last_mut().unwrap().push(ABE::Expr(Expr::Lst(lst)));
+            }
+        }
+    }
+    if stack.len() != 1 {
+        return Err(ASTParseError::UnmatchedOpen);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+impl ABList {
+    /// Encode as a '#ab' frame. Unlike a frame of `Vec<ABE>` this keeps empty bytes.
+    pub fn to_ab_frame(&self) -> Vec<u8> {
+        let mut out = AB_MAGIC.to_vec();
+        for (ctr, bytes) in self.as_slice() {
+            if let Some(c) = ctr {
+                out.push(*c as u8);
+            }
+            write_bytes(bytes, &mut out);
+        }
+        out
+    }
+    pub fn from_ab_frame(frame: &[u8]) -> Result<ABList, ASTParseError> {
+        let mut lst = ABList::default();
+        for token in tokens(frame) {
+            lst = match token? {
+                Token::Ctr(c) => lst.push_ctr(c),
+                Token::Bytes(b) => lst.push_bytes(b),
+                Token::Open | Token::Close => {
+                    return Err(ASTParseError::Frame("an ABList has no expression lists"))
+                }
+            };
+        }
+        Ok(lst)
+    }
+}
+
+#[test]
+fn ab_frame() {
+    use crate::{ast::parse_abe_strict_b, eval::clist};
+    let abe = parse_abe_strict_b(br"a:\0\n\xff/[b:[c]]:").unwrap();
+    let frame = encode_ab_frame(&abe);
+    assert!(is_ab_frame(&frame));
+    assert_eq!(parse_ab_frame(&frame).unwrap(), abe);
+    assert_eq!(crate::parse_abe(&frame, false).unwrap(), abe);
+
+    let lst = clist([b"" as &[u8], b"x\0y", b""]);
+    let frame = lst.to_ab_frame();
+    assert_eq!(ABList::from_ab_frame(&frame).unwrap(), lst);
+
+    // lengths of 128 and up take multiple LEB128 bytes
+    let big: Vec<u8> = (0..=255).collect();
+    let lst = clist([&big[..], &big[..200]]);
+    let frame = lst.to_ab_frame();
+    assert_eq!(ABList::from_ab_frame(&frame).unwrap(), lst);
+
+    assert!(parse_ab_frame(b"\0#abb\x05ab").is_err());
+    assert!(parse_ab_frame(b"b\x01a").is_err());
+    assert!(ABList::from_ab_frame(&encode_ab_frame(&abe)).is_err());
+}
@@ -382,6 +382,8 @@ pub enum ASTParseError {
     UnmatchedOpen,
     #[error("Newline in brackets")]
     NewlineInBrackets,
+    #[error("#ab frame: {0}")]
+    Frame(&'static str),
 }
 
 mod collect {
@@ -433,8 +435,11 @@ pub fn parse_abe_b(st: &[u8], parse_unencoded: bool) -> Result<Vec<ABE>, ASTPars
     }
 }
 
-/// ALl bytes must be valid ABE.
+/// ALl bytes must be valid ABE (or a [crate::abframe] '#ab' frame).
 pub fn parse_abe_strict_b(st: &[u8]) -> Result<Vec<ABE>, ASTParseError> {
+    if crate::abframe::is_ab_frame(st) {
+        return crate::abframe::parse_ab_frame(st);
+    }
     let mut depth = 0;
     let mut r: Vec<ABTok> = vec![];
     let mut bytes = vec![];
@@ -478,6 +483,9 @@ pub fn parse_abe_strict_b(st: &[u8]) -> Result<Vec<ABE>, ASTParseError> {
 
 /// In contrast to [[parse_abe_strict_b]] this function does not error bytes outside the range 0x20..0xfe, but reads them as-is.
 pub fn parse_abe_with_unencoded_b(st: &[u8]) -> Result<Vec<ABE>, ASTParseError> {
+    if crate::abframe::is_ab_frame(st) {
+        return crate::abframe::parse_ab_frame(st);
+    }
     let mut depth = 0;
     let mut r: Vec<ABTok> = vec![];
     let mut bytes = vec![];
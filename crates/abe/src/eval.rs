@@ -438,8 +438,8 @@ impl std::fmt::Debug for EvalError {
 pub type Describer<'cb> = &'cb mut dyn FnMut(
     &str,
     &str,
-    &mut dyn Iterator<Item = ScopeFuncInfo>,
-    &mut dyn Iterator<Item = ScopeMacroInfo>,
+    &mut dyn Iterator<Item = ScopeFuncInfo<'_>>,
+    &mut dyn Iterator<Item = ScopeMacroInfo<'_>>,
 );
 
 pub trait Scope {
@@ -526,24 +526,24 @@ pub trait EvalScopeImpl {
 pub struct ScopeFunc<T> {
     pub apply: fn(T, &[&[u8]], bool, &dyn Scope) -> ApplyResult,
     pub to_abe: fn(T, &[u8], &[ABE]) -> ApplyResult<String>,
-    pub info: ScopeFuncInfo,
+    pub info: ScopeFuncInfo<'static>,
 }
 #[derive(Clone)]
-pub struct ScopeFuncInfo {
-    pub id: &'static str,
+pub struct ScopeFuncInfo<'o> {
+    pub id: &'o str,
     pub init_eq: Option<bool>,
     pub to_abe: bool,
     pub argc: std::ops::RangeInclusive<usize>,
-    pub help: &'static str,
+    pub help: &'o str,
 }
 pub struct ScopeMacro<T> {
     pub apply: fn(T, &[ABE], &dyn Scope) -> ApplyResult,
-    pub info: ScopeMacroInfo,
+    pub info: ScopeMacroInfo<'static>,
 }
 #[derive(Copy, Clone)]
-pub struct ScopeMacroInfo {
-    pub id: &'static str,
-    pub help: &'static str,
+pub struct ScopeMacroInfo<'o> {
+    pub id: &'o str,
+    pub help: &'o str,
 }
 
 impl Scope for () {
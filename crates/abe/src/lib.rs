@@ -17,6 +17,7 @@
     bigint_helper_methods
 )]
 pub mod abconf;
+pub mod abframe;
 pub mod abe_macro;
 pub mod abtxt;
 pub mod ast;
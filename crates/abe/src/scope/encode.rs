@@ -1,7 +1,9 @@
 use crate::{
-    ast::{is_colon, parse_abe_strict_b, take_first},
+    abframe::is_ab_frame,
+    ast::{is_colon, parse_abe_strict_b, take_first, Ctr},
     eval::{
-        ApplyResult, EvalScopeImpl, Scope, ScopeFunc, ScopeFuncInfo, ScopeMacro, ScopeMacroInfo,
+        clist, ABList, ApplyResult, EvalScopeImpl, Scope, ScopeFunc, ScopeFuncInfo, ScopeMacro,
+        ScopeMacroInfo,
     },
 };
 use anyhow::{anyhow, Context};
@@ -39,6 +41,32 @@ impl EvalScopeImpl for Encode {
                 },
                 to_abe: crate::eval::none,
             },
+            ScopeFunc {
+                info: ScopeFuncInfo {
+                    id: "#ab",
+                    init_eq: None,
+                    to_abe: true,
+                    argc: 1..=16,
+                    help: "'#ab' length-delimited binary frame of the args. encodes a frame back into [#ab:..]",
+                },
+                apply: |_, inp, _, _| ApplyResult::Value(clist(inp).to_ab_frame()),
+                to_abe: |_, bytes, _| {
+                    if !is_ab_frame(bytes) {
+                        return ApplyResult::NoValue;
+                    }
+                    let lst = ABList::from_ab_frame(bytes)?;
+                    let items = lst.as_slice();
+                    // only the frames [#ab:..] can produce
+                    let is_clist = items
+                        .iter()
+                        .enumerate()
+                        .all(|(i, (ctr, _))| *ctr == (i != 0).then_some(Ctr::Colon));
+                    if !is_clist || !(1..=16).contains(&items.len()) {
+                        return ApplyResult::NoValue;
+                    }
+                    ApplyResult::Value(format!("[#ab:{lst}]"))
+                },
+            },
             ScopeFunc {
                 info: ScopeFuncInfo {
                     id: "?",
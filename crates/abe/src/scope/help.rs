@@ -70,11 +70,11 @@ impl EvalScopeImpl for Help {
 
 pub(crate) fn fmt_describer(
     f: &mut dyn std::fmt::Write,
-    seen: &mut HashSet<&'static str>,
+    seen: &mut HashSet<String>,
     name: &str,
     about: &str,
-    funcs: &mut dyn Iterator<Item = ScopeFuncInfo>,
-    evals: &mut dyn Iterator<Item = ScopeMacroInfo>,
+    funcs: &mut dyn Iterator<Item = ScopeFuncInfo<'_>>,
+    evals: &mut dyn Iterator<Item = ScopeMacroInfo<'_>>,
 ) -> std::fmt::Result {
     let (mut fnc_head, mut evl_head) = (true, true);
     writeln!(f, "# {name}\n{about}")?;
@@ -89,7 +89,7 @@ pub(crate) fn fmt_describer(
         if std::mem::take(&mut fnc_head) {
             writeln!(f, "## Functions")?;
         }
-        let state = if seen.insert(id) {
+        let state = if seen.insert(id.to_owned()) {
             "        "
         } else {
             "<partial>"
@@ -135,7 +135,7 @@ impl<A: Scope> Display for DisplayHelp<A> {
         writeln!(f)?;
 
         let mut err = Ok(());
-        let mut set = HashSet::<&'static str>::new();
+        let mut set = HashSet::<String>::new();
         self.0.describe(&mut |name, about, fncs, macros| {
             if err.is_err() {
                 return;
@@ -16,6 +16,8 @@ pub mod bytes;
 pub use bytes::BytesFE;
 pub mod uint;
 pub use uint::UIntFE;
+pub mod user;
+pub use user::UserScope;
 
 pub type BasicScope = (
     (EScope<BytesFE>, EScope<UIntFE>, EScope<BaseNScope>),
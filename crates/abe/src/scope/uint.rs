@@ -44,6 +44,202 @@ pub fn carry_sub_be(bytes: &mut [u8], val: &[u8]) -> bool {
     carry
 }
 
+/// Saturating multiplication of equal sized big endian ints. Returns true on overflow.
+pub fn mul_be(bytes: &mut [u8], val: &[u8]) -> bool {
+    debug_assert!(bytes.len() == val.len());
+    let len = bytes.len();
+    let mut out = vec![0u16; len];
+    let mut overflow = false;
+    for (i, &a) in bytes.iter().rev().enumerate() {
+        if a == 0 {
+            continue;
+        }
+        let mut carry = 0u16;
+        for (j, &b) in val.iter().rev().enumerate() {
+            let prod = a as u16 * b as u16 + carry;
+            if i + j >= len {
+                overflow |= prod != 0;
+                carry = 0;
+                continue;
+            }
+            let sum = out[len - 1 - (i + j)] as u32 + (prod & 0xff) as u32;
+            out[len - 1 - (i + j)] = (sum & 0xff) as u16;
+            carry = (prod >> 8) + (sum >> 8) as u16;
+        }
+        overflow |= carry != 0;
+    }
+    bytes.iter_mut().zip(out).for_each(|(b, o)| *b = o as u8);
+    overflow
+}
+/// Divide a big endian int in place by a small divisor. Returns the remainder.
+pub fn div_small_be(bytes: &mut [u8], divisor: u8) -> u8 {
+    let mut rem = 0u16;
+    for b in bytes.iter_mut() {
+        let cur = (rem << 8) | *b as u16;
+        *b = (cur / divisor as u16) as u8;
+        rem = cur % divisor as u16;
+    }
+    rem as u8
+}
+/// Long division of equal sized big endian ints. Returns (quotient, remainder) or None if val is zero.
+pub fn divrem_be(bytes: &[u8], val: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    debug_assert!(bytes.len() == val.len());
+    if val.iter().all(|b| *b == 0) {
+        return None;
+    }
+    let mut quot = vec![0; bytes.len()];
+    let mut rem = vec![0; bytes.len()];
+    for bit in 0..bytes.len() * 8 {
+        let overflow = shl1_be(&mut rem, bytes[bit / 8] >> (7 - bit % 8) & 1);
+        if overflow || rem.as_slice() >= val {
+            carry_sub_be(&mut rem, val);
+            quot[bit / 8] |= 1 << (7 - bit % 8);
+        }
+    }
+    Some((quot, rem))
+}
+fn shl1_be(bytes: &mut [u8], mut carry: u8) -> bool {
+    for b in bytes.iter_mut().rev() {
+        let next = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next;
+    }
+    carry != 0
+}
+/// Two's complement negation of a big endian int.
+pub fn negate_be(bytes: &mut [u8]) {
+    bytes.iter_mut().for_each(|b| *b = !*b);
+    let mut one = vec![0; bytes.len()];
+    if let Some(last) = one.last_mut() {
+        *last = 1;
+    }
+    carry_add_be(bytes, &one);
+}
+pub fn is_negative_be(bytes: &[u8]) -> bool {
+    bytes.first().is_some_and(|b| b & 0x80 != 0)
+}
+
+/// Parse a decimal into a big endian int of `size` bytes. A leading '-' gives the two's complement.
+pub fn parse_decimal_be(b: &[u8], size: usize, signed: bool) -> Result<Vec<u8>, ApplyErr> {
+    let (neg, digits) = match b.strip_prefix(b"-") {
+        Some(d) if signed => (true, d),
+        _ => (false, b),
+    };
+    if digits.is_empty() {
+        return Err(anyhow!("expected a decimal number"));
+    }
+    let mut out = vec![0; size];
+    let mut ten = vec![0; size];
+    let mut digit = vec![0; size];
+    if size > 0 {
+        ten[size - 1] = 10;
+    }
+    for c in digits {
+        if !c.is_ascii_digit() {
+            return Err(anyhow!("invalid digit {:?}", *c as char));
+        }
+        digit[size - 1] = c - b'0';
+        if mul_be(&mut out, &ten) || carry_add_be(&mut out, &digit) {
+            return Err(anyhow!("number too large for {size} bytes"));
+        }
+    }
+    if signed {
+        // the magnitude of the minimum value is one more than the maximum
+        let min = neg && out[0] == 0x80 && out[1..].iter().all(|b| *b == 0);
+        if is_negative_be(&out) && !min {
+            return Err(anyhow!("number too large for {size} bytes"));
+        }
+        if neg {
+            negate_be(&mut out);
+        }
+    }
+    Ok(out)
+}
+/// Print a big endian int of any size as a decimal
+pub fn print_decimal_be(bytes: &[u8], signed: bool) -> String {
+    let mut val = bytes.to_vec();
+    let neg = signed && is_negative_be(&val);
+    if neg {
+        negate_be(&mut val);
+    }
+    let mut digits = vec![];
+    loop {
+        // negating the minimum value leaves the sign bit, dividing as unsigned is still correct
+        digits.push(b'0' + div_small_be(&mut val, 10));
+        if val.iter().all(|b| *b == 0) {
+            break;
+        }
+    }
+    if neg {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+fn equal_size(inp: &[&[u8]]) -> Result<(), ApplyErr> {
+    if !inp.iter().all(|v| v.len() == inp[0].len()) {
+        return Err(anyhow!("Mismatch length"));
+    }
+    Ok(())
+}
+/// Flip the sign bit so signed ints compare as bytes
+fn signed_key(b: &[u8]) -> Vec<u8> {
+    let mut v = b.to_vec();
+    if let Some(f) = v.first_mut() {
+        *f ^= 0x80
+    }
+    v
+}
+fn cmp_test(inp: &[&[u8]], signed: bool) -> Result<Vec<u8>, ApplyErr> {
+    let (a, op, b) = (inp[0], inp[1], inp[2]);
+    equal_size(&[a, b])?;
+    let ord = if signed {
+        signed_key(a).cmp(&signed_key(b))
+    } else {
+        a.cmp(b)
+    };
+    use std::cmp::Ordering::*;
+    let ok = match op {
+        b"<" => ord == Less,
+        b"<=" => ord != Greater,
+        b">" => ord == Greater,
+        b">=" => ord != Less,
+        b"=" => ord == Equal,
+        b"!=" => ord != Equal,
+        _ => return Err(anyhow!("unknown op - expected one of < <= > >= = !=")),
+    };
+    if !ok {
+        return Err(anyhow!(
+            "{} {} {} is false",
+            print_decimal_be(a, signed),
+            String::from_utf8_lossy(op),
+            print_decimal_be(b, signed)
+        ));
+    }
+    Ok(a.to_vec())
+}
+/// Saturating signed add/sub of equal sized ints.
+fn signed_add_sub(inp: &[&[u8]], sub: bool) -> Result<Vec<u8>, ApplyErr> {
+    equal_size(inp)?;
+    let mut r = inp[0].to_vec();
+    for i in &inp[1..] {
+        let (a_neg, b_neg) = (is_negative_be(&r), is_negative_be(i) != sub);
+        if sub {
+            carry_sub_be(&mut r, i);
+        } else {
+            carry_add_be(&mut r, i);
+        }
+        // overflow if both operands share a sign and the result does not
+        if a_neg == b_neg && is_negative_be(&r) != a_neg {
+            r.iter_mut().for_each(|v| *v = if a_neg { 0 } else { 255 });
+            r[0] ^= 0x80;
+            return Ok(r);
+        }
+    }
+    Ok(r)
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct UIntFE;
 impl EvalScopeImpl for UIntFE {
@@ -54,7 +250,7 @@ impl EvalScopeImpl for UIntFE {
         fncs!([
             ( "+" , 1..=16,   "Saturating addition. Requires all inputs to be equal size",
                     |_,inp:&[&[u8]]| {
-                        if !inp.iter().all(|v| v.len() == inp[0].len()){ return Err(anyhow!("Mismatch length"))}
+                        equal_size(inp)?;
                         let mut r = inp[0].to_vec();
                         for i in &inp[1..]{
                             if carry_add_be(&mut r, i){
@@ -67,7 +263,7 @@ impl EvalScopeImpl for UIntFE {
             ),
             ( "-" , 1..=16,   "Saturating subtraction. Requires all inputs to be equal size",
                     |_,inp:&[&[u8]]| {
-                        if !inp.iter().all(|v| v.len() == inp[0].len()){ return Err(anyhow!("Mismatch length"))}
+                        equal_size(inp)?;
                         let mut r = inp[0].to_vec();
                         for i in &inp[1..]{
                             if carry_sub_be(&mut r, i){
@@ -78,6 +274,43 @@ impl EvalScopeImpl for UIntFE {
                         Ok(r)
                     }
             ),
+            ( "*" , 1..=16,   "Saturating multiplication. Requires all inputs to be equal size",
+                    |_,inp:&[&[u8]]| {
+                        equal_size(inp)?;
+                        let mut r = inp[0].to_vec();
+                        for i in &inp[1..]{
+                            if mul_be(&mut r, i){
+                                r.iter_mut().for_each(|v| *v = 255);
+                                return Ok(r)
+                            }
+                        }
+                        Ok(r)
+                    }
+            ),
+            ( "div" , 2..=2,   "[a:b] unsigned division. Requires equal size",
+                    |_,inp:&[&[u8]]| {
+                        equal_size(inp)?;
+                        Ok(divrem_be(inp[0],inp[1]).ok_or_else(|| anyhow!("division by zero"))?.0)
+                    }
+            ),
+            ( "mod" , 2..=2,   "[a:b] unsigned remainder. Requires equal size",
+                    |_,inp:&[&[u8]]| {
+                        equal_size(inp)?;
+                        Ok(divrem_be(inp[0],inp[1]).ok_or_else(|| anyhow!("division by zero"))?.1)
+                    }
+            ),
+            ( "i+" , 1..=16,   "Saturating signed addition. Requires all inputs to be equal size",
+                    |_,inp:&[&[u8]]| signed_add_sub(inp,false)
+            ),
+            ( "i-" , 1..=16,   "Saturating signed subtraction. Requires all inputs to be equal size",
+                    |_,inp:&[&[u8]]| signed_add_sub(inp,true)
+            ),
+            ( "u?" , 3..=3,   "[a:OP:b] error unless the unsigned test passes - OP is one of < <= > >= = !=",
+                    |_,inp:&[&[u8]]| cmp_test(inp,false)
+            ),
+            ( "i?" , 3..=3,   "[a:OP:b] error unless the signed test passes - OP is one of < <= > >= = !=",
+                    |_,inp:&[&[u8]]| cmp_test(inp,true)
+            ),
             ( "u8" , 1..=1,   "parse 1 byte", |_,inp:&[&[u8]]| Ok(parse_b::<u8>(inp[0])?.to_be_bytes().to_vec()),
                { id : |b:&[u8],_| b.try_into().ok().map(u8::from_be_bytes).map(|t| t.to_string()) }
             ),
@@ -93,15 +326,33 @@ impl EvalScopeImpl for UIntFE {
             ( "u128" , 1..=1, "parse 16 byte", |_,inp:&[&[u8]]| Ok(parse_b::<u128>(inp[0])?.to_be_bytes().to_vec()) ,
                { id : |b:&[u8],_| b.try_into().ok().map(u128::from_be_bytes).map(|t| t.to_string()) }
             ),
+            ( "u256" , 1..=1, "parse 32 byte", |_,inp:&[&[u8]]| parse_decimal_be(inp[0],32,false),
+               { id : |b:&[u8],_| (b.len() == 32).then(|| print_decimal_be(b,false)) }
+            ),
+            ( "i8" , 1..=1,   "parse 1 byte signed", |_,inp:&[&[u8]]| Ok(parse_b::<i8>(inp[0])?.to_be_bytes().to_vec()),
+               { id : |b:&[u8],_| b.try_into().ok().map(i8::from_be_bytes).map(|t| t.to_string()) }
+            ),
+            ( "i16" , 1..=1,  "parse 2 byte signed", |_,inp:&[&[u8]]| Ok(parse_b::<i16>(inp[0])?.to_be_bytes().to_vec()),
+               { id : |b:&[u8],_| b.try_into().ok().map(i16::from_be_bytes).map(|t| t.to_string()) }
+            ),
+            ( "i32" , 1..=1,  "parse 4 byte signed", |_,inp:&[&[u8]]| Ok(parse_b::<i32>(inp[0])?.to_be_bytes().to_vec()),
+               { id : |b:&[u8],_| b.try_into().ok().map(i32::from_be_bytes).map(|t| t.to_string()) }
+            ),
+            ( "i64" , 1..=1,  "parse 8 byte signed", |_,inp:&[&[u8]]| Ok(parse_b::<i64>(inp[0])?.to_be_bytes().to_vec()),
+               { id : |b:&[u8],_| b.try_into().ok().map(i64::from_be_bytes).map(|t| t.to_string()) }
+            ),
+            ( "i128" , 1..=1, "parse 16 byte signed", |_,inp:&[&[u8]]| Ok(parse_b::<i128>(inp[0])?.to_be_bytes().to_vec()),
+               { id : |b:&[u8],_| b.try_into().ok().map(i128::from_be_bytes).map(|t| t.to_string()) }
+            ),
+            ( "i256" , 1..=1, "parse 32 byte signed", |_,inp:&[&[u8]]| parse_decimal_be(inp[0],32,true),
+               { id : |b:&[u8],_| (b.len() == 32).then(|| print_decimal_be(b,true)) }
+            ),
             ( "?u" , 1..=1, "Print big endian bytes as decimal",
-              |_,inp:&[&[u8]]| {
-                  let val = inp[0];
-                  if val.len() > 16 { return Err(anyhow::anyhow!("ints larger than 16 bytes (fixme)"))}
-                  let mut v = [0;16];
-                  v[16-val.len()..].copy_from_slice(val);
-                  Ok(u128::from_be_bytes(v).to_string().into_bytes())
-              }
-              ),
+              |_,inp:&[&[u8]]| Ok(print_decimal_be(inp[0],false).into_bytes())
+            ),
+            ( "?i" , 1..=1, "Print big endian two's complement bytes as decimal",
+              |_,inp:&[&[u8]]| Ok(print_decimal_be(inp[0],true).into_bytes())
+            ),
             ( "lu" , 1..=1, "parse little endian byte (upto 16)",
               |_,inp:&[&[u8]]| Ok(cut_ending_nulls2(&parse_b::<u128>(inp[0])?.to_le_bytes()).to_vec())
             ),
@@ -112,12 +363,50 @@ impl EvalScopeImpl for UIntFE {
             ( "lu128" , 1..=1, "parse 16 little endian byte", |_,inp:&[&[u8]]| Ok(parse_b::<u128>(inp[0])?.to_le_bytes().to_vec()) ),
             ( "?lu",1..=1,"print little endian number",
              |_,inp:&[&[u8]]| {
-                 let val = inp[0];
-                 if val.len() > 16 { return Err(anyhow::anyhow!("ints larger than 16 bytes (fixme)"));}
-                 let mut v = [0;16];
-                 v[0..val.len()].copy_from_slice(val);
-                 Ok(u128::from_le_bytes(v).to_string().into_bytes())
+                 let mut val = inp[0].to_vec();
+                 val.reverse();
+                 Ok(print_decimal_be(&val,false).into_bytes())
              })
         ])
     }
 }
+
+#[test]
+fn big_ints() {
+    use crate::{ast::parse_abe_strict_b, eval::eval, scope::basic_scope};
+    let run = |st: &str| {
+        eval(&basic_scope(), &parse_abe_strict_b(st.as_bytes()).unwrap()).map(|v| v.concat())
+    };
+    let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    assert_eq!(run(&format!("[u256:{max}]")).unwrap(), [255; 32]);
+    assert!(run(&format!("[u256:{max}0]")).is_err());
+    assert_eq!(run(&format!("[u256:{max}/?u]")).unwrap(), max.as_bytes());
+    assert_eq!(run("[u256:10/?u]").unwrap(), b"10");
+    assert_eq!(
+        run("[*:[u256:12345678901234567890]:[u256:1000]/?u]").unwrap(),
+        b"12345678901234567890000"
+    );
+    assert_eq!(run("[*:[u8:20]:[u8:20]]").unwrap(), [255]);
+    assert_eq!(run("[div:[u256:1000]:[u256:7]/?u]").unwrap(), b"142");
+    assert_eq!(run("[mod:[u256:1000]:[u256:7]/?u]").unwrap(), b"6");
+    assert!(run("[div:[u8:1]:[u8:0]]").is_err());
+
+    assert_eq!(run("[i64:-5]").unwrap(), (-5i64).to_be_bytes());
+    assert_eq!(run("[i64:-5/?i]").unwrap(), b"-5");
+    assert_eq!(run("[i8:-128/?i]").unwrap(), b"-128");
+    assert_eq!(run("[i256:-1]").unwrap(), [255; 32]);
+    assert_eq!(run("[i256:-2/?i]").unwrap(), b"-2");
+    assert_eq!(run("[i+:[i16:-300]:[i16:100]/?i]").unwrap(), b"-200");
+    assert_eq!(run("[i-:[i8:-100]:[i8:100]/?i]").unwrap(), b"-128");
+    assert_eq!(run("[i+:[i8:100]:[i8:100]/?i]").unwrap(), b"127");
+
+    assert!(run("[u?:[u256:5]:<:[u256:6]]").is_ok());
+    assert!(run("[u?:[u256:7]:<:[u256:6]]").is_err());
+    assert!(run("[i?:[i32:-7]:<:[i32:6]]").is_ok());
+    assert!(run("[u?:[i32:-7]:<:[i32:6]]").is_err());
+
+    let enc =
+        |bytes: &[u8], opts: &str| crate::eval::encode(&basic_scope(), bytes, opts, false).unwrap();
+    assert_eq!(enc(&[255; 32], "u256"), format!("[u256:{max}]"));
+    assert_eq!(enc(&(-3i32).to_be_bytes(), "i32"), "[i32:-3]");
+}
@@ -0,0 +1,161 @@
+use std::{ops::RangeInclusive, rc::Rc};
+
+use crate::{ast::ABE, eval::*};
+
+pub type UserFunc = Rc<dyn Fn(&[&[u8]], bool, &dyn Scope) -> ApplyResult>;
+pub type UserMacro = Rc<dyn Fn(&[ABE], &dyn Scope) -> ApplyResult>;
+
+/// A scope of functions and macros added at runtime. e.g. from a python callback.
+#[derive(Clone)]
+pub struct UserScope {
+    pub name: String,
+    funcs: Vec<UserFuncEntry>,
+    macros: Vec<UserMacroEntry>,
+}
+#[derive(Clone)]
+struct UserFuncEntry {
+    id: String,
+    help: String,
+    argc: RangeInclusive<usize>,
+    func: UserFunc,
+}
+#[derive(Clone)]
+struct UserMacroEntry {
+    id: String,
+    help: String,
+    mac: UserMacro,
+}
+
+impl UserScope {
+    pub fn new(name: impl Into<String>) -> Self {
+        UserScope {
+            name: name.into(),
+            funcs: vec![],
+            macros: vec![],
+        }
+    }
+    /// Add or replace a function.
+    pub fn add_func(
+        &mut self,
+        id: &str,
+        help: &str,
+        argc: RangeInclusive<usize>,
+        func: impl Fn(&[&[u8]], bool, &dyn Scope) -> ApplyResult + 'static,
+    ) {
+        let entry = UserFuncEntry {
+            id: id.to_owned(),
+            help: help.to_owned(),
+            argc,
+            func: Rc::new(func),
+        };
+        match self.funcs.iter_mut().find(|e| e.id == id) {
+            Some(e) => *e = entry,
+            None => self.funcs.push(entry),
+        }
+    }
+    /// Add or replace a macro.
+    pub fn add_macro(
+        &mut self,
+        id: &str,
+        help: &str,
+        mac: impl Fn(&[ABE], &dyn Scope) -> ApplyResult + 'static,
+    ) {
+        let entry = UserMacroEntry {
+            id: id.to_owned(),
+            help: help.to_owned(),
+            mac: Rc::new(mac),
+        };
+        match self.macros.iter_mut().find(|e| e.id == id) {
+            Some(e) => *e = entry,
+            None => self.macros.push(entry),
+        }
+    }
+    /// Remove a function and macro with this id. Returns true if anything was removed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let len = self.funcs.len() + self.macros.len();
+        self.funcs.retain(|e| e.id != id);
+        self.macros.retain(|e| e.id != id);
+        len != self.funcs.len() + self.macros.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.funcs.is_empty() && self.macros.is_empty()
+    }
+}
+
+impl Scope for UserScope {
+    fn try_apply_func(
+        &self,
+        id: &[u8],
+        inp: &[&[u8]],
+        init: bool,
+        scope: &dyn Scope,
+    ) -> ApplyResult {
+        match self.funcs.iter().find(|e| e.id.as_bytes() == id) {
+            Some(e) if !e.argc.contains(&inp.len()) => {
+                ApplyResult::arg_err(inp, &format!("between {:?}", e.argc))
+            }
+            Some(e) => (e.func)(inp, init, scope),
+            None => ApplyResult::NoValue,
+        }
+    }
+    fn try_apply_macro(&self, id: &[u8], abe: &[ABE], scope: &dyn Scope) -> ApplyResult {
+        match self.macros.iter().find(|e| e.id.as_bytes() == id) {
+            Some(e) => (e.mac)(abe, scope),
+            None => ApplyResult::NoValue,
+        }
+    }
+    fn try_encode(&self, _id: &[u8], _options: &[ABE], _bytes: &[u8]) -> ApplyResult<String> {
+        ApplyResult::NoValue
+    }
+    fn describe(&self, cb: Describer) {
+        let mut funcs = self.funcs.iter().map(|e| ScopeFuncInfo {
+            id: &e.id,
+            init_eq: None,
+            to_abe: false,
+            argc: e.argc.clone(),
+            help: &e.help,
+        });
+        let mut macros = self.macros.iter().map(|e| ScopeMacroInfo {
+            id: &e.id,
+            help: &e.help,
+        });
+        cb(&self.name, "user defined", &mut funcs, &mut macros);
+    }
+}
+
+#[test]
+fn user_scope() {
+    use crate::{ast::parse_abe_strict_b, scope::basic_scope};
+    let mut user = UserScope::new("test");
+    user.add_func("twice", "repeat the input", 1..=1, |inp, _, _| {
+        inp[0].repeat(2).into()
+    });
+    user.add_macro("count", "number of abe elements", |abe, _| {
+        abe.len().to_string().into_bytes().into()
+    });
+    let scope = (basic_scope(), user.clone());
+    let run =
+        |st: &str| eval(&scope, &parse_abe_strict_b(st.as_bytes()).unwrap()).map(|v| v.concat());
+    assert_eq!(run("[twice:ab]").unwrap(), b"abab");
+    assert_eq!(run("[:ab/twice]").unwrap(), b"abab");
+    assert_eq!(run("[/count:a:b]").unwrap(), b"4");
+    assert!(run("[twice:a:b]").is_err());
+
+    user.remove("twice");
+    let scope = (basic_scope(), user.clone());
+    assert!(eval(&scope, &parse_abe_strict_b(b"[twice:ab]").unwrap()).is_err());
+
+    // describe lists what is registered - adding the same id again replaces it
+    for help in ["repeat", "repeat twice"] {
+        user.add_func("twice", help, 1..=1, |inp, _, _| inp[0].repeat(2).into());
+    }
+    let mut listed = vec![];
+    user.describe(&mut |_, _, funcs, macros| {
+        listed.extend(funcs.map(|f| format!("{} {}", f.id, f.help)));
+        listed.extend(macros.map(|m| format!("{} {}", m.id, m.help)));
+    });
+    assert_eq!(
+        listed,
+        ["twice repeat twice", "count number of abe elements"]
+    );
+}
@@ -8,7 +8,7 @@ Encrypt public and private key with argon2.
 Uses public key as salt, xors private key with the encoded hash.
 **/
 // This is not ideal but both the package argon2 and rust-argon2 are hiding implementation details.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Costs {
     pub mem: u32,
     pub times: u32,
@@ -36,8 +36,27 @@ impl Default for Costs {
         DEFAULT_COST
     }
 }
+
+/// The argon2 variant used to derive the xor key.
+/// Argon2id is resistant to side channel attacks and is the better fit for keys unlocked on shared or embedded hardware.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Variant {
+    #[default]
+    Argon2d,
+    Argon2id,
+}
+impl Variant {
+    fn argon2(self) -> argon2::Variant {
+        match self {
+            Variant::Argon2d => argon2::Variant::Argon2d,
+            Variant::Argon2id => argon2::Variant::Argon2id,
+        }
+    }
+}
+
 mod params {
     pub struct Decoded {
+        pub variant: crate::Variant,
         pub costs: crate::Costs,
         pub salt: Vec<u8>,
         pub hash: [u8; 32],
@@ -45,7 +64,13 @@ mod params {
     /// Attempts to decode the encoded string slice.
     pub fn decode_string(encoded: &str) -> Option<Decoded> {
         use base64::prelude::*;
-        let items = encoded.strip_prefix("$argon2d$v=19$")?;
+        let (variant, items) = match encoded.strip_prefix("$argon2d$v=19$") {
+            Some(items) => (crate::Variant::Argon2d, items),
+            None => (
+                crate::Variant::Argon2id,
+                encoded.strip_prefix("$argon2id$v=19$")?,
+            ),
+        };
         let mut items_it = items.split('$');
         let options = items_it.next()?;
         let salt = BASE64_STANDARD_NO_PAD.decode(items_it.next()?).ok()?;
@@ -65,6 +90,7 @@ mod params {
             return None;
         }
         Some(Decoded {
+            variant,
             costs: crate::Costs {
                 mem,
                 times: time,
@@ -91,9 +117,18 @@ pub enum KeyError {
 }
 
 pub fn encrypt(key: &SigningKey, password: &[u8], cost: Option<Costs>) -> String {
-    _encrypt(key, password, cost.unwrap_or(DEFAULT_COST)).expect("bug - key encryption errror?")
+    encrypt_variant(key, password, cost, Variant::Argon2d)
+}
+pub fn encrypt_variant(
+    key: &SigningKey,
+    password: &[u8],
+    cost: Option<Costs>,
+    variant: Variant,
+) -> String {
+    _encrypt(key, password, cost.unwrap_or(DEFAULT_COST), variant)
+        .expect("bug - key encryption errror?")
 }
-fn _encrypt(key: &SigningKey, password: &[u8], cost: Costs) -> Option<String> {
+fn _encrypt(key: &SigningKey, password: &[u8], cost: Costs, variant: Variant) -> Option<String> {
     use base64::prelude::*;
     let Costs {
         mem,
@@ -101,7 +136,7 @@ fn _encrypt(key: &SigningKey, password: &[u8], cost: Costs) -> Option<String> {
         parallelism,
     } = cost;
     let config = Config {
-        variant: argon2::Variant::Argon2d,
+        variant: variant.argon2(),
         mem_cost: mem,
         time_cost: time,
         lanes: parallelism,
@@ -139,6 +174,24 @@ pub fn pubkey(identity: &str) -> Result<PublicKey, KeyError> {
     Ok(pubkey)
 }
 
+/// The variant and cost parameters an enckey was encrypted with.
+pub fn params(enckey: &str) -> Result<(Variant, Costs), KeyError> {
+    let argon_param = params::decode_string(enckey).ok_or(KeyError::BadData)?;
+    Ok((argon_param.variant, argon_param.costs))
+}
+
+/// Decrypt and encrypt the key with a new password, cost, and variant.
+pub fn rekey(
+    enckey: &str,
+    password: &[u8],
+    new_password: &[u8],
+    cost: Option<Costs>,
+    variant: Variant,
+) -> Result<String, KeyError> {
+    let key = decrypt(enckey, password)?;
+    Ok(encrypt_variant(&key, new_password, cost, variant))
+}
+
 pub fn decrypt(enckey: &str, password: &[u8]) -> Result<SigningKey, KeyError> {
     let argon_param = params::decode_string(enckey).ok_or(KeyError::BadData)?;
     let Costs {
@@ -147,7 +200,7 @@ pub fn decrypt(enckey: &str, password: &[u8]) -> Result<SigningKey, KeyError> {
         parallelism,
     } = argon_param.costs;
     let config = Config {
-        variant: argon2::Variant::Argon2d,
+        variant: argon_param.variant.argon2(),
         version: argon2::Version::Version13,
         mem_cost: mem,
         time_cost: time,
@@ -196,3 +249,26 @@ pub fn test_key_encoding() {
     let e = encrypt(&key, b"", Some(INSECURE_COST));
     assert_eq!(e, TEST_KEY_ID);
 }
+
+#[test]
+pub fn test_rekey() {
+    let key = SigningKey::generate();
+    let e = encrypt(&key, b"hello", Some(INSECURE_COST));
+    let id = rekey(
+        &e,
+        b"hello",
+        b"world",
+        Some(INSECURE_COST),
+        Variant::Argon2id,
+    )
+    .unwrap();
+    assert!(id.starts_with("$argon2id$"));
+    assert_eq!(params(&id).unwrap(), (Variant::Argon2id, INSECURE_COST));
+    assert_eq!(pubkey(&id).unwrap(), key.pubkey_bytes());
+    assert!(decrypt(&id, b"hello").is_err());
+    assert_eq!(
+        decrypt(&id, b"world").unwrap().pubkey_bytes(),
+        key.pubkey_bytes()
+    );
+    assert!(rekey(&id, b"hello", b"world", None, Variant::Argon2d).is_err());
+}
@@ -11,7 +11,7 @@ use abe::TypedABE;
 use anyhow::{bail, Context};
 use clap::Parser;
 
-use super::opts::CommonOpts;
+use super::{opts::CommonOpts, WriteDestSpec};
 
 #[derive(Parser, Clone, Debug)]
 pub struct KeyOpts {
@@ -143,10 +143,31 @@ pub struct KeyGenOpts {
     /// new password -- implies new_pass
     #[arg(long)]
     new_pass_str: Option<String>,
+    /// encrypt new enckeys with argon2id instead of argon2d
+    #[arg(long)]
+    argon2id: bool,
+
+    #[command(subcommand)]
+    action: Option<KeyAction>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub enum KeyAction {
+    /// sign a successor key with the current key, publish the rotation point, and set the successor for --key
+    Rotate {
+        /// use this enckey as the successor instead of generating a new key
+        #[arg(long)]
+        successor: Option<String>,
+        /// destination of the rotation point
+        #[arg(long, default_value = "db")]
+        write: Vec<WriteDestSpec>,
+    },
+    /// re-encrypt the key using --decrypt-cost, --argon2id, and optionally --new-pass
+    Rekey,
 }
 
 pub fn keygen(common: &CommonOpts, opts: KeyGenOpts) -> anyhow::Result<()> {
-    use linkspace_argon2_identity::{decrypt, encrypt, pubkey};
+    use linkspace_argon2_identity::{decrypt, encrypt_variant, pubkey, rekey, Variant};
     let KeyGenOpts {
         decrypt_cost,
         overwrite,
@@ -159,6 +180,8 @@ pub fn keygen(common: &CommonOpts, opts: KeyGenOpts) -> anyhow::Result<()> {
         new_pass,
         new_pass_str,
         error_none,
+        argon2id,
+        action,
     } = opts;
 
     let with_rt = if no_lk { None } else { Some(common.runtime()?) };
@@ -169,10 +192,17 @@ pub fn keygen(common: &CommonOpts, opts: KeyGenOpts) -> anyhow::Result<()> {
         _ => EXPENSIVE_COST,
     });
 
+    let variant = if argon2id {
+        Variant::Argon2id
+    } else {
+        Variant::Argon2d
+    };
+    let encrypt = |key: &SigningKey, password: &[u8]| encrypt_variant(key, password, cost, variant);
+
     let mut generate = |password: &[u8]| {
         let key = SigningKey::generate();
         no_check = true;
-        encrypt(&key, password, cost)
+        encrypt(&key, password)
     };
     let print = |enckey, pubkey| {
         if !no_enckey {
@@ -206,7 +236,50 @@ pub fn keygen(common: &CommonOpts, opts: KeyGenOpts) -> anyhow::Result<()> {
         let new_password = key.password_bytes_prompt(common, true, "new password: ")?;
         key.password = Some(abtxt::as_abtxt(&new_password).to_string());
         key.utf8_password = false;
-        enckey = Some(encrypt(&skey, &new_password, cost))
+        enckey = Some(encrypt(&skey, &new_password))
+    }
+
+    match action {
+        None => {}
+        Some(KeyAction::Rekey) => {
+            let keystr = enckey.context("no key found")?;
+            let password = key.password_bytes_prompt(common, true, "decrypting - password>")?;
+            let enckey = rekey(&keystr, &password, &password, cost, variant)?;
+            let pubkey = match &with_rt {
+                Some(rt) if !user_enckey_input => {
+                    lns::setup_special_keyclaim(rt, name, &enckey, true)?
+                }
+                _ => B64(pubkey(&enckey)?),
+            };
+            print(enckey, pubkey);
+            return Ok(());
+        }
+        Some(KeyAction::Rotate { successor, write }) => {
+            let rt = with_rt
+                .as_ref()
+                .context("rotate requires a linkspace instance")?;
+            let keystr = enckey.context("no key found")?;
+            let password = key.password_bytes_prompt(common, true, "decrypting - password>")?;
+            let old = decrypt(&keystr, &password)?;
+            let successor = match successor {
+                Some(k) => k,
+                None => {
+                    let password = key.password_bytes_prompt(
+                        common,
+                        true,
+                        "generating successor - password>",
+                    )?;
+                    generate(&password)
+                }
+            };
+            let new_pubkey = B64(pubkey(&successor)?);
+            let pkt = lns::claim::rotation(&old, new_pubkey, &[]);
+            let mut write = common.open(&write)?;
+            common.write_multi_dest(&mut write, &pkt, None)?;
+            lns::setup_special_keyclaim(rt, name, &successor, true)?;
+            print(successor, new_pubkey);
+            return Ok(());
+        }
     }
 
     if overwrite {
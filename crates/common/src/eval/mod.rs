@@ -107,13 +107,36 @@ impl<R: LKS> ReadHash<R> {
             None => Err(EvalError::NoSuchFunc(id.to_vec()).into()),
         }
     }
+    /// follow the first link with a tag ending in each tag. The first is read from the packet in scope.
+    fn walk(&self, tags: &[&[u8]], scope: &dyn Scope) -> anyhow::Result<LkHash> {
+        let (first, rest) = tags.split_first().context("missing tag")?;
+        let ptr = scope
+            .try_apply_func(b"link", &[*first], true, scope)
+            .into_opt()
+            .context("walk requires a packet in scope")??;
+        let mut hash = LkHash::try_fit_slice(&ptr)?;
+        let env = self.0.lk()?;
+        let reader = env.get_reader();
+        for tag in rest {
+            let pkt = reader
+                .read(&hash)?
+                .with_context(|| format!("could not find pkt {hash}"))?;
+            hash = pkt
+                .select()
+                .first_tailmask(tag)
+                .with_context(|| format!("{hash} has no link {}", crate::abe::abtxt::as_abtxt(tag)))?
+                .ptr;
+        }
+        Ok(hash)
+    }
 }
 impl<R: LKS> EvalScopeImpl for ReadHash<R> {
     fn about(&self) -> (String, String) {
         ("database".into(),
          "get packets from the local db.
 e-funcs evaluate their args as if in pkt scope.
-funcs evaluate as if [/[func + args]:[rest]]. (e.g. [/readhash:HASH:[group:str]] == [readhash:..:group:str])".into())
+funcs evaluate as if [/[func + args]:[rest]]. (e.g. [/readhash:HASH:[group:str]] == [readhash:..:group:str])
+walk follows links by tag starting at the packet in scope. (e.g. [/walk:reply-to:author:[data:str]])".into())
     }
     fn list_funcs(&self) -> &[ScopeFunc<&Self>] {
         &[
@@ -144,6 +167,16 @@ funcs evaluate as if [/[func + args]:[rest]]. (e.g. [/readhash:HASH:[group:str]]
                 },
                 to_abe:none
             },
+            ScopeFunc {
+                apply : |this:&Self,inp:&[&[u8]],_,scope|{
+                    this.walk(inp, scope).map(|h| h.0.to_vec()).into()
+                },
+                info: ScopeFuncInfo {
+                    id:  "walk", init_eq: None, argc: 1..=16,to_abe:false,
+                    help:"TAG* - follow the first link with a tag ending in TAG starting at the packet in scope and return the final hash"
+                },
+                to_abe:none
+            },
         ]
     }
     fn list_macros(&self) -> &[ScopeMacro<&Self>] {
@@ -177,6 +210,32 @@ funcs evaluate as if [/[func + args]:[rest]]. (e.g. [/readhash:HASH:[group:str]]
                 id: "readhash",
                 help: "HASH ':' expr (':' alt if not found) ",
             },
+        },
+        ScopeMacro {
+            apply: |this, abe: &[ABE], scope| {
+                let mut it = abe.split(|v| v.is_colon());
+                let _empty = it.next().context("arg delimited with ':'")?;
+                ast::exact::<0>(_empty)?;
+                let mut parts: Vec<&[ABE]> = it.collect();
+                let expr = parts.pop().context("missing expr")?;
+                let tags: Vec<Vec<u8>> = parts
+                    .into_iter()
+                    .map(|tag| eval(scope, tag).map(|v| v.concat()))
+                    .try_collect()?;
+                let tags: Vec<&[u8]> = tags.iter().map(|t| t.as_slice()).collect();
+                let hash = this.walk(&tags, scope)?;
+                let env = this.0.lk()?;
+                let reader = env.get_reader();
+                let pkt = reader
+                    .read(&hash)?
+                    .with_context(|| format!("could not find pkt {}", hash))?;
+                let r = eval(&(pkt_scope(&pkt), scope), expr)?.concat();
+                ApplyResult::Value(r)
+            },
+            info: ScopeMacroInfo {
+                id: "walk",
+                help: "(':' TAG)* ':' expr - follow links by tag from the packet in scope and evaluate expr for the final packet",
+            },
         }]
     }
 }
@@ -0,0 +1,156 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+The request protocol of `lk daemon`.
+
+Every message is a frame: [kind:u8] [len:u32 BE] [payload].
+Queries are sent in their binary form (see [Query::to_ab_frames]).
+
+A client sends a request and reads frames until the daemon replies with [Kind::Ok] or [Kind::Err] (utf8 message).
+Matches of watches ([Kind::Match] and [Kind::Stopped]) can arrive at any time, including while waiting on a reply.
+
+| request          | payload            | frames before the reply | Ok payload                         |
+|------------------|--------------------|-------------------------|------------------------------------|
+| [Kind::Save]     | packets            |                         | u32 number of new packets          |
+| [Kind::Get]      | query              | [Kind::Pkt] per match   | u32 number of matches              |
+| [Kind::Watch]    | query with a qid   | [Kind::Match] per match | i32 as returned by watch_query     |
+| [Kind::Stop]     | [range:u8] [qid]   |                         |                                    |
+| [Kind::Status]   |                    |                         | [DaemonStatus]                     |
+**/
+use std::io::{self, Read, Write};
+
+use linkspace_core::{
+    prelude::{NetPkt, Stamp},
+    query::Query,
+};
+
+/// the default socket name in the linkspace directory
+pub const SOCKET_NAME: &str = "daemon.sock";
+/// frames above this size are refused
+pub const MAX_FRAME_LEN: usize = 1 << 26;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Kind {
+    Save = b's',
+    Get = b'g',
+    Watch = b'w',
+    Stop = b'x',
+    Status = b'?',
+
+    Ok = b'k',
+    Err = b'!',
+    /// a match of a get
+    Pkt = b'p',
+    /// a match of a watch: [qid_len:u8] [qid] [pkt]
+    Match = b'm',
+    /// a watch ended: [qid]
+    Stopped = b'c',
+}
+impl TryFrom<u8> for Kind {
+    type Error = io::Error;
+    fn try_from(b: u8) -> Result<Self, Self::Error> {
+        use Kind::*;
+        [Save, Get, Watch, Stop, Status, Ok, Err, Pkt, Match, Stopped]
+            .into_iter()
+            .find(|k| *k as u8 == b)
+            .ok_or_else(|| invalid(format!("unknown frame kind {b:#x}")))
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// write a frame with the payload made up of segments
+pub fn write_frame(mut w: impl Write, kind: Kind, payload: &[&[u8]]) -> io::Result<()> {
+    let len: usize = payload.iter().map(|p| p.len()).sum();
+    if len > MAX_FRAME_LEN {
+        return Err(invalid("frame too large"));
+    }
+    let mut buf = Vec::with_capacity(5 + len);
+    buf.push(kind as u8);
+    buf.extend_from_slice(&(len as u32).to_be_bytes());
+    payload.iter().for_each(|p| buf.extend_from_slice(p));
+    w.write_all(&buf)
+}
+
+/// read a frame. None if the stream closed before a new frame
+pub fn read_frame(mut r: impl Read) -> io::Result<Option<(Kind, Vec<u8>)>> {
+    let mut kind = [0];
+    if r.read(&mut kind)? == 0 {
+        return Ok(None);
+    }
+    read_frame_body(kind[0], r).map(Some)
+}
+/// read the rest of a frame after its first byte
+pub fn read_frame_body(kind: u8, mut r: impl Read) -> io::Result<(Kind, Vec<u8>)> {
+    let kind = Kind::try_from(kind)?;
+    let mut len = [0; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid("frame too large"));
+    }
+    let mut payload = vec![0; len];
+    r.read_exact(&mut payload)?;
+    Ok((kind, payload))
+}
+
+pub fn write_match(w: impl Write, qid: &[u8], pkt: &dyn NetPkt) -> io::Result<()> {
+    let qid_len = u8::try_from(qid.len()).map_err(|_| invalid("qid too long"))?;
+    let segments = pkt.byte_segments().io_slices();
+    let mut payload = vec![&[qid_len] as &[u8], qid];
+    payload.extend(segments.iter().map(|s| &**s));
+    write_frame(w, Kind::Match, &payload)
+}
+/// split a [Kind::Match] payload into (qid, pkt bytes)
+pub fn split_match(payload: &[u8]) -> io::Result<(&[u8], &[u8])> {
+    let (len, rest) = payload
+        .split_first()
+        .ok_or_else(|| invalid("empty match"))?;
+    if rest.len() < *len as usize {
+        return Err(invalid("truncated qid"));
+    }
+    Ok(rest.split_at(*len as usize))
+}
+
+pub fn write_query(w: impl Write, kind: Kind, query: &Query) -> io::Result<()> {
+    write_frame(w, kind, &[&query.to_ab_frames()])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonStatus {
+    /// the log head of the database
+    pub head: Stamp,
+    /// number of connected clients
+    pub clients: u32,
+    /// number of watches of this connection
+    pub watches: u32,
+    /// the linkspace directory
+    pub dir: String,
+}
+impl DaemonStatus {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.head.0.to_vec();
+        out.extend_from_slice(&self.clients.to_be_bytes());
+        out.extend_from_slice(&self.watches.to_be_bytes());
+        out.extend_from_slice(self.dir.as_bytes());
+        out
+    }
+    pub fn from_bytes(b: &[u8]) -> io::Result<DaemonStatus> {
+        if b.len() < 16 {
+            return Err(invalid("truncated status"));
+        }
+        let u32_at = |i: usize| u32::from_be_bytes(b[i..i + 4].try_into().unwrap());
+        Ok(DaemonStatus {
+            head: Stamp::from(<[u8; 8]>::try_from(&b[..8]).unwrap()),
+            clients: u32_at(8),
+            watches: u32_at(12),
+            dir: String::from_utf8_lossy(&b[16..]).into_owned(),
+        })
+    }
+}
@@ -3,16 +3,27 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
-/// basic response-reply proving each side can sign with a key
+/**
+basic response-reply proving each side can sign with a key.
+
+Since version 1 the data of phase0 and phase1 carry an [Offer] to negotiate the version and group,
+and both sides derive a session key for each direction from the ECDH secret of their keys and the phase0 and phase1 hashes.
+There is no forward secrecy - a leaked key reveals its past sessions.
+**/
 use std::time::Duration;
 
 use crate::{prelude::*, protocols::unicast_group};
-use anyhow::ensure;
+use anyhow::{ensure, Context};
+use linkspace_core::crypto::{blake3, seal::shared_secret, Hash};
 pub const HANDSHAKE_D: Domain = ab(b"\xFFhandshake");
 
 pub const ID_SENTINAL_SPACENAME: RootedStaticSpace<17> = rspace1::<8>(b"sentinal");
 pub const ANONYMOUSE_SPACENAME: RootedStaticSpace<18> = rspace1::<9>(b"anonymous");
 
+/// Peers that send no [Offer] are version 0 and only prove their key.
+pub const HANDSHAKE_VERSION: u8 = 1;
+const SESSION_KEY_CONTEXT: &str = "linkspace handshake v1 session key";
+
 const MAX_DIFF_SECONDS: usize = 15;
 pub fn valid_stamp_range(stamp: Stamp, max_diff_sec: Option<usize>) -> anyhow::Result<()> {
     let dur = Duration::from_secs(max_diff_sec.unwrap_or(MAX_DIFF_SECONDS) as u64);
@@ -29,11 +40,92 @@ pub fn valid_stamp_range(stamp: Stamp, max_diff_sec: Option<usize>) -> anyhow::R
     Ok(())
 }
 
+/**
+The proposal of the client (phase0) or the agreement of the server (phase1).
+```text
+version:1
+group:[b64]
+```
+Unknown lines are ignored.
+**/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Offer {
+    pub version: u8,
+    /// the group to exchange
+    pub group: Option<GroupID>,
+}
+impl Offer {
+    pub fn new(group: Option<GroupID>) -> Offer {
+        Offer {
+            version: HANDSHAKE_VERSION,
+            group,
+        }
+    }
+    pub fn to_data(&self) -> Vec<u8> {
+        let mut data = format!("version:{}\n", self.version);
+        if let Some(group) = self.group {
+            data.push_str(&format!("group:{group}\n"));
+        }
+        data.into_bytes()
+    }
+    pub fn from_data(data: &[u8]) -> anyhow::Result<Offer> {
+        let mut offer = Offer::default();
+        for line in std::str::from_utf8(data)?.lines() {
+            match line.split_once(':') {
+                Some(("version", v)) => offer.version = v.parse()?,
+                Some(("group", g)) => offer.group = Some(g.parse()?),
+                _ => tracing::debug!(line, "ignoring handshake line"),
+            }
+        }
+        Ok(offer)
+    }
+}
+
+/// The result of a successful handshake
+pub struct Session {
+    pub their_key: PubKey,
+    pub version: u8,
+    pub group: Option<GroupID>,
+    /// (send, receive) keys for a [channel](linkspace_core::crypto::channel). None if the peer is version 0.
+    pub keys: Option<(Hash, Hash)>,
+}
+impl Session {
+    /// The (send, receive) keys - refuses a version 0 peer.
+    pub fn channel_keys(&self) -> anyhow::Result<(Hash, Hash)> {
+        self.keys
+            .context("peer does not support session keys (handshake version 0)")
+    }
+}
+
+fn session_keys(
+    id: &SigningKey,
+    their_key: PubKey,
+    phase0: &Phase0,
+    phase1: &Phase1,
+    is_client: bool,
+) -> anyhow::Result<(Hash, Hash)> {
+    let shared = shared_secret(id, &their_key)?;
+    let derive = |direction: &[u8]| -> Hash {
+        let mut hasher = blake3::Hasher::new_derive_key(SESSION_KEY_CONTEXT);
+        hasher.update(&shared);
+        hasher.update(&phase0.0.hash().0);
+        hasher.update(&phase1.0.hash().0);
+        hasher.update(direction);
+        hasher.finalize().into()
+    };
+    let (to_server, to_client) = (derive(b"client"), derive(b"server"));
+    Ok(if is_client {
+        (to_server, to_client)
+    } else {
+        (to_client, to_server)
+    })
+}
+
 pub struct Phase0(pub NetPktBox);
 pub struct Phase1(pub NetPktBox);
 pub struct Phase2(pub NetPktBox);
 pub struct Phase3(pub NetPktBox);
-pub fn phase0_client_init(id: &SigningKey) -> Phase0 {
+pub fn phase0_client_init(id: &SigningKey, offer: &Offer) -> Phase0 {
     tracing::trace!("Build phase0");
     let now = now();
     Phase0(
@@ -42,7 +134,7 @@ pub fn phase0_client_init(id: &SigningKey) -> Phase0 {
             HANDSHAKE_D,
             &ID_SENTINAL_SPACENAME,
             &[],
-            &[],
+            &offer.to_data(),
             now,
             id,
             (),
@@ -50,10 +142,12 @@ pub fn phase0_client_init(id: &SigningKey) -> Phase0 {
         .as_netbox(),
     )
 }
+/// serve_group restricts the group a client can request, or sets it if the client did not request one.
 pub fn phase1_server_signs(
     theirs: &Phase0,
     id: &SigningKey,
     max_diff_sec: Option<usize>,
+    serve_group: Option<GroupID>,
 ) -> anyhow::Result<Phase1> {
     tracing::trace!("Build Phase1");
     let theirs = &theirs.0;
@@ -72,6 +166,18 @@ pub fn phase1_server_signs(
         our_group != PRIVATE,
         "Connecting to yourself (using the same key) is currently not supported"
     );
+    let their_offer = Offer::from_data(theirs.data())?;
+    let group = match (their_offer.group, serve_group) {
+        (Some(requested), Some(served)) => {
+            ensure!(requested == served, "group {requested} is not served");
+            Some(requested)
+        }
+        (requested, served) => requested.or(served),
+    };
+    let agreed = Offer {
+        version: their_offer.version.min(HANDSHAKE_VERSION),
+        group,
+    };
     let links = [Link::new("auth", *theirs.hash())];
     Ok(Phase1(
         keypoint(
@@ -79,7 +185,7 @@ pub fn phase1_server_signs(
             HANDSHAKE_D,
             &ID_SENTINAL_SPACENAME,
             &links,
-            &[],
+            &agreed.to_data(),
             now(),
             id,
             (),
@@ -92,7 +198,7 @@ pub fn phase2_client_signs(
     server_reply: &Phase1,
     id: &SigningKey,
     max_diff_sec: Option<usize>,
-) -> anyhow::Result<(Phase2, PubKey)> {
+) -> anyhow::Result<(Phase2, Session)> {
     tracing::trace!("Build Phase2");
     ensure!(
         my_phase0.0.pubkey() == Some(&id.pubkey()),
@@ -128,6 +234,20 @@ pub fn phase2_client_signs(
         theirs.get_rooted_spacename() == ID_SENTINAL_SPACENAME.as_ref(),
         "wrong spacename"
     );
+    let mine = Offer::from_data(my_phase0.0.data())?;
+    let agreed = Offer::from_data(theirs.data())?;
+    ensure!(
+        agreed.version <= mine.version,
+        "server picked unsupported version {}",
+        agreed.version
+    );
+    if agreed.version > 0 && mine.group.is_some() {
+        ensure!(agreed.group == mine.group, "server refused the group");
+    }
+    let keys = match agreed.version {
+        0 => None,
+        _ => Some(session_keys(id, their_key, my_phase0, server_reply, true)?),
+    };
     let now = now();
     let proof = keypoint(
         our_group,
@@ -140,14 +260,20 @@ pub fn phase2_client_signs(
         (),
     )
     .as_netbox();
-    Ok((Phase2(proof), their_key))
+    let session = Session {
+        their_key,
+        version: agreed.version,
+        group: agreed.group,
+        keys,
+    };
+    Ok((Phase2(proof), session))
 }
 pub fn phase3_server_verify(
     their_init: &Phase0,
     my_phase1: &Phase1,
     theirs: &Phase2,
     id: &SigningKey,
-) -> anyhow::Result<PubKey> {
+) -> anyhow::Result<Session> {
     ensure!(
         my_phase1.0.pubkey() == Some(&id.pubkey()),
         "your identity mismatch"
@@ -176,5 +302,81 @@ pub fn phase3_server_verify(
         "not in the session domain"
     );
     ensure!(theirs.group() == Some(&our_group), "not in the right group");
-    Ok(their_key)
+    let agreed = Offer::from_data(my_phase1.0.data())?;
+    let keys = match agreed.version {
+        0 => None,
+        _ => Some(session_keys(id, their_key, their_init, my_phase1, false)?),
+    };
+    Ok(Session {
+        their_key,
+        version: agreed.version,
+        group: agreed.group,
+        keys,
+    })
+}
+
+/// Run the four phases between two keys in memory
+#[cfg(test)]
+fn handshake_in_memory(
+    client: &SigningKey,
+    server: &SigningKey,
+    offer: &Offer,
+    serve_group: Option<GroupID>,
+) -> anyhow::Result<(Session, Session)> {
+    // what the other side reads
+    let recv = |pkt: &NetPktBox| {
+        let mut pkt = pkt.as_netbox();
+        let header = pkt.net_header_mut().unwrap();
+        *header = header.hop();
+        pkt
+    };
+    let phase0 = phase0_client_init(client, offer);
+    let their_phase0 = Phase0(recv(&phase0.0));
+    let phase1 = phase1_server_signs(&their_phase0, server, None, serve_group)?;
+    let (phase2, client_session) =
+        phase2_client_signs(&phase0, &Phase1(recv(&phase1.0)), client, None)?;
+    let server_session =
+        phase3_server_verify(&their_phase0, &phase1, &Phase2(recv(&phase2.0)), server)?;
+    Ok((client_session, server_session))
+}
+
+#[test]
+fn handshake_sessions() {
+    let (client, server) = (SigningKey::generate(), SigningKey::generate());
+    let (group, other) = (B64([1; 32]), B64([2; 32]));
+
+    let (c, s) = handshake_in_memory(&client, &server, &Offer::new(None), None).unwrap();
+    assert_eq!(
+        (c.their_key, s.their_key),
+        (server.pubkey(), client.pubkey())
+    );
+    assert_eq!(
+        (c.version, s.version),
+        (HANDSHAKE_VERSION, HANDSHAKE_VERSION)
+    );
+    let (c_send, c_recv) = c.channel_keys().unwrap();
+    let (s_send, s_recv) = s.channel_keys().unwrap();
+    assert_eq!((c_send, c_recv), (s_recv, s_send));
+    assert_ne!(c_send, c_recv);
+    assert_eq!((c.group, s.group), (None, None));
+
+    // a new handshake between the same keys has new session keys
+    let (again, _) = handshake_in_memory(&client, &server, &Offer::new(None), None).unwrap();
+    assert_ne!(again.channel_keys().unwrap().0, c_send);
+
+    let (c, s) =
+        handshake_in_memory(&client, &server, &Offer::new(Some(group)), Some(group)).unwrap();
+    assert_eq!((c.group, s.group), (Some(group), Some(group)));
+    let (c, s) = handshake_in_memory(&client, &server, &Offer::new(None), Some(group)).unwrap();
+    assert_eq!((c.group, s.group), (Some(group), Some(group)));
+    let (c, s) = handshake_in_memory(&client, &server, &Offer::new(Some(group)), None).unwrap();
+    assert_eq!((c.group, s.group), (Some(group), Some(group)));
+    assert!(handshake_in_memory(&client, &server, &Offer::new(Some(group)), Some(other)).is_err());
+
+    // a version 0 client - as one that sends no offer
+    let (c, s) = handshake_in_memory(&client, &server, &Offer::default(), Some(group)).unwrap();
+    assert_eq!((c.version, s.version), (0, 0));
+    assert!(c.keys.is_none() && s.keys.is_none());
+    assert!(c.channel_keys().is_err());
+    assert!(s.channel_keys().is_err());
 }
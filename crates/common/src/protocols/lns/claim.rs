@@ -135,6 +135,30 @@ impl Claim {
             name,
         })
     }
+    /// Read a root claim. Unlike [Claim::from] the spacename must be exactly the claim prefix.
+    pub fn root(pkt: impl NetPkt) -> anyhow::Result<Self> {
+        ensure!(pkt.is_linkpoint(), "claim is always a linkpoint");
+        ensure!(*pkt.get_domain() == LNS);
+        ensure!(
+            pkt.get_rooted_spacename() == &*CLAIM_PREFIX,
+            "a root claim has spacename {}",
+            CLAIM_PREFIX.space()
+        );
+        ensure!(
+            *pkt.get_group() == PUBLIC,
+            "root claim in the wrong group ({})",
+            pkt.get_group()
+        );
+        let claim = Claim {
+            pkt: RecvPkt::from_dyn(&pkt),
+            name: Name::root(),
+        };
+        ensure!(
+            claim.authorities().next().is_some(),
+            "root claim without authorities"
+        );
+        Ok(claim)
+    }
     pub fn until(&self) -> Stamp {
         as_stamp_tag(self.pkt.get_links()[0].tag).0
     }
@@ -152,6 +176,26 @@ impl Claim {
             .map(|v| v.ptr)
     }
 
+    /// The number of authority votes a sub claim requires - defaults to 1.
+    pub fn threshold(&self) -> anyhow::Result<usize> {
+        let line = self
+            .pkt
+            .data()
+            .split(|i| *i == b'\n')
+            .find_map(|line| line.strip_prefix(THRESHOLD_PREFIX));
+        let threshold: usize = match line {
+            None => return Ok(1),
+            Some(t) => std::str::from_utf8(t)?.trim().parse()?,
+        };
+        let authorities = self.authorities().count();
+        ensure!(
+            (1..=authorities).contains(&threshold),
+            "{} requires {threshold} votes but has {authorities} authorities",
+            self.name
+        );
+        Ok(threshold)
+    }
+
     pub fn links(&self) -> SelectLink {
         SelectLink(self.pkt.get_links())
     }
@@ -193,3 +237,38 @@ pub fn vote(claim: &Claim, key: &SigningKey, data: &[u8]) -> anyhow::Result<NetP
     )
     .as_netbox())
 }
+
+/// A keypoint signed by the old key naming its successor.
+pub fn rotation(old: &SigningKey, successor: PubKey, data: &[u8]) -> NetPktBox {
+    let rotate_link = [Link::new(ROTATE_TAG, successor)];
+    keypoint(PUBLIC, LNS, &ROTATE_P, &rotate_link, data, now(), old, ()).as_netbox()
+}
+
+/// The latest successor a key has named with a rotation point - if any.
+pub fn lookup_rotation(reader: &ReadTxn, key: PubKey) -> anyhow::Result<Option<PubKey>> {
+    let mut preds = PktPredicates::from_gd(PUBLIC, LNS)
+        .space(ROTATE_P.space())?
+        .create_before(now())?;
+    preds.pubkey.add(TestOp::Equal, key.into());
+    let rotation = reader.query_tree(query_mode::Order::Desc, &preds).next();
+    Ok(rotation.and_then(|p| {
+        p.get_links()
+            .iter()
+            .find(|l| l.tag == ROTATE_TAG)
+            .map(lptr)
+            .copied()
+    }))
+}
+
+/// Follow the rotation points from key to its latest successor. The chain starts with key.
+pub fn rotation_chain(reader: &ReadTxn, key: PubKey) -> anyhow::Result<Vec<PubKey>> {
+    let mut chain = vec![key];
+    while let Some(next) = lookup_rotation(reader, *chain.last().unwrap())? {
+        if chain.contains(&next) {
+            tracing::warn!(%next, "rotation loop");
+            break;
+        }
+        chain.push(next);
+    }
+    Ok(chain)
+}
@@ -43,6 +43,9 @@ pub mod admin;
 pub mod eval;
 pub mod utils;
 
+#[cfg(test)]
+mod tests;
+
 pub const LNS: Domain = ab(b"lns");
 pub const CLAIM_PREFIX: RootedStaticSpace<15> = rspace1::<6>(b"claims");
 /// tag expected for local claims pointing to a (live) lns:[#:pub] claim
@@ -54,6 +57,16 @@ pub const ENCKEY_TAG: [u8; 6] = *b"enckey";
 /// A linkpoint at lns:[#:0]:by-tag/../PTR will contain by-claim:CLAIM_HASH
 pub const BY_CLAIM_TAG: Tag = ab(b"by-claim");
 pub const VOTE_TAG: Tag = ab(b"vote");
+/// A keypoint at lns:{#:pub}:/rotate signed by the old key with a rotate-to:NEW_PUBKEY link.
+pub const ROTATE_P: RootedStaticSpace<15> = rspace1::<6>(b"rotate");
+pub const ROTATE_TAG: Tag = ab(b"rotate-to");
+/// A claim data line `threshold:M` requires M of its authorities to vote for a sub claim before it is live.
+pub const THRESHOLD_PREFIX: &[u8] = b"threshold:";
+/// A linkpoint at lns:[#:0]:/root-set replaces the builtin roots.
+/// Its links are root-claim:CLAIM_HASH followed by root-sig:VOTE_HASH for each vote. No links restores the builtin roots.
+pub const ROOT_SET_P: RootedStaticSpace<17> = rspace1::<8>(b"root-set");
+pub const ROOT_CLAIM_TAG: Tag = ab(b"root-claim");
+pub const ROOT_SIG_TAG: Tag = ab(b"root-sig");
 
 pub const BY_TAG_P: linkspace_pkt::RootedStaticSpace<15> = linkspace_pkt::rspace1::<6>(b"by-tag");
 pub static BY_GROUP_TAG: [&[u8]; 2] = [b"by-tag", &GROUP_TAG];
@@ -138,12 +151,15 @@ pub fn lookup_live_chain(
             }
         }
         // The admin process doesn't exist yet so we walk the chain for now
-        NameType::Public => public_claim::walk_live_claims(
-            &lk.get_reader(),
-            public_claim::root_claim(),
-            &mut name.space().iter(),
-            issue_handler,
-        ),
+        NameType::Public => {
+            let reader = lk.get_reader();
+            public_claim::walk_live_claims(
+                &reader,
+                public_claim::active_root_claim(&reader, issue_handler)?,
+                &mut name.space().iter(),
+                issue_handler,
+            )
+        }
     }
 }
 
@@ -171,6 +187,36 @@ pub fn lookup_group(lk: &Linkspace, name: &Name) -> anyhow::Result<Option<GroupI
     lookup_claim(lk, name).map(|m| m.and_then(|c| c.group().copied()))
 }
 
+/// The pubkeys of the live claims directly below name - e.g. alice:team:local and bob:team:local for team:local.
+/// A rotated key (see [claim::rotation]) resolves to its latest successor.
+pub fn lookup_members(lk: &Linkspace, name: &Name) -> anyhow::Result<Vec<PubKey>> {
+    let reader = lk.get_reader();
+    let depth = name.space().to_array().len() + 1;
+    let mut seen: Vec<Name> = vec![];
+    let mut members = vec![];
+    for claim in utils::list_all_potential_claims_with_prefix(&reader, name) {
+        let claim = match claim {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::debug!(?e, "skipping bad claim");
+                continue;
+            }
+        };
+        if claim.name.space().to_array().len() != depth || seen.contains(&claim.name) {
+            continue;
+        }
+        seen.push(claim.name.clone());
+        let Some(pubkey) = lookup_pubkey(lk, &claim.name)? else {
+            continue;
+        };
+        let current = *claim::rotation_chain(&reader, pubkey)?.last().unwrap();
+        if !members.contains(&current) {
+            members.push(current);
+        }
+    }
+    Ok(members)
+}
+
 pub fn lookup_claim(lk: &Linkspace, name: &Name) -> anyhow::Result<Option<Claim>> {
     lookup_live_chain(lk, name, &mut |_| Ok(())).map(|o| o.ok().map(|o| o.claim))
 }
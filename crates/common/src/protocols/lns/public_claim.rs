@@ -1,6 +1,8 @@
 use crate::prelude::*;
-use crate::protocols::lns::{LNS, VOTE_TAG};
-use linkspace_core::prelude::query_mode::Mode;
+use crate::protocols::lns::{LNS, ROOT_CLAIM_TAG, ROOT_SET_P, ROOT_SIG_TAG, VOTE_TAG};
+use anyhow::{ensure, Context};
+use linkspace_core::prelude::query_mode::{Mode, Order};
+use linkspace_core::prelude::RecvPktPtr;
 use linkspace_pkt::reroute::RecvPkt;
 use linkspace_pkt::utils::LkHashMap;
 use thiserror::Error;
@@ -29,6 +31,82 @@ pub fn root_claim() -> LiveClaim {
     }
 }
 
+/// The latest lns:[#:0]:/root-set entry
+pub fn root_set_entry<'o>(
+    reader: &'o ReadTxn,
+    admin: Option<PubKey>,
+) -> anyhow::Result<Option<RecvPktPtr<'o>>> {
+    let mut preds = PktPredicates::from_gd(PRIVATE, LNS)
+        .space(ROOT_SET_P.space())?
+        .create_before(now())?;
+    if let Some(v) = admin {
+        preds.pubkey.add(TestOp::Equal, v.into())
+    }
+    Ok(reader.query_tree(Order::Desc, &preds).next())
+}
+
+/// Read the root claim and its votes a root-set entry points to. Returns None if the entry restores the builtin roots.
+pub fn read_root_set(reader: &ReadTxn, root_set: &dyn NetPkt) -> anyhow::Result<Option<LiveClaim>> {
+    let mut links = root_set.get_links().iter();
+    let claim_hash = match links.next() {
+        None => return Ok(None),
+        Some(l) if l.tag == ROOT_CLAIM_TAG => l.ptr,
+        Some(l) => anyhow::bail!("expected a {} link - found {l}", ROOT_CLAIM_TAG),
+    };
+    let claim = Claim::root(reader.read(&claim_hash)?.context("missing root claim")?)?;
+    let mut signatures = vec![];
+    for link in links.filter(|l| l.tag == ROOT_SIG_TAG) {
+        let vote = reader.read(&link.ptr)?.context("missing root vote")?;
+        let auth = vote.pubkey().context("vote is not a keypoint")?;
+        ensure!(
+            claim.authorities().any(|a| a == *auth),
+            "vote {} by {auth} who is not a root authority",
+            link.ptr
+        );
+        ensure!(
+            vote.get_links().first().map(|l| (l.tag, l.ptr)) == Some((VOTE_TAG, claim_hash)),
+            "vote {} is not for {claim_hash}",
+            link.ptr
+        );
+        signatures.push(vote.owned());
+    }
+    Ok(Some(LiveClaim {
+        claim,
+        signatures,
+        parent: None,
+    }))
+}
+
+/// The root-set entry if one is set and valid, otherwise the builtin [root_claim].
+pub fn active_root_claim(
+    reader: &ReadTxn,
+    issue_handler: IssueHandler,
+) -> anyhow::Result<LiveClaim> {
+    if let Some(entry) = root_set_entry(reader, None)? {
+        match read_root_set(reader, &entry) {
+            Ok(Some(root)) => return Ok(root),
+            Ok(None) => {}
+            Err(error) => issue_handler(Issue::BadRootSet {
+                root_set: entry.pkt.hash(),
+                error,
+            })?,
+        }
+    }
+    Ok(root_claim())
+}
+
+/// Create a lns:[#:0]:/root-set entry. `None` restores the builtin roots.
+pub fn root_set_pkt(root: Option<(LkHash, &[LkHash])>, admin: Option<&SigningKey>) -> NetPktBox {
+    let links: Vec<Link> = root
+        .into_iter()
+        .flat_map(|(claim, votes)| {
+            std::iter::once(Link::new(ROOT_CLAIM_TAG, claim))
+                .chain(votes.iter().map(|v| Link::new(ROOT_SIG_TAG, *v)))
+        })
+        .collect();
+    point(PRIVATE, LNS, &ROOT_SET_P, &links, &[], now(), admin, ()).as_netbox()
+}
+
 pub type IssueHandler<'o> = &'o mut dyn FnMut(Issue) -> anyhow::Result<()>;
 
 pub type Voteing = (LkHash, (Option<Claim>, Vec<RecvPkt>));
@@ -50,6 +128,19 @@ pub enum Issue {
     },
     #[error("a claim we don't know was voted {0}")]
     MissingClaim(LkHash),
+    #[error("the root-set {root_set} is invalid - using the builtin roots: {error:?}")]
+    BadRootSet {
+        root_set: LkHash,
+        error: anyhow::Error,
+    },
+    #[error("the claim {claim} has an invalid threshold - no sub claim is live: {error:?}")]
+    BadThreshold { claim: LkHash, error: anyhow::Error },
+    #[error("the most voted claim {claim} has {votes} of the {threshold} required votes")]
+    BelowThreshold {
+        claim: LkHash,
+        votes: usize,
+        threshold: usize,
+    },
     #[error("a tied between claims")]
     Tie(Vec<Voteing>),
 }
@@ -65,29 +156,19 @@ pub fn walk_live_claims(
         Some(v) => v,
         None => return Ok(Ok(parent)),
     };
-    let rspace = parent
-        .claim
-        .pkt
-        .get_rooted_spacename()
-        .into_buf()
-        .append(sub);
-    let mut predicates =
-        Query::dgsk(LNS, *parent.claim.pkt.get_group(), rspace, B64([255; 32])).predicates;
-    predicates.depth.add(crate::core::prelude::TestOp::Equal, 2);
+    let mut predicates = vote_predicates(&parent.claim, sub);
     let mut claim_votes: LkHashMap<(Option<Claim>, Vec<RecvPkt>)> = Default::default();
-    let mut _count = 0;
-    let max_required_votes = (parent.claim.authorities().count() + 1) / 2;
+    let threshold = match required_votes(&parent.claim, issue_handler)? {
+        Some(t) => t,
+        None => return Ok(Err(parent)),
+    };
+    let max_required_votes = ((parent.claim.authorities().count() + 1) / 2).max(threshold);
     for auth in parent.claim.authorities() {
-        _count = 0;
-        predicates.pubkey = TestSet::new_eq(auth.into());
-        match reader
-            .query(Mode::TREE_DESC, &predicates, &mut _count)?
-            .next()
-        {
+        match latest_vote(reader, &mut predicates, auth)? {
             Some(vote) => match vote.get_links().first() {
                 Some(l) if l.tag == VOTE_TAG => match claim_votes.entry(l.ptr) {
                     std::collections::hash_map::Entry::Occupied(mut o) => {
-                        o.get_mut().1.push(vote.owned());
+                        o.get_mut().1.push(vote);
                         if o.get().1.len() >= max_required_votes {
                             break;
                         };
@@ -111,11 +192,11 @@ pub fn walk_live_claims(
                                 None
                             }
                         };
-                        v.insert((claim, vec![vote.owned()]));
+                        v.insert((claim, vec![vote]));
                     }
                 },
                 _ => {
-                    issue_handler(Issue::UnknownVoteFmt(vote.owned()))?;
+                    issue_handler(Issue::UnknownVoteFmt(vote))?;
                 }
             },
             None => issue_handler(Issue::NoVote {
@@ -151,6 +232,14 @@ pub fn walk_live_claims(
 
     votes_by_claim.sort_by_key(order);
     let live = votes_by_claim.pop().unwrap();
+    if live.1 .1.len() < threshold {
+        issue_handler(Issue::BelowThreshold {
+            claim: live.0,
+            votes: live.1 .1.len(),
+            threshold,
+        })?;
+        return Ok(Err(parent));
+    }
     let mut ties: Vec<_> = votes_by_claim
         .into_iter()
         .take_while(|p| order(&live) == order(p))
@@ -177,3 +266,54 @@ pub fn walk_live_claims(
         }
     }
 }
+
+/// The [Claim::threshold] of a parent claim. An invalid threshold is reported and returns None - no sub claim is live.
+pub fn required_votes(
+    parent: &Claim,
+    issue_handler: IssueHandler,
+) -> anyhow::Result<Option<usize>> {
+    match parent.threshold() {
+        Ok(t) => Ok(Some(t)),
+        Err(error) => {
+            issue_handler(Issue::BadThreshold {
+                claim: parent.pkt.hash(),
+                error,
+            })?;
+            Ok(None)
+        }
+    }
+}
+
+fn vote_predicates(parent: &Claim, sub: &[u8]) -> PktPredicates {
+    let rspace = parent.pkt.get_rooted_spacename().into_buf().append(sub);
+    let mut predicates =
+        Query::dgsk(LNS, *parent.pkt.get_group(), rspace, B64([255; 32])).predicates;
+    predicates.depth.add(crate::core::prelude::TestOp::Equal, 2);
+    predicates
+}
+
+fn latest_vote(
+    reader: &ReadTxn,
+    predicates: &mut PktPredicates,
+    auth: PubKey,
+) -> anyhow::Result<Option<RecvPkt>> {
+    let mut _count = 0;
+    predicates.pubkey = TestSet::new_eq(auth.into());
+    Ok(reader
+        .query(Mode::TREE_DESC, predicates, &mut _count)?
+        .next()
+        .map(|vote| vote.owned()))
+}
+
+/// The latest vote of every authority in `parent` for its sub claim `sub`.
+pub fn authority_votes(
+    reader: &ReadTxn,
+    parent: &Claim,
+    sub: &[u8],
+) -> anyhow::Result<Vec<(PubKey, Option<RecvPkt>)>> {
+    let mut predicates = vote_predicates(parent, sub);
+    parent
+        .authorities()
+        .map(|auth| Ok((auth, latest_vote(reader, &mut predicates, auth)?)))
+        .collect()
+}
@@ -0,0 +1,275 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::time::Duration;
+
+use crate::{
+    prelude::{lmdb::BTreeEnv, *},
+    runtime::Linkspace,
+};
+
+use super::{
+    claim::{lookup_rotation, rotation, rotation_chain, vote, Claim},
+    name::Name,
+    public_claim::{active_root_claim, root_claim, root_set_pkt, Issue},
+    utils::revalidate_public_claims,
+    *,
+};
+
+fn open(name: &str) -> Linkspace {
+    let dir = std::env::temp_dir().join("lk-lns-tests").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    let env = BTreeEnv::open(dir, true).unwrap();
+    Linkspace::new_opt_rt(env, Default::default())
+}
+
+/// save and process. Sleeps so the packets are created before the next `now()`
+fn save(lk: &Linkspace, pkts: &[&dyn NetPkt]) {
+    for pkt in pkts {
+        lk.env().save_dyn_one(*pkt).unwrap();
+    }
+    std::thread::sleep(Duration::from_millis(1));
+    lk.process();
+}
+
+/// A root claim with the keys as authorities
+fn root(keys: &[&SigningKey], data: &[u8]) -> Claim {
+    let mut links: Vec<Link> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let tag = lnstag(Stamp::ZERO, &[b'a' + i as u8], b'^').unwrap();
+            Link::new(tag, key.pubkey())
+        })
+        .collect();
+    links[0].tag[0..8].copy_from_slice(&Stamp::MAX.0);
+    let pkt = linkpoint(PUBLIC, LNS, &CLAIM_PREFIX, &links, data, now(), ()).as_netbox();
+    Claim::root(pkt).unwrap()
+}
+
+fn claim(name: &Name, pubkey: PubKey) -> Claim {
+    Claim::new(
+        name.clone(),
+        Stamp::MAX,
+        &mut [Link::new(PUBKEY_TAG, pubkey)],
+        &[],
+    )
+    .unwrap()
+}
+
+fn active_root(lk: &Linkspace) -> (LkHash, Vec<Issue>) {
+    let mut issues = vec![];
+    let root = active_root_claim(&lk.get_reader(), &mut |i| {
+        issues.push(i);
+        Ok(())
+    })
+    .unwrap();
+    (root.claim.pkt.hash(), issues)
+}
+
+fn is_live(lk: &Linkspace, claim: &Claim) -> bool {
+    lookup_claim(lk, &claim.name)
+        .unwrap()
+        .map_or(false, |c| c.pkt.hash() == claim.pkt.hash())
+}
+
+/// The hash of the live claim for name and the issues found
+fn lookup(lk: &Linkspace, name: &Name) -> (Option<LkHash>, Vec<Issue>) {
+    let mut issues = vec![];
+    let live = lookup_live_chain(lk, name, &mut |i| {
+        issues.push(i);
+        Ok(())
+    })
+    .unwrap();
+    (live.ok().map(|l| l.claim.pkt.hash()), issues)
+}
+
+/// revalidate and return whether claim is live
+fn revalidate(lk: &Linkspace, claim: &Claim) -> bool {
+    let reader = lk.get_reader();
+    let all: Vec<(Claim, bool)> = revalidate_public_claims(lk, &reader)
+        .collect::<anyhow::Result<_>>()
+        .unwrap();
+    let hash = claim.pkt.hash();
+    all.into_iter()
+        .find(|(c, _)| c.pkt.hash() == hash)
+        .expect("revalidate lists the claim")
+        .1
+}
+
+#[test]
+fn root_set_replaces_roots() {
+    let lk = open("root_set_replaces_roots");
+    let builtin = root_claim().claim.pkt.hash();
+    let (a, b) = (SigningKey::generate(), SigningKey::generate());
+    let (root_a, root_b) = (root(&[&a], &[]), root(&[&b], &[]));
+    let test = claim(&Name::from(&[b"test".as_slice()]).unwrap(), a.pubkey());
+    let vote_a = vote(&test, &a, &[]).unwrap();
+    save(&lk, &[&root_a.pkt, &root_b.pkt, &test.pkt, &vote_a]);
+
+    assert_eq!(active_root(&lk).0, builtin);
+    assert!(!is_live(&lk, &test), "not voted by the builtin roots");
+
+    save(&lk, &[&root_set_pkt(Some((root_a.pkt.hash(), &[])), None)]);
+    let (active, issues) = active_root(&lk);
+    assert_eq!(active, root_a.pkt.hash());
+    assert!(issues.is_empty());
+    assert!(is_live(&lk, &test));
+    assert!(revalidate(&lk, &test));
+
+    // a vote by someone other than its authorities makes the root-set invalid
+    let bad_vote = vote(&root_a, &b, &[]).unwrap();
+    let bad_set = root_set_pkt(Some((root_a.pkt.hash(), &[bad_vote.hash()])), None);
+    save(&lk, &[&bad_vote, &bad_set]);
+    let (active, issues) = active_root(&lk);
+    assert_eq!(active, builtin);
+    assert!(
+        matches!(&issues[..], [Issue::BadRootSet { root_set, .. }] if *root_set == bad_set.hash())
+    );
+
+    // the claim is not live under a root whose authorities did not vote for it
+    let root_vote = vote(&root_b, &b, &[]).unwrap();
+    let set_b = root_set_pkt(Some((root_b.pkt.hash(), &[root_vote.hash()])), None);
+    save(&lk, &[&root_vote, &set_b]);
+    assert_eq!(active_root(&lk).0, root_b.pkt.hash());
+    assert!(!is_live(&lk, &test));
+    assert!(!revalidate(&lk, &test));
+
+    save(&lk, &[&vote(&test, &b, &[]).unwrap()]);
+    assert!(is_live(&lk, &test));
+    assert!(revalidate(&lk, &test));
+
+    save(&lk, &[&root_set_pkt(None, None)]);
+    assert_eq!(active_root(&lk).0, builtin);
+    assert!(!revalidate(&lk, &test));
+}
+
+/// Save a root with the keys as authorities and make it the active root
+fn threshold_root(lk: &Linkspace, keys: &[&SigningKey; 3], threshold: &[u8]) -> Claim {
+    let root = root(keys, threshold);
+    save(lk, &[&root.pkt]);
+    save(lk, &[&root_set_pkt(Some((root.pkt.hash(), &[])), None)]);
+    root
+}
+
+#[test]
+fn threshold_votes() {
+    let lk = open("threshold_votes");
+    let (a, b, c) = (
+        SigningKey::generate(),
+        SigningKey::generate(),
+        SigningKey::generate(),
+    );
+    threshold_root(&lk, &[&a, &b, &c], b"threshold:2\n");
+    let name = Name::from(&[b"test".as_slice()]).unwrap();
+    let test = claim(&name, a.pubkey());
+    save(&lk, &[&test.pkt, &vote(&test, &a, &[]).unwrap()]);
+
+    let (live, issues) = lookup(&lk, &name);
+    assert_eq!(live, None);
+    assert!(issues.iter().any(|i| matches!(i,
+        Issue::BelowThreshold { claim, votes: 1, threshold: 2 } if *claim == test.pkt.hash()
+    )));
+
+    save(&lk, &[&vote(&test, &b, &[]).unwrap()]);
+    let (live, issues) = lookup(&lk, &name);
+    assert_eq!(live, Some(test.pkt.hash()));
+    assert!(!issues
+        .iter()
+        .any(|i| matches!(i, Issue::BelowThreshold { .. })));
+
+    // two claims with a vote each do not reach the threshold
+    let split = Name::from(&[b"split".as_slice()]).unwrap();
+    let (x, y) = (claim(&split, a.pubkey()), claim(&split, b.pubkey()));
+    let (vote_x, vote_y) = (vote(&x, &a, &[]).unwrap(), vote(&y, &b, &[]).unwrap());
+    save(&lk, &[&x.pkt, &y.pkt, &vote_x, &vote_y]);
+    let (live, issues) = lookup(&lk, &split);
+    assert_eq!(live, None);
+    assert!(issues.iter().any(|i| matches!(
+        i,
+        Issue::BelowThreshold {
+            votes: 1,
+            threshold: 2,
+            ..
+        }
+    )));
+}
+
+#[test]
+fn bad_threshold_fails_closed() {
+    let lk = open("bad_threshold_fails_closed");
+    let (a, b, c) = (
+        SigningKey::generate(),
+        SigningKey::generate(),
+        SigningKey::generate(),
+    );
+    let root = threshold_root(&lk, &[&a, &b, &c], b"threshold:4\n");
+    let name = Name::from(&[b"test".as_slice()]).unwrap();
+    let test = claim(&name, a.pubkey());
+    let votes: Vec<_> = [&a, &b, &c]
+        .into_iter()
+        .map(|k| vote(&test, k, &[]).unwrap())
+        .collect();
+    save(&lk, &[&test.pkt, &votes[0], &votes[1], &votes[2]]);
+
+    let (live, issues) = lookup(&lk, &name);
+    assert_eq!(live, None, "an invalid threshold makes no sub claim live");
+    assert!(
+        matches!(&issues[..], [Issue::BadThreshold { claim, .. }] if *claim == root.pkt.hash())
+    );
+}
+
+#[test]
+fn rotation_chains() {
+    let lk = open("rotation_chains");
+    let [a, b, c, d, mallory] = std::array::from_fn(|_| SigningKey::generate());
+    let chain = |key: &SigningKey| rotation_chain(&lk.get_reader(), key.pubkey()).unwrap();
+    assert_eq!(chain(&a), vec![a.pubkey()]);
+
+    // b never named c as its successor
+    save(
+        &lk,
+        &[
+            &rotation(&a, b.pubkey(), &[]),
+            &rotation(&c, d.pubkey(), &[]),
+        ],
+    );
+    assert_eq!(chain(&a), vec![a.pubkey(), b.pubkey()]);
+
+    save(&lk, &[&rotation(&b, c.pubkey(), &[])]);
+    assert_eq!(
+        chain(&a),
+        vec![a.pubkey(), b.pubkey(), c.pubkey(), d.pubkey()]
+    );
+
+    // a rotation is only read for the key that signed it
+    save(&lk, &[&rotation(&mallory, a.pubkey(), b"a")]);
+    assert_eq!(
+        lookup_rotation(&lk.get_reader(), a.pubkey()).unwrap(),
+        Some(b.pubkey())
+    );
+    assert_eq!(
+        chain(&mallory),
+        vec![
+            mallory.pubkey(),
+            a.pubkey(),
+            b.pubkey(),
+            c.pubkey(),
+            d.pubkey()
+        ]
+    );
+    assert_eq!(chain(&d), vec![d.pubkey()]);
+
+    // a loop ends the chain
+    save(&lk, &[&rotation(&d, a.pubkey(), &[])]);
+    assert_eq!(
+        chain(&a),
+        vec![a.pubkey(), b.pubkey(), c.pubkey(), d.pubkey()]
+    );
+
+    // the latest rotation of a key replaces the previous one
+    save(&lk, &[&rotation(&a, d.pubkey(), &[])]);
+    assert_eq!(chain(&a), vec![a.pubkey(), d.pubkey()]);
+}
@@ -4,9 +4,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use crate::prelude::*;
+use crate::{prelude::*, runtime::Linkspace};
 
-use super::{claim::Claim, name::Name, LNS};
+use super::{claim::Claim, name::Name, CLAIM_PREFIX, LNS};
 
 pub fn list_all_potential_claims_with_prefix<'o>(
     reader: &'o ReadTxn,
@@ -35,6 +35,37 @@ pub fn list_all_potential_claims_with_prefix<'o>(
     )
 }
 
+/// Check every known public claim against the active roots. Yields each claim and whether it is (still) live.
+pub fn revalidate_public_claims<'o>(
+    lk: &'o Linkspace,
+    reader: &'o ReadTxn,
+) -> impl Iterator<Item = anyhow::Result<(Claim, bool)>> + 'o {
+    let now = now();
+    let mut preds = PktPredicates::from_gd(PUBLIC, LNS)
+        .create_before(now)
+        .unwrap();
+    let _ = preds.prefix(CLAIM_PREFIX.space());
+    // skip the root claims themselves
+    preds.depth.add(TestOp::Greater, 1);
+    reader
+        .query_tree(query_mode::Order::Desc, &preds)
+        // votes are keypoints in the same spaces
+        .filter(|pkt| pkt.is_linkpoint())
+        .filter_map(move |pkt| -> Option<anyhow::Result<(Claim, bool)>> {
+            let claim = match Claim::from(pkt) {
+                Ok(c) if c.until() > now => c,
+                Ok(_) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let is_live = match super::lookup_live_chain(lk, &claim.name, &mut |_| Ok(())) {
+                Ok(Ok(live)) => live.claim.pkt.hash() == claim.pkt.hash(),
+                Ok(Err(_)) => false,
+                Err(e) => return Some(Err(e)),
+            };
+            Some(Ok((claim, is_live)))
+        })
+}
+
 pub type TaggedClaim = ((Stamp, [u8; 8]), anyhow::Result<Option<Claim>>);
 pub fn list_all_reverse_lookups(
     _reader: &ReadTxn,
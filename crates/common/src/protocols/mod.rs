@@ -5,6 +5,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 use linkspace_core::prelude::{GroupID, PubKey};
 
+pub mod daemon;
 pub mod handshake;
 
 #[cfg(feature = "runtime")]
@@ -291,7 +291,20 @@ impl Linkspace {
             }
             count = Rc::strong_count(&txn)
         };
-        for pkt in txn.pkts_after(from) {
+        // skip the scan if the bus tells us no watch can match what was written
+        let can_skip = exec.env.log_bloom(from, upto).is_some_and(|bloom| {
+            !lock
+                .entries()
+                .iter()
+                .any(|e| bloom.may_match(&e.query.predicates))
+        });
+        let scan_from = if can_skip {
+            tracing::debug!("no watch can match the new packets");
+            upto
+        } else {
+            from
+        };
+        for pkt in txn.pkts_after(scan_from) {
             upto = pkt.recv;
             if pkt.net_header().flags.contains(NetFlags::SILENT) {
                 tracing::trace!("(not) skipping silent pkt - TODO make this a option");
@@ -524,3 +537,80 @@ impl Timer for Linkspace {
         self.0.spawner.get().expect("No Spawner Set").sleep(dur)
     }
 }
+
+#[test]
+fn skip_unmatched_batches() {
+    let dir = std::env::temp_dir()
+        .join("lk-rx-tests")
+        .join("skip_unmatched_batches");
+    let _ = std::fs::remove_dir_all(&dir);
+    let env = BTreeEnv::open(dir, true).unwrap();
+    let lk = Linkspace::new_opt_rt(env.clone(), Default::default());
+    let scope = linkspace_core::eval::core_scope();
+    let hits = Rc::new(RefCell::new(vec![]));
+    let queries: Vec<Query> = [
+        ("match", "domain:=:test\ngroup:=:[#:pub]"),
+        ("other_domain", "domain:=:other\ngroup:=:[#:pub]"),
+        ("other_group", "domain:=:test\ngroup:=:[#:0]"),
+    ]
+    .into_iter()
+    .map(|(qid, stmnts)| {
+        let mut q = Query::default();
+        q.parse(
+            format!("{stmnts}\ni_db:<:[u32:0]\n:qid:{qid}").as_bytes(),
+            &scope,
+        )
+        .unwrap();
+        let hits = hits.clone();
+        lk.watch_query(
+            &q,
+            move |_: &dyn NetPkt, _: &Linkspace| -> ControlFlow<()> {
+                hits.borrow_mut().push(qid);
+                ControlFlow::Continue(())
+            },
+            Span::none(),
+        )
+        .unwrap();
+        q
+    })
+    .collect();
+    let save = |domain: &[u8]| {
+        let pkt = linkpoint(
+            PUBLIC,
+            ab(domain),
+            &rspace_buf(&[b"x"]),
+            &[],
+            &[],
+            now(),
+            (),
+        );
+        env.save_dyn_one(&pkt).unwrap();
+    };
+    // the bloom of everything since the last process - None if a batch is unknown
+    let bloom_since = |from: Stamp| env.log_bloom(from, env.new_read_txn().unwrap().log_head());
+
+    let mut head = lk.process();
+    save(b"skip");
+    let bloom = bloom_since(head).expect("a known batch");
+    assert!(queries.iter().all(|q| !bloom.may_match(&q.predicates)));
+    head = lk.process();
+    assert!(hits.borrow().is_empty());
+
+    save(b"test");
+    head = lk.process();
+    assert_eq!(*hits.borrow(), ["match"]);
+
+    // more batches than the bus keeps (64) - the summary of the first is gone
+    for _ in 0..=64 {
+        save(b"skip");
+    }
+    save(b"test");
+    assert!(bloom_since(head).is_none());
+    head = lk.process();
+    assert_eq!(*hits.borrow(), ["match", "match"]);
+
+    save(b"skip");
+    assert!(bloom_since(head).is_some());
+    lk.process();
+    assert_eq!(*hits.borrow(), ["match", "match"]);
+}
@@ -122,6 +122,9 @@ pub(crate) fn open(path: &Path, make_dir: bool) -> std::io::Result<LMDBEnv> {
             DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED,
         )
         .unwrap();
+    let quota = env
+        .create_db(Some("quota"), DatabaseFlags::empty())
+        .unwrap();
     let uid: [u8; 8] = std::fs::read(idfile)
         .unwrap()
         .try_into()
@@ -131,6 +134,7 @@ pub(crate) fn open(path: &Path, make_dir: bool) -> std::io::Result<LMDBEnv> {
         pktlog,
         tree,
         hash,
+        quota,
         env,
         uid,
     })
@@ -149,6 +153,8 @@ pub(crate) struct LMDBEnv {
     pub(crate) pktlog: Database,
     pub(crate) tree: Database,
     pub(crate) hash: Database,
+    /// quota config and usage - see [crate::env::quota]
+    pub(crate) quota: Database,
 }
 pub struct LMDBTxn<'env> {
     pub(crate) txn: RoTransaction<'env>,
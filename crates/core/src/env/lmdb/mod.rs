@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::LNS_ROOTS;
-pub use ipcbus::ProcBus;
+pub use ipcbus::{batch::Batch, ProcBus};
 use linkspace_pkt::{NetPkt, NetPktPtr, Stamp, PUBLIC_GROUP_PKT};
 use lmdb_sys::MDB_envinfo;
 
@@ -17,12 +17,17 @@ use self::{
     get::ReadTxn,
 };
 
-use super::misc::SaveState;
+use super::{
+    log_bloom::LogBloom,
+    misc::SaveState,
+    quota::{Quota, QuotaKind},
+};
 
 pub mod db;
 pub mod db_info;
 pub mod get;
 pub mod queries;
+pub mod quota;
 pub mod save;
 pub mod tree_iter;
 
@@ -79,12 +84,27 @@ impl BTreeEnv {
     pub fn next_deadline(&self, deadline: Option<std::time::Instant>) -> Option<u64> {
         self.0.log_head.next_deadline(deadline)
     }
+    /// The combined [LogBloom] of everything saved in (from,upto]. None if unknown, e.g. a write by another process over the udp bus.
+    pub fn log_bloom(&self, from: Stamp, upto: Stamp) -> Option<LogBloom> {
+        self.0
+            .log_head
+            .batch_bits(from.get(), upto.get())
+            .map(LogBloom::from_bits)
+    }
     // private generic function.
     fn save<P: NetPkt>(&self, pkts: &mut [(P, SaveState)]) -> io::Result<Range<u64>> {
-        let range = self.0.lmdb.save(pkts).map_err(db::as_io)?;
+        let (prev, range) = self.0.lmdb.save(pkts).map_err(db::as_io)?;
         tracing::trace!(?range, new = range.end - range.start, "save ok");
         if range.start < range.end {
-            let _ = self.0.log_head.emit(range.end - 1);
+            let mut bloom = LogBloom::default();
+            for (pkt, _) in pkts.iter().filter(|(_, state)| state.is_written()) {
+                bloom.add(pkt);
+            }
+            let _ = self.0.log_head.emit_batch(Batch {
+                prev,
+                end: range.end - 1,
+                bits: bloom.to_bits(),
+            });
         }
         Ok(range)
     }
@@ -103,6 +123,13 @@ impl BTreeEnv {
     pub fn lmdb_version(&self) -> LMDBVersion {
         self.0.lmdb.version_info()
     }
+    /// see [crate::env::quota]
+    pub fn quota_set(&self, kind: QuotaKind, id: &[u8], quota: Quota) -> io::Result<()> {
+        self.0.lmdb.quota_set(kind, id, quota).map_err(db::as_io)
+    }
+    pub fn quota_list(&self) -> io::Result<Vec<(QuotaKind, Vec<u8>, Quota)>> {
+        self.0.lmdb.quota_list().map_err(db::as_io)
+    }
 }
 
 // We have one private generic function, this instantiates one for Ptr and one for &dyn
@@ -0,0 +1,51 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use lmdb::{Cursor, Error, Transaction, WriteFlags};
+use lmdb_sys::{MDB_NEXT, MDB_SET_RANGE};
+
+use crate::env::quota::{config_key, parse_config_key, Quota, QuotaKind, CONFIG_PREFIX};
+
+use super::db::LMDBEnv;
+
+impl LMDBEnv {
+    /// set the quota for a kind and id (empty for the default). An unlimited quota removes it.
+    pub fn quota_set(&self, kind: QuotaKind, id: &[u8], quota: Quota) -> lmdb::Result<()> {
+        let mut txn = self.env.begin_rw_txn()?;
+        let key = config_key(kind, id);
+        if quota.is_unlimited() {
+            match txn.del(self.quota, &key, None) {
+                Ok(()) | Err(Error::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+        } else {
+            txn.put(self.quota, &key, &quota.to_bytes(), WriteFlags::empty())?;
+        }
+        txn.commit()
+    }
+    /// all quotas as (kind, id, quota)
+    pub fn quota_list(&self) -> lmdb::Result<Vec<(QuotaKind, Vec<u8>, Quota)>> {
+        let txn = self.env.begin_ro_txn()?;
+        let cur = txn.open_ro_cursor(self.quota)?;
+        let mut list = vec![];
+        let mut op = (Some(&[CONFIG_PREFIX][..]), MDB_SET_RANGE);
+        loop {
+            match cur.get(op.0, None, op.1) {
+                Ok((Some(key), val)) => {
+                    let Some((kind, id)) = parse_config_key(key) else {
+                        break;
+                    };
+                    if let Some(quota) = Quota::from_bytes(val) {
+                        list.push((kind, id.to_vec(), quota));
+                    }
+                }
+                Ok((None, _)) | Err(Error::NotFound) => break,
+                Err(e) => return Err(e),
+            }
+            op = (None, MDB_NEXT);
+        }
+        Ok(list)
+    }
+}
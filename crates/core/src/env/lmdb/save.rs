@@ -7,26 +7,31 @@ use linkspace_pkt::{
 };
 use lmdb::{RwCursor, Transaction, WriteFlags};
 
-use crate::env::misc::SaveState;
+use crate::env::{
+    misc::SaveState,
+    quota::{QuotaCheck, Usage, CONFIG_PREFIX, EXPIRE_BATCH, SWEEP_KEY, USAGE_PREFIX},
+};
 
 use super::db::LMDBEnv;
 
 impl LMDBEnv {
-    /// return first stamp used and last stamp. If first == last then nothing was written.
+    /// return the last stamp in the log before this txn (0 if empty), and the first stamp used and last stamp. If first == last then nothing was written.
     #[tracing::instrument(skip_all, err)]
-    pub fn save<P: NetPkt>(&self, pkts: &mut [(P, SaveState)]) -> lmdb::Result<Range<u64>> {
+    pub fn save<P: NetPkt>(&self, pkts: &mut [(P, SaveState)]) -> lmdb::Result<(u64, Range<u64>)> {
         use lmdb::Error;
         use lmdb_sys::*;
 
         let lmdb_e = &self;
-        let txn = lmdb_e.env.begin_rw_txn()?;
+        let mut txn = lmdb_e.env.begin_rw_txn()?;
 
         let pktlog = RwCursor::new(&txn, lmdb_e.pktlog)?;
 
         let mut start = now().get();
+        let mut prev = 0;
         match pktlog.ro().get(None, None, lmdb_sys::MDB_LAST) {
             Ok((Some(recv), _)) => {
                 let last: u64 = super::db::pktlog::val(recv.try_into().unwrap());
+                prev = last;
                 if last > start {
                     eprintln!("db log saved entries from the future? - this could become undefined behavior");
 
@@ -38,11 +43,46 @@ impl LMDBEnv {
             Err(e) => return Err(e),
         };
 
+        let mut quota_db = RwCursor::new(&txn, lmdb_e.quota)?;
+        let mut quotas = QuotaCheck::default();
+        let mut op = (Some(&[CONFIG_PREFIX][..]), MDB_SET_RANGE);
+        loop {
+            match quota_db.ro().get(op.0, None, op.1) {
+                Ok((Some(key), val)) if key.first() == Some(&CONFIG_PREFIX) => {
+                    quotas.add_config(key, val)
+                }
+                Ok(_) | Err(Error::NotFound) => break,
+                Err(e) => return Err(e),
+            }
+            op = (None, MDB_NEXT);
+        }
+
         let mut hash = RwCursor::new(&txn, lmdb_e.hash)?;
         let mut at = start;
 
         for (p, state) in pkts.iter_mut() {
             if matches!(state, SaveState::Pending) {
+                if !quotas.is_empty() {
+                    // an existing packet does not count towards a quota
+                    match hash.ro().get(Some(&p.hash_ref().0), None, MDB_SET) {
+                        Ok(_) => {
+                            *state = SaveState::Exists;
+                            continue;
+                        }
+                        Err(Error::NotFound) => {}
+                        Err(e) => return Err(e),
+                    }
+                    let admit = quotas.admit(&*p, at, |key| {
+                        match quota_db.ro().get(Some(key), None, MDB_SET) {
+                            Ok((_, val)) => Usage::from_bytes(val),
+                            Err(_) => None,
+                        }
+                    });
+                    if !admit {
+                        *state = SaveState::Rejected;
+                        continue;
+                    }
+                }
                 match hash.put(p.hash_ref(), &at.to_ne_bytes(), WriteFlags::NO_OVERWRITE) {
                     Ok(()) => {
                         at += 1;
@@ -60,7 +100,7 @@ impl LMDBEnv {
         tracing::trace!(total_new, start, end = at, "new txn for");
 
         if total_new == 0 {
-            return Ok(start..at);
+            return Ok((prev, start..at));
         };
         at = start;
         for (pkt, state) in pkts.iter() {
@@ -110,8 +150,55 @@ impl LMDBEnv {
             }
         }
         std::mem::drop(tree);
+        let mut expired = vec![];
+        let mut sweep = None;
+        let counted = quotas.changed_usage().next().is_some();
+        if counted {
+            // check a batch of usage entries, continuing at the key where the previous save stopped
+            let from = match quota_db.ro().get(Some(&SWEEP_KEY), None, MDB_SET) {
+                Ok((_, val)) => val.to_vec(),
+                Err(Error::NotFound) => vec![USAGE_PREFIX],
+                Err(e) => return Err(e),
+            };
+            let mut op = (Some(&from[..]), MDB_SET_RANGE);
+            let mut checked = 0;
+            loop {
+                match quota_db.ro().get(op.0, None, op.1) {
+                    Ok((Some(key), _)) if checked == EXPIRE_BATCH => {
+                        // None (at the end) starts the next batch at the first usage entry
+                        sweep = (key.first() == Some(&USAGE_PREFIX)).then(|| key.to_vec());
+                        break;
+                    }
+                    Ok((Some(key), val)) if key.first() == Some(&USAGE_PREFIX) => {
+                        if quotas.usage_expired(key, val, start) {
+                            expired.push(key.to_vec());
+                        }
+                        checked += 1;
+                    }
+                    Ok(_) | Err(Error::NotFound) => break,
+                    Err(e) => return Err(e),
+                }
+                op = (None, MDB_NEXT);
+            }
+            if let Some(key) = &sweep {
+                quota_db.put(&SWEEP_KEY, key, WriteFlags::empty())?;
+            }
+        }
+        for (key, usage) in quotas.changed_usage() {
+            quota_db.put(&key, &usage.to_bytes(), WriteFlags::empty())?;
+        }
+        std::mem::drop(quota_db);
+        for key in expired {
+            txn.del(lmdb_e.quota, &key, None)?;
+        }
+        if counted && sweep.is_none() {
+            match txn.del(lmdb_e.quota, &SWEEP_KEY, None) {
+                Ok(()) | Err(Error::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
 
         txn.commit()?;
-        Ok(start..at)
+        Ok((prev, start..at))
     }
 }
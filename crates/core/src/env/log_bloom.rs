@@ -0,0 +1,77 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use linkspace_pkt::{Domain, GroupID, NetPkt};
+
+use crate::predicate::pkt_predicates::PktPredicates;
+
+/// A bloom filter of the domains and groups of a write batch.
+/// Only linkpoints and keypoints are added - a domain or group predicate never matches a datapoint.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogBloom {
+    pub domains: u64,
+    pub groups: u64,
+}
+
+fn bit(bytes: &[u8]) -> u64 {
+    // fnv-1a
+    let h = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    1 << (h % 64)
+}
+
+impl LogBloom {
+    pub fn add(&mut self, pkt: &(impl NetPkt + ?Sized)) {
+        if let Some(domain) = pkt.domain() {
+            self.domains |= bit(&domain.0);
+        }
+        if let Some(group) = pkt.group() {
+            self.groups |= bit(&group.0);
+        }
+    }
+    /// false if no packet in the batch can match the predicates
+    pub fn may_match(&self, predicates: &PktPredicates) -> bool {
+        if let Some(domain) = predicates.domain.as_eq() {
+            if self.domains & bit(&Domain::from(domain).0) == 0 {
+                return false;
+            }
+        }
+        if let Some(group) = predicates.group.as_eq() {
+            if self.groups & bit(&GroupID::from(group).0) == 0 {
+                return false;
+            }
+        }
+        true
+    }
+    pub fn to_bits(self) -> [u64; 2] {
+        [self.domains, self.groups]
+    }
+    pub fn from_bits([domains, groups]: [u64; 2]) -> LogBloom {
+        LogBloom { domains, groups }
+    }
+}
+
+#[test]
+fn bloom() {
+    use crate::query::Query;
+    use linkspace_pkt::{ab, linkpoint, rspace_buf, Stamp, PUBLIC};
+    let rspace = rspace_buf(&[b"a"]);
+    let pkt = linkpoint(PUBLIC, ab(b"test"), &rspace, &[], &[], Stamp::ZERO, ());
+    let mut bloom = LogBloom::default();
+    assert!(bloom.may_match(&Query::default().predicates));
+    bloom.add(&pkt);
+
+    let scope = crate::eval::core_scope();
+    let matches = |stmts: &str| {
+        let mut q = Query::default();
+        q.parse(stmts.as_bytes(), &scope).unwrap();
+        bloom.may_match(&q.predicates)
+    };
+    assert!(matches("domain:=:test\ngroup:=:[#:pub]"));
+    assert!(matches("prefix:=:/other"));
+    assert!(!matches("domain:=:other\ngroup:=:[#:pub]"));
+    assert!(!matches("group:=:[#:0]"));
+}
@@ -13,6 +13,8 @@ pub enum SaveState {
     Error = 0b001,
     Exists = 0b010,
     Written = 0b110,
+    /// exceeds a quota (see [super::quota])
+    Rejected = 0b1000,
 }
 impl SaveState {
     pub fn is_written(&self) -> bool {
@@ -7,7 +7,9 @@
 
 use linkspace_pkt::{reroute::RecvPkt, NetPktPtr};
 
+pub mod log_bloom;
 pub mod misc;
+pub mod quota;
 pub mod tree_key;
 
 #[cfg(feature = "lmdb")]
@@ -0,0 +1,318 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/*!
+Quotas on saving packets received from elsewhere.
+
+A quota limits the packets per second and the bytes per day saved under a pubkey, group, or domain.
+Both the quotas and the usage are stored in the database and updated in the save transaction, so they hold across processes.
+
+Only packets with hop > 0 are counted, i.e. packets created locally are never rejected.
+A keypoint counts under its pubkey, its group, and its domain. A linkpoint under its group and domain.
+A datapoint has neither and counts under the group [#:0] and the domain of zeros.
+A packet exceeding any of its quotas is not saved and gets [SaveState::Rejected](super::misc::SaveState::Rejected).
+
+A quota with an empty id is the default for every id of that kind without its own quota.
+The usage is still counted per id. The usage of an id is removed once its day has passed.
+Every save that counts a packet checks at most [EXPIRE_BATCH] usage entries, continuing where the previous one stopped.
+*/
+use std::collections::HashMap;
+
+use linkspace_pkt::{NetPkt, NetPktExt, PointExt};
+
+const CONFIG: u8 = b'c';
+const USAGE: u8 = b'u';
+const SWEEP: u8 = b's';
+const PKTS_PER_SEC: u8 = 1;
+const BYTES_PER_DAY: u8 = 2;
+const SECOND: u64 = 1_000_000;
+const DAY: u64 = 86_400 * SECOND;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum QuotaKind {
+    PubKey = b'k',
+    Group = b'g',
+    Domain = b'd',
+}
+impl QuotaKind {
+    pub const ALL: [QuotaKind; 3] = [QuotaKind::PubKey, QuotaKind::Group, QuotaKind::Domain];
+    pub fn from_byte(b: u8) -> Option<QuotaKind> {
+        QuotaKind::ALL.into_iter().find(|k| *k as u8 == b)
+    }
+    /// the length of an id of this kind
+    pub fn id_len(self) -> usize {
+        match self {
+            QuotaKind::PubKey | QuotaKind::Group => 32,
+            QuotaKind::Domain => 16,
+        }
+    }
+}
+
+/// None is unlimited, 0 rejects every packet
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    pub pkts_per_sec: Option<u32>,
+    pub bytes_per_day: Option<u64>,
+}
+impl Quota {
+    pub fn is_unlimited(&self) -> bool {
+        self.pkts_per_sec.is_none() && self.bytes_per_day.is_none()
+    }
+    /// [flags] [pkts_per_sec] [bytes_per_day] - a flag is set for each limit
+    pub fn to_bytes(&self) -> [u8; 13] {
+        let mut b = [0; 13];
+        if self.pkts_per_sec.is_some() {
+            b[0] |= PKTS_PER_SEC;
+        }
+        if self.bytes_per_day.is_some() {
+            b[0] |= BYTES_PER_DAY;
+        }
+        b[1..5].copy_from_slice(&self.pkts_per_sec.unwrap_or(0).to_be_bytes());
+        b[5..].copy_from_slice(&self.bytes_per_day.unwrap_or(0).to_be_bytes());
+        b
+    }
+    pub fn from_bytes(b: &[u8]) -> Option<Quota> {
+        let b: [u8; 13] = b.try_into().ok()?;
+        let pps = u32::from_be_bytes(b[1..5].try_into().unwrap());
+        let bpd = u64::from_be_bytes(b[5..].try_into().unwrap());
+        Some(Quota {
+            pkts_per_sec: (b[0] & PKTS_PER_SEC != 0).then_some(pps),
+            bytes_per_day: (b[0] & BYTES_PER_DAY != 0).then_some(bpd),
+        })
+    }
+}
+
+/// The usage in the current second and day
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub second: u64,
+    pub pkts: u32,
+    pub day: u64,
+    pub bytes: u64,
+}
+impl Usage {
+    /// the usage at stamp - counters of a passed window are reset
+    pub fn at(self, stamp: u64) -> Usage {
+        let (second, day) = (stamp / SECOND, stamp / DAY);
+        Usage {
+            second,
+            pkts: if second == self.second { self.pkts } else { 0 },
+            day,
+            bytes: if day == self.day { self.bytes } else { 0 },
+        }
+    }
+    pub fn admits(&self, quota: &Quota, size: u64) -> bool {
+        quota.pkts_per_sec.map_or(true, |max| self.pkts < max)
+            && quota
+                .bytes_per_day
+                .map_or(true, |max| self.bytes.saturating_add(size) <= max)
+    }
+    pub fn to_bytes(&self) -> [u8; 28] {
+        let mut b = [0; 28];
+        b[..8].copy_from_slice(&self.second.to_be_bytes());
+        b[8..12].copy_from_slice(&self.pkts.to_be_bytes());
+        b[12..20].copy_from_slice(&self.day.to_be_bytes());
+        b[20..].copy_from_slice(&self.bytes.to_be_bytes());
+        b
+    }
+    pub fn from_bytes(b: &[u8]) -> Option<Usage> {
+        let b: [u8; 28] = b.try_into().ok()?;
+        Some(Usage {
+            second: u64::from_be_bytes(b[..8].try_into().unwrap()),
+            pkts: u32::from_be_bytes(b[8..12].try_into().unwrap()),
+            day: u64::from_be_bytes(b[12..20].try_into().unwrap()),
+            bytes: u64::from_be_bytes(b[20..].try_into().unwrap()),
+        })
+    }
+}
+
+/// [CONFIG] [kind] [id]
+pub fn config_key(kind: QuotaKind, id: &[u8]) -> Vec<u8> {
+    [&[CONFIG, kind as u8], id].concat()
+}
+/// [USAGE] [kind] [id]
+fn usage_key(kind: QuotaKind, id: &[u8]) -> Vec<u8> {
+    [&[USAGE, kind as u8], id].concat()
+}
+/// the first byte of every config key
+pub const CONFIG_PREFIX: u8 = CONFIG;
+/// the first byte of every usage key
+pub const USAGE_PREFIX: u8 = USAGE;
+/// the key storing the usage key where the next expiry check starts
+pub const SWEEP_KEY: [u8; 1] = [SWEEP];
+/// the maximum number of usage entries checked for expiry in a save
+pub const EXPIRE_BATCH: usize = 64;
+/// the kind and id of a config key
+pub fn parse_config_key(key: &[u8]) -> Option<(QuotaKind, &[u8])> {
+    match key {
+        [CONFIG, kind, id @ ..] => Some((QuotaKind::from_byte(*kind)?, id)),
+        _ => None,
+    }
+}
+
+/// The quotas and the usage of a single save transaction
+#[derive(Debug, Default)]
+pub struct QuotaCheck {
+    config: HashMap<Vec<u8>, Quota>,
+    /// only the usage changed in this transaction
+    usage: HashMap<Vec<u8>, Usage>,
+}
+impl QuotaCheck {
+    /// add a stored config entry. Other keys are ignored
+    pub fn add_config(&mut self, key: &[u8], val: &[u8]) {
+        if let (Some(_), Some(quota)) = (parse_config_key(key), Quota::from_bytes(val)) {
+            self.config.insert(key.to_vec(), quota);
+        }
+    }
+    /// true if no quota is set
+    pub fn is_empty(&self) -> bool {
+        self.config.is_empty()
+    }
+    /// Count the packet and return true if it is within its quotas.
+    /// load reads the stored usage of a usage key.
+    pub fn admit(
+        &mut self,
+        pkt: &(impl NetPkt + ?Sized),
+        stamp: u64,
+        mut load: impl FnMut(&[u8]) -> Option<Usage>,
+    ) -> bool {
+        if self.config.is_empty() || pkt.net_header().hop.get() == 0 {
+            return true;
+        }
+        let size = pkt.size() as u64;
+        let ids: [(QuotaKind, &[u8]); 3] = [
+            (
+                QuotaKind::PubKey,
+                pkt.pubkey().map(|k| &k.0[..]).unwrap_or_default(),
+            ),
+            (QuotaKind::Group, &pkt.get_group().0),
+            (QuotaKind::Domain, &pkt.get_domain().0),
+        ];
+        let mut counted = Vec::with_capacity(3);
+        for (kind, id) in ids {
+            if id.is_empty() {
+                continue;
+            }
+            let quota = self
+                .config
+                .get(&config_key(kind, id))
+                .or_else(|| self.config.get(&config_key(kind, &[])));
+            let Some(quota) = quota else { continue };
+            let key = usage_key(kind, id);
+            let usage = match self.usage.get(&key) {
+                Some(u) => *u,
+                None => load(&key).unwrap_or_default(),
+            }
+            .at(stamp);
+            if !usage.admits(quota, size) {
+                tracing::info!(hash=%pkt.hash_ref(), ?kind, ?quota, ?usage, "quota exceeded");
+                return false;
+            }
+            counted.push((key, usage));
+        }
+        for (key, mut usage) in counted {
+            usage.pkts = usage.pkts.saturating_add(1);
+            usage.bytes = usage.bytes.saturating_add(size);
+            self.usage.insert(key, usage);
+        }
+        true
+    }
+    /// the usage to store after the packets are saved
+    pub fn changed_usage(&self) -> impl Iterator<Item = (&[u8], Usage)> {
+        self.usage.iter().map(|(k, u)| (k.as_slice(), *u))
+    }
+    /// true if a stored usage entry is not changed and its day has passed at stamp, i.e. it counts nothing and can be removed
+    pub fn usage_expired(&self, key: &[u8], val: &[u8], stamp: u64) -> bool {
+        !self.usage.contains_key(key)
+            && Usage::from_bytes(val).map_or(true, |u| u.day < stamp / DAY)
+    }
+}
+
+#[test]
+fn quota_check() {
+    use linkspace_pkt::{
+        ab, linkpoint, reroute::ReroutePkt, rspace_buf, NetPktHeader, Stamp, PUBLIC,
+    };
+    let rspace = rspace_buf(&[b"a"]);
+    let pkt = linkpoint(PUBLIC, ab(b"test"), &rspace, &[], &[], Stamp::ZERO, ());
+    let mut remote = ReroutePkt::new(&pkt);
+    remote.net_header = NetPktHeader::EMPTY.hop();
+
+    let mut check = QuotaCheck::default();
+    let quota = Quota {
+        pkts_per_sec: Some(2),
+        bytes_per_day: None,
+    };
+    check.add_config(&config_key(QuotaKind::Group, &[]), &quota.to_bytes());
+    let t = 10 * DAY;
+    assert!(check.admit(&remote, t, |_| None));
+    assert!(check.admit(&remote, t + 1, |_| None));
+    assert!(!check.admit(&remote, t + 2, |_| None));
+    // local packets are not counted
+    assert!(check.admit(&pkt, t + 3, |_| None));
+    // the next second
+    assert!(check.admit(&remote, t + SECOND, |_| None));
+
+    let quota = Quota {
+        pkts_per_sec: None,
+        bytes_per_day: Some(remote.size() as u64),
+    };
+    check.add_config(
+        &config_key(QuotaKind::Domain, &ab(b"test").0),
+        &quota.to_bytes(),
+    );
+    assert!(check.admit(&remote, t + 2 * SECOND, |_| None));
+    assert!(!check.admit(&remote, t + 3 * SECOND, |_| None));
+    // the next day
+    assert!(check.admit(&remote, t + DAY, |_| None));
+    let stored: Vec<_> = check.changed_usage().collect();
+    assert_eq!(stored.len(), 2);
+    assert!(stored
+        .iter()
+        .all(|(k, u)| Usage::from_bytes(&u.to_bytes()) == Some(*u) && k[0] == USAGE));
+
+    // only the usage of a passed day that is not counted again is removed
+    let (key, usage) = stored[0];
+    let old = Usage {
+        day: usage.day - 1,
+        ..usage
+    };
+    assert!(!check.usage_expired(key, &old.to_bytes(), t + DAY));
+    let other = usage_key(QuotaKind::Group, &[1; 32]);
+    assert!(check.usage_expired(&other, &old.to_bytes(), t + DAY));
+    assert!(!check.usage_expired(&other, &usage.to_bytes(), t + DAY));
+}
+
+#[test]
+fn zero_quota() {
+    use linkspace_pkt::{
+        ab, linkpoint, reroute::ReroutePkt, rspace_buf, NetPktHeader, Stamp, PUBLIC,
+    };
+    let rspace = rspace_buf(&[b"a"]);
+    let pkt = linkpoint(PUBLIC, ab(b"test"), &rspace, &[], &[], Stamp::ZERO, ());
+    let mut remote = ReroutePkt::new(&pkt);
+    remote.net_header = NetPktHeader::EMPTY.hop();
+
+    for quota in [
+        Quota {
+            pkts_per_sec: Some(0),
+            bytes_per_day: None,
+        },
+        Quota {
+            pkts_per_sec: None,
+            bytes_per_day: Some(0),
+        },
+    ] {
+        assert!(!quota.is_unlimited());
+        assert_eq!(Quota::from_bytes(&quota.to_bytes()), Some(quota));
+        let mut check = QuotaCheck::default();
+        check.add_config(&config_key(QuotaKind::Group, &[]), &quota.to_bytes());
+        assert!(!check.admit(&remote, DAY, |_| None));
+        assert!(check.admit(&pkt, DAY, |_| None));
+    }
+    let unlimited = Quota::default();
+    assert_eq!(Quota::from_bytes(&unlimited.to_bytes()), Some(unlimited));
+}
@@ -24,6 +24,8 @@ pub const fn treekey_checked(r: RuleType) -> bool {
         },
         RuleType::RecvStamp => true,
         RuleType::SpacePrefix => true,
+        RuleType::SpaceGlob => false,
+        RuleType::Content(_) => false,
         RuleType::Limit(_) => false,
     }
 }
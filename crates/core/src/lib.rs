@@ -47,6 +47,7 @@ pub mod predicate;
 pub mod prelude;
 
 pub mod query;
+pub mod query_template;
 pub mod stamp_fmt;
 pub mod stamp_range;
 
@@ -0,0 +1,170 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//! Predicates over the data and links of a packet. e.g. `data_prefix:=:{"type"\:"msg"` or `link_tag:=:[a:reply-to]`.
+//! Multiple predicates of the same kind must all match. They can not be answered by the tree index.
+use linkspace_pkt::{abe::eval::ABList, LkHash, NetPkt, NetPktPtr, PointTypeFlags, Tag};
+use parse_display::{Display, FromStr};
+
+use super::{
+    exprs::{Predicate, RuleType},
+    test_pkt::PktStreamTest,
+    TestOp,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Display, FromStr)]
+pub enum ContentKind {
+    #[display("data_prefix")]
+    DataPrefix,
+    #[display("data_suffix")]
+    DataSuffix,
+    #[display("data_contains")]
+    DataContains,
+    /// a link with this tag exists
+    #[display("link_tag")]
+    LinkTag,
+    /// a link to this hash exists
+    #[display("link_ptr")]
+    LinkPtr,
+}
+pub const CONTENT_KINDS: [ContentKind; 5] = [
+    ContentKind::DataPrefix,
+    ContentKind::DataSuffix,
+    ContentKind::DataContains,
+    ContentKind::LinkTag,
+    ContentKind::LinkPtr,
+];
+impl ContentKind {
+    /// The point types that can match
+    pub fn pkts(self) -> PointTypeFlags {
+        match self {
+            ContentKind::LinkTag | ContentKind::LinkPtr => PointTypeFlags::LINK,
+            _ => PointTypeFlags::DATA,
+        }
+    }
+    pub fn fixed_size(self) -> Option<usize> {
+        match self {
+            ContentKind::LinkTag => Some(16),
+            ContentKind::LinkPtr => Some(32),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentTest {
+    DataPrefix(Vec<u8>),
+    DataSuffix(Vec<u8>),
+    DataContains(Vec<u8>),
+    LinkTag(Tag),
+    LinkPtr(LkHash),
+}
+impl ContentTest {
+    pub fn new(kind: ContentKind, val: ABList) -> anyhow::Result<Self> {
+        let bytes = || -> anyhow::Result<Vec<u8>> {
+            Ok(val
+                .as_exact_bytes()
+                .map_err(|_| anyhow::anyhow!("expected bytes without delimiters"))?
+                .to_vec())
+        };
+        Ok(match kind {
+            ContentKind::DataPrefix => ContentTest::DataPrefix(bytes()?),
+            ContentKind::DataSuffix => ContentTest::DataSuffix(bytes()?),
+            ContentKind::DataContains => ContentTest::DataContains(bytes()?),
+            ContentKind::LinkTag => ContentTest::LinkTag(Tag::try_from(val.clone())?),
+            ContentKind::LinkPtr => ContentTest::LinkPtr(LkHash::try_from(val.clone())?),
+        })
+    }
+    pub fn kind(&self) -> ContentKind {
+        match self {
+            ContentTest::DataPrefix(_) => ContentKind::DataPrefix,
+            ContentTest::DataSuffix(_) => ContentKind::DataSuffix,
+            ContentTest::DataContains(_) => ContentKind::DataContains,
+            ContentTest::LinkTag(_) => ContentKind::LinkTag,
+            ContentTest::LinkPtr(_) => ContentKind::LinkPtr,
+        }
+    }
+    pub fn matches(&self, pkt: &dyn NetPkt) -> bool {
+        match self {
+            ContentTest::DataPrefix(p) => pkt.data().starts_with(p),
+            ContentTest::DataSuffix(p) => pkt.data().ends_with(p),
+            ContentTest::DataContains(p) => {
+                p.is_empty() || pkt.data().windows(p.len()).any(|w| w == p.as_slice())
+            }
+            ContentTest::LinkTag(tag) => pkt.get_links().iter().any(|l| l.tag == *tag),
+            ContentTest::LinkPtr(ptr) => pkt.get_links().iter().any(|l| l.ptr == *ptr),
+        }
+    }
+    /// true if every packet matching self also matches other
+    pub fn implies(&self, other: &ContentTest) -> bool {
+        use ContentTest::*;
+        match (self, other) {
+            (DataPrefix(a), DataPrefix(b)) => a.starts_with(b),
+            (DataSuffix(a), DataSuffix(b)) => a.ends_with(b),
+            (DataPrefix(a) | DataSuffix(a) | DataContains(a), DataContains(b)) => {
+                b.is_empty() || a.windows(b.len()).any(|w| w == b.as_slice())
+            }
+            _ => self == other,
+        }
+    }
+    pub fn predicate(&self) -> Predicate {
+        let val: ABList = match self {
+            ContentTest::DataPrefix(b)
+            | ContentTest::DataSuffix(b)
+            | ContentTest::DataContains(b) => b.clone().into(),
+            ContentTest::LinkTag(tag) => (*tag).into(),
+            ContentTest::LinkPtr(ptr) => ptr.0.to_vec().into(),
+        };
+        Predicate::from(RuleType::Content(self.kind()), TestOp::Equal, val)
+    }
+}
+
+impl PktStreamTest for ContentTest {
+    fn test(&self, pkt: &NetPktPtr) -> bool {
+        self.matches(pkt)
+    }
+    fn get_field(&self) -> RuleType {
+        RuleType::Content(self.kind())
+    }
+    fn as_rules(&self) -> Box<dyn Iterator<Item = Predicate> + '_> {
+        Box::new(std::iter::once(self.predicate()))
+    }
+}
+
+#[test]
+fn content_query() {
+    use crate::prelude::Query;
+    use linkspace_pkt::{ab, datapoint, linkpoint, now, rspace_buf, Link, AB, B64};
+    let scope = crate::eval::core_scope();
+    let data = datapoint(b"hello world", ());
+    let links = [Link {
+        tag: ab(b"reply-to"),
+        ptr: data.hash(),
+    }];
+    let rspace = rspace_buf(&[b"a"]);
+    let link = linkpoint(
+        B64([0; 32]),
+        AB([0; 16]),
+        &rspace,
+        &links,
+        b"{\"type\":\"msg\"}",
+        now(),
+        (),
+    );
+    let check = |stmts: &str, pkt: &dyn NetPkt| {
+        let mut q = Query::default();
+        q.parse(stmts.as_bytes(), &scope).unwrap();
+        let mut test = q.compile().unwrap();
+        test(pkt).0
+    };
+    assert!(check("data_prefix:=:hello", &data));
+    assert!(check("data_suffix:=:world\ndata_contains:=:o w", &data));
+    assert!(!check("data_contains:=:bye", &data));
+    assert!(check("link_tag:=:[a:reply-to]", &link));
+    assert!(!check("link_tag:=:[a:other]", &link));
+    assert!(check(&format!("link_ptr:=:[b:{}]", data.hash()), &link));
+    assert!(check("data_prefix:=:{\"type\"\\:\"msg\"", &link));
+    assert!(!check("link_tag:=:[a:reply-to]", &data));
+}
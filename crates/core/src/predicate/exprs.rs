@@ -7,6 +7,7 @@ use std::fmt::Display;
 
 use either::Either;
 use linkspace_pkt::abe::ast::*;
+use linkspace_pkt::abe::scope::uint::{parse_decimal_be, print_decimal_be};
 use thiserror::Error;
 
 impl ABEValidator for ExtPredicate {
@@ -59,10 +60,9 @@ impl TryFrom<ABList> for ExtPredicate {
             Some(s) => s.into(),
             None => return Err(TestEvalErr::ParseKind(AB(kind))),
         };
-        let op: ExtendedTestOp = std::str::from_utf8(&op)
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .ok_or(TestEvalErr::Err("Cant parse op"))?;
+        let op = std::str::from_utf8(&op)
+            .map_err(|_| TestEvalErr::Err("Cant parse op"))
+            .and_then(ExtendedTestOp::parse_op)?;
         let predicate = ExtPredicate { kind, val, op };
         Ok(predicate)
     }
@@ -81,9 +81,29 @@ pub enum ExtendedTestOp {
     #[display("*=")]
     /// Set the last bytes to exactly equal to
     TailMask,
+    #[display("{0}!")]
+    /// Error if the field already has a predicate. See [PktPredicates::add_ext_predicate_strict]
+    /// Only a [TestOp] can be strict - '>=!', '<=!', '=*!', and '*=!' are errors.
+    Strict(TestOp),
     #[display("{0}")]
     Op(TestOp),
 }
+impl ExtendedTestOp {
+    /// Parse an op with a clear error for a strict op that does not exist (e.g. '>=!')
+    pub fn parse_op(op: &str) -> Result<ExtendedTestOp, TestEvalErr> {
+        if let Ok(op) = op.parse() {
+            return Ok(op);
+        }
+        match op.strip_suffix('!').map(str::parse::<ExtendedTestOp>) {
+            Some(Ok(inner)) if !matches!(inner, ExtendedTestOp::Strict(_)) => {
+                Err(TestEvalErr::Err(
+                    "only a single char op (=,<,>,..) can be strict - not >=, <=, =*, or *=",
+                ))
+            }
+            _ => Err(TestEvalErr::Err("Cant parse op")),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ExtPredicate {
@@ -125,7 +145,7 @@ impl ExtPredicate {
                     None
                 }
             }
-            ExtendedTestOp::Op(op) => Some(op),
+            ExtendedTestOp::Op(op) | ExtendedTestOp::Strict(op) => Some(op),
             ExtendedTestOp::TailMask | ExtendedTestOp::HeadMask => {
                 let e = || TestEvalErr::Err("head/tail mask can only apply to fixed len fields");
                 let size = kind.fixed_size().ok_or(e())?;
@@ -170,6 +190,48 @@ impl ExtPredicate {
     }
 }
 
+/// Split a 'KIND OP VALUE' alias such as `data_size<1000` or `create>[now:-1D]`.
+fn split_alias(line: &[u8]) -> Option<(PredicateType, ExtendedTestOp, &[u8])> {
+    let name_len = line
+        .iter()
+        .position(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))?;
+    let (name, rest) = line.split_at(name_len);
+    let op_len = rest
+        .iter()
+        .position(|c| !b"<>=".contains(c))
+        .unwrap_or(rest.len());
+    let (op, val) = rest.split_at(op_len);
+    let op = match op {
+        b"<" | b">" | b"=" | b"<=" | b">=" => std::str::from_utf8(op).ok()?.parse().ok()?,
+        _ => return None,
+    };
+    Some((PredicateType::try_from_id(name)?, op, val))
+}
+
+impl ExtPredicate {
+    /** Parse the shorthand `KIND OP VALUE` where OP is one of < > = <= >=.
+    A decimal VALUE is encoded with the width of the field, otherwise VALUE is evaluated as abe.
+    e.g. `data_size<1000` == `data_size:<:[u16:1000]` and `create>[now:-1D]` == `create:>:[now:-1D]`.
+    Returns None if the line is not an alias.
+    **/
+    pub fn try_from_alias(line: &[u8], scope: &dyn Scope) -> Option<anyhow::Result<Self>> {
+        let (kind, op, val) = split_alias(line)?;
+        let kind: RuleType = kind.into();
+        let decimal = val.first().is_some_and(u8::is_ascii_digit);
+        let val = match kind.uint_size() {
+            Some(size) if decimal => parse_decimal_be(val, size, false).map(ABList::from),
+            // a decimal for a fixed size field is likely a mistake
+            None if decimal && kind.fixed_size().is_some() => {
+                Err(anyhow::anyhow!("{kind} does not take a decimal"))
+            }
+            _ => parse_abe_strict_b(val)
+                .map_err(anyhow::Error::from)
+                .and_then(|abe| Ok(eval(scope, &abe)?)),
+        };
+        Some(val.map(|val| ExtPredicate { kind, op, val }))
+    }
+}
+
 pub type PredicateExpr = TypedABE<ExtPredicate>;
 #[derive(Debug, Clone)]
 pub struct Predicate {
@@ -194,6 +256,20 @@ impl Predicate {
             print_abe(lst)
         }
     }
+    /// The decimal alias (e.g. `data_size<1000`) if the kind is a counter and op is < > or =
+    pub fn to_alias_str(&self) -> Option<String> {
+        let size = self.kind.uint_size().filter(|s| *s <= 4)?;
+        if !matches!(self.op, TestOp::Less | TestOp::Greater | TestOp::Equal) {
+            return None;
+        }
+        let bytes = self.val.as_exact_bytes().ok().filter(|b| b.len() == size)?;
+        Some(format!(
+            "{}{}{}",
+            self.kind,
+            self.op,
+            print_decimal_be(bytes, false)
+        ))
+    }
     pub fn from_slice(kind: impl Into<RuleType>, op: impl Into<TestOp>, val: &[u8]) -> Predicate {
         Predicate::from(kind, op, val)
     }
@@ -219,7 +295,10 @@ use crate::eval::*;
 use linkspace_pkt::{FieldEnum, SpaceBuf, AB};
 use parse_display::{Display, FromStr};
 
-use crate::predicate::TestOp;
+use crate::predicate::{
+    content::{ContentKind, CONTENT_KINDS},
+    TestOp,
+};
 use crate::prelude::predicate_type::PredicateType;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Display, FromStr)]
@@ -243,6 +322,10 @@ pub enum RuleType {
     RecvStamp,
     #[display("prefix")]
     SpacePrefix,
+    #[display("spaceglob")]
+    SpaceGlob,
+    #[display("{0}")]
+    Content(ContentKind),
     #[display("{0}")]
     Limit(QScope),
 }
@@ -251,28 +334,54 @@ impl RuleType {
         FieldEnum::LIST
             .map(RuleType::Field)
             .into_iter()
-            .chain([RuleType::RecvStamp, RuleType::SpacePrefix])
+            .chain([
+                RuleType::RecvStamp,
+                RuleType::SpacePrefix,
+                RuleType::SpaceGlob,
+            ])
             .chain(QSCOPES.map(RuleType::Limit))
+            .chain(CONTENT_KINDS.map(RuleType::Content))
     }
 
     pub fn try_canonical(self, abl: ABList) -> anyhow::Result<Vec<ABE>> {
         match self {
             RuleType::Field(f) => f.try_to_abe(abl).ok_or(anyhow::anyhow!("Field to abe err")),
             RuleType::RecvStamp => Ok(linkspace_pkt::Stamp::try_from(abl)?.to_abe()),
-            RuleType::SpacePrefix => Ok(SpaceBuf::try_from(abl)?.to_abe()),
+            RuleType::SpacePrefix | RuleType::SpaceGlob => Ok(SpaceBuf::try_from(abl)?.to_abe()),
             RuleType::Limit(_) => Ok(linkspace_pkt::U32::try_from(abl)?.to_abe()),
+            RuleType::Content(ContentKind::LinkTag) => {
+                Ok(linkspace_pkt::Tag::try_from(abl)?.to_abe())
+            }
+            RuleType::Content(ContentKind::LinkPtr) => {
+                Ok(linkspace_pkt::LkHash::try_from(abl)?.to_abe())
+            }
+            RuleType::Content(_) => Ok(abl.into()),
         }
     }
     pub fn canonical(self, abl: &ABList) -> Vec<ABE> {
         self.try_canonical(abl.clone())
             .unwrap_or_else(|_| abl.clone().into())
     }
+    /// The byte size if the field is an unsigned integer, i.e. it can be written as a decimal.
+    pub fn uint_size(self) -> Option<usize> {
+        use FieldEnum::*;
+        match self {
+            RuleType::Field(
+                DepthF | LinksLenF | DataSizeF | SizeF | VarHopF | VarUBits0F | VarUBits1F
+                | VarUBits2F | VarUBits3F | CreateF | VarStampF | PubKeyF | GroupIDF | PktHashF,
+            )
+            | RuleType::RecvStamp
+            | RuleType::Limit(_) => self.fixed_size(),
+            _ => None,
+        }
+    }
     pub fn fixed_size(self) -> Option<usize> {
         match self {
             RuleType::Field(f) => f.fixed_size(),
             RuleType::RecvStamp => Some(8),
-            RuleType::SpacePrefix => None,
+            RuleType::SpacePrefix | RuleType::SpaceGlob => None,
             RuleType::Limit(_) => Some(4),
+            RuleType::Content(k) => k.fixed_size(),
         }
     }
 }
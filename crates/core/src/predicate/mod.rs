@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 pub mod bitset_test;
+pub mod content;
 pub mod uint;
 pub mod value_test;
 
@@ -14,6 +15,7 @@ pub mod treekey;
 pub mod builder;
 pub mod pkt_predicates;
 pub mod predicate_type;
+pub mod space_glob;
 pub mod test_pkt;
 pub use uint::*;
 pub use value_test::*;
@@ -10,12 +10,14 @@ use linkspace_pkt::{abe::eval::ABList, *};
 
 use crate::{
     predicate::{bitset_test::BitTestSet, exprs::RuleType},
-    prelude::ExtPredicate,
+    prelude::{ExtPredicate, ExtendedTestOp},
 };
 use anyhow::{ensure, Context};
 
 use super::{
+    content::ContentTest,
     exprs::{Predicate, QScope},
+    space_glob::SpaceGlob,
     treekey::TreeKeys,
     value_test::*,
 };
@@ -45,6 +47,8 @@ pub struct PktPredicates {
 
     pub rspace_prefix: RootedSpaceBuf,
     pub depth: TestSet<u8>,
+    pub space_glob: Vec<SpaceGlob>,
+    pub content: Vec<ContentTest>,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -99,6 +103,8 @@ impl PktPredicates {
         data_size: TestSet::DEFAULT,
         links_len: TestSet::DEFAULT,
         depth: TestSet::DEFAULT,
+        space_glob: Vec::new(),
+        content: Vec::new(),
         create: TestSet::DEFAULT,
         recv_stamp: Bound::DEFAULT,
         state: StatePredicates {
@@ -162,6 +168,8 @@ impl PktPredicates {
             size,
             rspace_prefix,
             depth,
+            space_glob,
+            content,
             recv_stamp,
             create,
             state,
@@ -202,6 +210,8 @@ impl PktPredicates {
             .chain((!rspace_prefix.is_empty()).then(|| {
                 Predicate::from(RuleType::SpacePrefix, TestOp::Equal, rspace_prefix.ablist())
             }))
+            .chain(space_glob.iter().map(SpaceGlob::predicate))
+            .chain(content.iter().map(ContentTest::predicate))
             .chain(as_rules_it2(
                 PktHashF,
                 hash.map(|v| -> LkHash { v.into() }).rules(),
@@ -220,8 +230,66 @@ impl PktPredicates {
             .chain(limits)
     }
 
+    /// true if every packet (and limit) selected by self is also selected by template.
+    /// Conservative - it can return false for some sets that are in fact a subset.
+    pub fn is_subset(&self, template: &PktPredicates) -> bool {
+        let sets_ok = self.pkt_types.is_subset(&template.pkt_types)
+            && self.var_flags.is_subset(&template.var_flags)
+            && self.var_hop.is_subset(&template.var_hop)
+            && self.var_stamp.is_subset(&template.var_stamp)
+            && (0..4).all(|i| self.var_ubits[i].is_subset(&template.var_ubits[i]))
+            && self.domain.is_subset(&template.domain)
+            && self.group.is_subset(&template.group)
+            && self.pubkey.is_subset(&template.pubkey)
+            && self.hash.is_subset(&template.hash)
+            && self.size.is_subset(&template.size)
+            && self.data_size.is_subset(&template.data_size)
+            && self.links_len.is_subset(&template.links_len)
+            && self.create.is_subset(&template.create)
+            && self.depth.is_subset(&template.depth)
+            && self.recv_stamp.is_subset(&template.recv_stamp)
+            && crate::predicate::exprs::QSCOPES.into_iter().all(|i| {
+                let (mut a, mut b) = (self.state, template.state);
+                a.idx(i).is_subset(b.idx(i))
+            });
+        if !sets_ok || !self.rspace_prefix.starts_with(&template.rspace_prefix) {
+            return false;
+        }
+        // a glob is satisfied by the same glob or by an exact spacename it matches
+        let exact_space = (self.depth.as_eq() == Some(*self.rspace_prefix.space_depth()))
+            .then(|| self.rspace_prefix.space());
+        let globs_ok = template.space_glob.iter().all(|g| {
+            self.space_glob.contains(g) || exact_space.map(|s| g.matches(s)).unwrap_or(false)
+        });
+        globs_ok
+            && template
+                .content
+                .iter()
+                .all(|t| self.content.iter().any(|c| c.implies(t)))
+    }
+
+    /// true if the kind has any predicate - including implied predicates (e.g. 'type' or 'depth' by 'prefix')
+    pub fn is_constrained(&self, kind: RuleType) -> bool {
+        self.iter().any(|p| p.kind == kind)
+    }
+    /// Like [Self::add_ext_predicate] but errors if the kind is already constrained.
+    /// The check happens before adding, i.e. on a strict error self remains valid.
+    pub fn add_ext_predicate_strict(&mut self, mut predicate: ExtPredicate) -> anyhow::Result<()> {
+        if let ExtendedTestOp::Strict(op) = predicate.op {
+            predicate.op = ExtendedTestOp::Op(op);
+        }
+        ensure!(
+            !self.is_constrained(predicate.kind),
+            "'{}' is already constrained",
+            predicate.kind
+        );
+        self.add_ext_predicate(predicate)
+    }
     /// warn - becomes invalid after error
     pub fn add_ext_predicate(&mut self, predicate: ExtPredicate) -> anyhow::Result<()> {
+        if let ExtendedTestOp::Strict(_) = predicate.op {
+            return self.add_ext_predicate_strict(predicate);
+        }
         for p in predicate.try_iter()? {
             self.add_predicate(&p)?;
         }
@@ -322,6 +390,33 @@ impl PktPredicates {
                 let sp = SpaceBuf::try_from(val)?;
                 self.prefix(sp)?;
             }
+            RuleType::SpaceGlob => {
+                ensure!(op == TestOp::Equal, "spaceglob only supports equallity");
+                let glob = SpaceGlob::new(SpaceBuf::try_from(val)?)?;
+                let (min, max) = glob.depth_range();
+                if let Some(i) = min.checked_sub(1) {
+                    self.depth.try_add(TestOp::Greater, i as u8)?;
+                }
+                if max < MAX_SPACE_DEPTH {
+                    self.depth.try_add(TestOp::Less, max as u8 + 1)?;
+                }
+                // only walk the sub space of the tree index
+                self.prefix(glob.literal_prefix())?;
+                self.check_space()?;
+                if !self.space_glob.contains(&glob) {
+                    self.space_glob.push(glob);
+                }
+            }
+            RuleType::Content(kind) => {
+                ensure!(op == TestOp::Equal, "{kind} only supports equallity");
+                self.pkt_types
+                    .try_add(TestOp::Mask1, kind.pkts().bits())
+                    .with_context(|| format!("incompatible pkt typs:{rule:?}"))?;
+                let test = ContentTest::new(*kind, val)?;
+                if !self.content.contains(&test) {
+                    self.content.push(test);
+                }
+            }
             RuleType::Limit(l) => {
                 self.state.idx(*l).add(op, U32::try_from(val)?.get());
                 self.state.is_valid()?;
@@ -31,7 +31,7 @@ macro_rules! predty {
             ),*
         }
         impl PredicateType{
-            pub const ALL : [PredicateType;24] = [$(PredicateType::$fname),*];
+            pub const ALL : [PredicateType;30] = [$(PredicateType::$fname),*];
 
             pub fn try_from_id(id:&[u8]) -> Option<Self> {
                 #![allow(non_upper_case_globals)]
@@ -76,6 +76,7 @@ predty!( enum PredicateType {
     Domain => ("domain",LINK,r"\[a:example\]","domain - if fewer than 16 bytes, prepadded with \0"),
     Prefix => ("prefix",LINK,r"/hello/world","all points with spacename starting with prefix - only accepts '=' op"),
     Spacename => ("spacename",LINK,r"/hello/world","exact spacename - only accepts '=' op"),
+    SpaceGlob => ("spaceglob",LINK,r"/chat/*/2024-*/**","spacename matching a pattern - '*' and '?' within a component, '**' for any number of components, '~REGEX' for a component regex - only accepts '=' op"),
     Pubkey => ("pubkey",SIGNATURE,r"\[@:me:local\]","public key used to sign point"),
     Create => ("create",LINK,r"\[now:-1H\]","the create stamp"),
     Depth => ("depth",LINK,r"\[u8:0\]","the total number of space components - max 8"),
@@ -94,7 +95,12 @@ predty!( enum PredicateType {
     Ubits3 => ("ubits3",EMPTY,r"\[u32:0\]","(mutable) user defined bits"),
     Type => ("type",EMPTY,r"\[b2:00000001\]","the field type bits - implied by other predicates"),
     Netflags => ("netflags",EMPTY,r"\[b2:00000000\]","(mutable) netflags"),
-    Size => ("size",DATA,r"\[u16:4\]","exact size of the netpkt when using lk_write or lk_read - includes netheader and hash ")
+    Size => ("size",DATA,r"\[u16:4\]","exact size of the netpkt when using lk_write or lk_read - includes netheader and hash "),
+    DataPrefix => ("data_prefix",DATA,r"hello","the data starts with - only accepts '=' op"),
+    DataSuffix => ("data_suffix",DATA,r"world","the data ends with - only accepts '=' op"),
+    DataContains => ("data_contains",DATA,r"o w","the data contains - only accepts '=' op"),
+    LinkTag => ("link_tag",LINK,r"\[a:reply-to\]","a link with this tag exists - only accepts '=' op"),
+    LinkPtr => ("link_ptr",LINK,r"\[b:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\]","a link to this hash exists - only accepts '=' op")
 });
 impl From<PredicateType> for RuleType {
     fn from(val: PredicateType) -> Self {
@@ -0,0 +1,201 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//! Match spacenames by a pattern of components. e.g. `spaceglob:=:/chat/*/2024-*/**`
+//!
+//! - `**` matches zero or more components
+//! - `*` matches any bytes within a component, `?` matches exactly one byte
+//! - a component starting with `~` is a regex that must match the entire component. e.g. `/chat/~\[0-9\]{4}`
+//!
+//! The components before the first pattern are a literal prefix. It is added as a 'prefix' predicate such that the tree index only walks the matching sub space.
+use anyhow::Context;
+use linkspace_pkt::{space_buf, NetPkt, NetPktPtr, Space, SpaceBuf, MAX_SPACE_DEPTH};
+use regex::bytes::Regex;
+
+use super::{
+    exprs::{Predicate, RuleType},
+    test_pkt::PktStreamTest,
+    TestOp,
+};
+
+#[derive(Debug, Clone)]
+enum CompPattern {
+    Literal(Vec<u8>),
+    Glob(Vec<u8>),
+    Regex(Regex),
+    AnyDepth,
+}
+impl CompPattern {
+    fn new(comp: &[u8]) -> anyhow::Result<Self> {
+        Ok(match comp {
+            b"**" => CompPattern::AnyDepth,
+            [b'~', re @ ..] => {
+                let re = std::str::from_utf8(re).context("regex is not utf8")?;
+                CompPattern::Regex(Regex::new(&format!("^(?:{re})$"))?)
+            }
+            _ if comp.iter().any(|c| matches!(c, b'*' | b'?')) => CompPattern::Glob(comp.to_vec()),
+            _ => CompPattern::Literal(comp.to_vec()),
+        })
+    }
+    fn matches(&self, comp: &[u8]) -> bool {
+        match self {
+            CompPattern::Literal(l) => l == comp,
+            CompPattern::Glob(g) => glob_match(g, comp),
+            CompPattern::Regex(re) => re.is_match(comp),
+            CompPattern::AnyDepth => true,
+        }
+    }
+}
+
+/// match '*' and '?' within a single component
+fn glob_match(pattern: &[u8], bytes: &[u8]) -> bool {
+    let (mut p, mut b) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while b < bytes.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, b));
+                p += 1;
+            }
+            Some(c) if *c == b'?' || *c == bytes[b] => {
+                p += 1;
+                b += 1;
+            }
+            _ => match star {
+                Some((sp, sb)) => {
+                    p = sp + 1;
+                    b = sb + 1;
+                    star = Some((sp, sb + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+fn match_comps(pattern: &[CompPattern], comps: &[&[u8]]) -> bool {
+    match pattern.split_first() {
+        None => comps.is_empty(),
+        Some((CompPattern::AnyDepth, rest)) => {
+            (0..=comps.len()).any(|skip| match_comps(rest, &comps[skip..]))
+        }
+        Some((p, rest)) => match comps.split_first() {
+            Some((c, comps)) => p.matches(c) && match_comps(rest, comps),
+            None => false,
+        },
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpaceGlob {
+    pattern: SpaceBuf,
+    comps: Vec<CompPattern>,
+}
+impl PartialEq for SpaceGlob {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl SpaceGlob {
+    pub fn new(pattern: SpaceBuf) -> anyhow::Result<Self> {
+        let comps = pattern
+            .to_array()
+            .iter()
+            .map(|c| CompPattern::new(c))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(SpaceGlob { pattern, comps })
+    }
+    pub fn pattern(&self) -> &Space {
+        &self.pattern
+    }
+    pub fn matches(&self, space: &Space) -> bool {
+        match_comps(&self.comps, &space.to_array())
+    }
+    /// The components before the first pattern
+    pub fn literal_prefix(&self) -> SpaceBuf {
+        let lit: Vec<&[u8]> = self
+            .comps
+            .iter()
+            .map_while(|c| match c {
+                CompPattern::Literal(l) => Some(l.as_slice()),
+                _ => None,
+            })
+            .collect();
+        space_buf(&lit)
+    }
+    /// The (min,max) depth of a matching spacename
+    pub fn depth_range(&self) -> (usize, usize) {
+        let fixed = self
+            .comps
+            .iter()
+            .filter(|c| !matches!(c, CompPattern::AnyDepth))
+            .count();
+        if fixed == self.comps.len() {
+            (fixed, fixed)
+        } else {
+            (fixed, MAX_SPACE_DEPTH)
+        }
+    }
+    pub fn predicate(&self) -> Predicate {
+        Predicate::from(RuleType::SpaceGlob, TestOp::Equal, self.pattern.ablist())
+    }
+}
+
+impl PktStreamTest for SpaceGlob {
+    fn test(&self, pkt: &NetPktPtr) -> bool {
+        pkt.spacename().map(|s| self.matches(s)).unwrap_or(false)
+    }
+    fn get_field(&self) -> RuleType {
+        RuleType::SpaceGlob
+    }
+    fn as_rules(&self) -> Box<dyn Iterator<Item = Predicate> + '_> {
+        Box::new(std::iter::once(self.predicate()))
+    }
+}
+
+#[test]
+fn space_glob() {
+    let glob = |p: &[&[u8]]| SpaceGlob::new(space_buf(p)).unwrap();
+    let sp = |p: &[&[u8]]| space_buf(p);
+
+    let g = glob(&[b"chat", b"*", b"2024-*", b"**"]);
+    assert_eq!(g.literal_prefix(), sp(&[b"chat"]));
+    assert_eq!(g.depth_range(), (3, MAX_SPACE_DEPTH));
+    assert!(g.matches(&sp(&[b"chat", b"room", b"2024-01"])));
+    assert!(g.matches(&sp(&[b"chat", b"room", b"2024-01", b"a", b"b"])));
+    assert!(!g.matches(&sp(&[b"chat", b"room", b"2023-01"])));
+    assert!(!g.matches(&sp(&[b"chat", b"room"])));
+    assert!(!g.matches(&sp(&[b"other", b"room", b"2024-01"])));
+
+    let g = glob(&[b"a", b"**", b"x?z"]);
+    assert!(g.matches(&sp(&[b"a", b"xyz"])));
+    assert!(g.matches(&sp(&[b"a", b"b", b"c", b"x_z"])));
+    assert!(!g.matches(&sp(&[b"a", b"b", b"xz"])));
+
+    let g = glob(&[b"log", b"~[0-9]{4}"]);
+    assert_eq!(g.depth_range(), (2, 2));
+    assert!(g.matches(&sp(&[b"log", b"2024"])));
+    assert!(!g.matches(&sp(&[b"log", b"20245"])));
+
+    assert!(glob_match(b"*a*b", b"xxaxxb"));
+    assert!(!glob_match(b"*a*b", b"xxaxxbc"));
+    assert!(glob_match(b"**", b""));
+}
+
+#[test]
+fn space_glob_query() {
+    use crate::prelude::Query;
+    let scope = crate::eval::core_scope();
+    let mut q = Query::default();
+    q.parse(b"spaceglob:=:/chat/*/2024-*", &scope).unwrap();
+    assert_eq!(q.predicates.rspace_prefix.space(), &*space_buf(&[b"chat"]));
+    assert_eq!(q.predicates.space_glob.len(), 1);
+    assert!(q.predicates.depth.info(3).val.is_some());
+    assert!(q.predicates.depth.info(4).val.is_none());
+    assert!(q.to_str(true).contains("spaceglob:=:/chat/*/2024-*"));
+    assert!(q.parse(b"prefix:=:/other", &scope).is_err());
+}
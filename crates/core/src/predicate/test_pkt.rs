@@ -115,6 +115,8 @@ pub fn compile_predicates(
         size,
         rspace_prefix,
         depth,
+        space_glob,
+        content,
         create,
         links_len,
         data_size,
@@ -149,6 +151,16 @@ pub fn compile_predicates(
                 Box::new(SpacePrefix(v.space().to_owned())) as Box<dyn PktStreamTest>,
                 RuleType::SpacePrefix,
             )
+        }))
+        .chain(
+            space_glob
+                .clone()
+                .into_iter()
+                .map(|g| (Box::new(g) as Box<dyn PktStreamTest>, RuleType::SpaceGlob)),
+        )
+        .chain(content.clone().into_iter().map(|c| {
+            let kind = RuleType::Content(c.kind());
+            (Box::new(c) as Box<dyn PktStreamTest>, kind)
         }));
     (it, *recv_stamp)
 }
@@ -649,6 +649,16 @@ impl<U: UInt> TestSet<U> {
         self.rules()
             .all(|(op, rh_val)| op.uint_func()(&val, &rh_val))
     }
+    /// true if every rule of other is implied by self. i.e. self ∩ other == self.
+    /// This compares bound and mask separately, and can return false for some sets that are in fact a subset.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.bound.is_subset(&other.bound) && self.mask.is_subset(&other.mask)
+    }
+}
+impl<U: UInt> Mask<U> {
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.ones.bit_and(other.ones) == other.ones && self.zeros.bit_and(other.zeros) == self.zeros
+    }
 }
 
 impl<U: UInt> Bound<U> {
@@ -660,6 +670,9 @@ impl<U: UInt> Bound<U> {
             low: self.low.max(other.low),
         })
     }
+    pub fn is_subset(&self, other: &Self) -> bool {
+        other.low <= self.low && self.high <= other.high
+    }
 }
 
 impl<V: UInt> TestSet<V> {
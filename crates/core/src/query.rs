@@ -91,6 +91,20 @@ impl Query {
         }
         out
     }
+    /// [Self::to_str] with counters printed as decimal aliases (e.g. `data_size<1000`).
+    /// For people to read - older peers do not parse aliases.
+    pub fn to_alias_str(&self) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        writeln!(out, "{}", &self.conf).unwrap();
+        for p in self.predicates.iter() {
+            match p.to_alias_str() {
+                Some(alias) => writeln!(out, "{alias}").unwrap(),
+                None => writeln!(out, "{}", p.to_str(true)).unwrap(),
+            }
+        }
+        out
+    }
     pub fn add_option(&mut self, name: &str, values: &[&[u8]]) {
         let mut ab = ABList::DEFAULT;
         ab.push_v((Some(Ctr::Colon), name.as_bytes().to_vec()));
@@ -147,12 +161,62 @@ impl Query {
             if line.is_empty() {
                 continue;
             }
+            if let Some(alias) = crate::prelude::ExtPredicate::try_from_alias(line, scope) {
+                let alias = alias.with_context(|| {
+                    anyhow::anyhow!("could not parse '{}'", String::from_utf8_lossy(line))
+                })?;
+                self.predicates.add_ext_predicate(alias)?;
+                continue;
+            }
             let e = eval(scope, &abe::parse_abe_strict_b(line)?)?;
             self.add_stmt(e)?;
         }
         Ok(())
     }
 
+    /// The options (in the order they were added) followed by the predicates as statements.
+    pub fn statements(&self) -> impl Iterator<Item = ABList> + '_ {
+        let predicates = self.predicates.iter().map(|p| {
+            let mut stmt = ABList::DEFAULT
+                .push_bytes(p.kind.to_string().as_bytes())
+                .push_ctr(Ctr::Colon)
+                .push_bytes(p.op.to_string().as_bytes())
+                .push_ctr(Ctr::Colon);
+            for (ctr, bytes) in p.val.as_slice() {
+                if let Some(c) = ctr {
+                    stmt = stmt.push_ctr(*c);
+                }
+                stmt = stmt.push_bytes(bytes);
+            }
+            stmt
+        });
+        self.conf.iter().cloned().chain(predicates)
+    }
+    /// The binary form of the query. Every statement is a '#ab' frame prefixed by its length as a u32 BE.
+    /// Unlike [Self::to_str] it needs no scope to read back.
+    pub fn to_ab_frames(&self) -> Vec<u8> {
+        let mut out = vec![];
+        for stmt in self.statements() {
+            let frame = stmt.to_ab_frame();
+            out.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            out.extend_from_slice(&frame);
+        }
+        out
+    }
+    pub fn from_ab_frames(mut bytes: &[u8]) -> anyhow::Result<Query> {
+        let mut query = Query::default();
+        while !bytes.is_empty() {
+            ensure!(bytes.len() >= 4, "truncated statement length");
+            let (len, rest) = bytes.split_at(4);
+            let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+            ensure!(rest.len() >= len, "truncated statement");
+            let (frame, rest) = rest.split_at(len);
+            query.add_stmt(ABList::from_ab_frame(frame)?)?;
+            bytes = rest;
+        }
+        Ok(query)
+    }
+
     pub fn hash_eq(h: linkspace_pkt::LkHash) -> Self {
         let mut predicates = PktPredicates::default();
         predicates.hash.add(TestOp::Equal, h.into());
@@ -208,3 +272,69 @@ impl<'o> EvalScopeImpl for &'o Query {
         )])
     }
 }
+
+#[test]
+fn decimal_alias() {
+    let scope = crate::eval::core_scope();
+    let mut alias = Query::default();
+    alias
+        .parse(b"data_size<1000\ni_db<=10\nhop>=3\ngroup=[#:test]", &scope)
+        .unwrap();
+    let mut full = Query::default();
+    full.parse(
+        b"data_size:<:[u16:1000]\ni_db:<:[u32:11]\nhop:>:[u32:2]\ngroup:=:[#:test]",
+        &scope,
+    )
+    .unwrap();
+    assert_eq!(alias.to_str(false), full.to_str(false));
+
+    assert_eq!(alias.to_str(true), full.to_str(true));
+    assert!(!alias.to_str(true).contains("data_size<"));
+    let printed = alias.to_alias_str();
+    assert!(printed.contains("data_size<1000\n"), "{printed}");
+    let mut reparsed = Query::default();
+    reparsed.parse(printed.as_bytes(), &scope).unwrap();
+    assert_eq!(reparsed.to_str(false), full.to_str(false));
+
+    assert!(Query::default().parse(b"domain<10", &scope).is_err());
+}
+
+#[test]
+fn strict_op() {
+    let scope = crate::eval::core_scope();
+    let mut q = Query::default();
+    q.parse(b"group:=!:[#:test]\ndata_size:<!:[u16:10]", &scope)
+        .unwrap();
+    assert!(q.parse(b"group:=!:[#:pub]", &scope).is_err());
+    assert!(q.parse(b"data_size:>!:[u16:2]", &scope).is_err());
+    // only single char ops have a strict form
+    let mut other = Query::default();
+    assert!(other.parse(b"data_size:<=!:[u16:10]", &scope).is_err());
+    assert!(other.parse(b"hash:=*!:[b:AAAA]", &scope).is_err());
+    assert!(crate::prelude::ExtendedTestOp::parse_op(">=!").is_err());
+    assert_eq!(
+        crate::prelude::ExtendedTestOp::parse_op("<!").unwrap(),
+        crate::prelude::ExtendedTestOp::Strict(TestOp::Less)
+    );
+    // the query is unchanged after a strict error
+    let mut full = Query::default();
+    full.parse(b"group:=:[#:test]\ndata_size:<:[u16:10]", &scope)
+        .unwrap();
+    assert_eq!(q.to_str(false), full.to_str(false));
+    q.parse(b"data_size:>:[u16:2]", &scope).unwrap();
+}
+
+#[test]
+fn ab_frames() {
+    let scope = crate::eval::core_scope();
+    let mut q = Query::default();
+    q.parse(
+        b"group:=:[#:test]\nprefix:=:/a/[u8:0]\ndata_size:<:[u16:10]\n:mode:log-asc\n:qid:\\0\\xff",
+        &scope,
+    )
+    .unwrap();
+    let back = Query::from_ab_frames(&q.to_ab_frames()).unwrap();
+    assert_eq!(back.to_str(false), q.to_str(false));
+    assert_eq!(back.qid().unwrap(), Some(Some(b"\0\xff" as &[u8])));
+    assert!(Query::from_ab_frames(&q.to_ab_frames()[1..]).is_err());
+}
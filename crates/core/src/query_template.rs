@@ -0,0 +1,134 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Query templates describe the set of queries a process (e.g. an exchange) is willing to answer.
+
+A template is a multi line query with `{name}` placeholders. e.g.
+```text
+domain:=:{domain}
+group:=:{group}
+prefix:=:/chat
+i_db:<:[u32:1000]
+```
+Filling a template substitutes the placeholder with the abtxt encoding of the bytes. Escaping ensures a value can not add statements.
+A `{` not followed by `name}` is kept as is.
+
+A query is acceptable if [query_satisfies] any of the filled templates, i.e. it does not select more than the template.
+A list of templates is separated by an empty line.
+**/
+use anyhow::Context;
+use linkspace_pkt::abe::{abtxt::as_abtxt, eval::Scope};
+
+use crate::query::Query;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTemplate(pub String);
+
+fn placeholder(st: &str) -> Option<&str> {
+    let end = st.find('}')?;
+    let name = &st[1..end];
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    valid.then_some(name)
+}
+
+impl QueryTemplate {
+    /// split a list of templates separated by an empty line
+    pub fn parse_list(list: &str) -> Vec<QueryTemplate> {
+        list.split("\n\n")
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| QueryTemplate(t.to_string()))
+            .collect()
+    }
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .match_indices('{')
+            .filter_map(|(i, _)| placeholder(&self.0[i..]))
+    }
+    /// substitute every placeholder. Errors if a placeholder has no value
+    pub fn fill(&self, vars: &[(&str, &[u8])]) -> anyhow::Result<String> {
+        let mut out = String::with_capacity(self.0.len());
+        let mut rest = self.0.as_str();
+        while let Some(i) = rest.find('{') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i..];
+            match placeholder(rest) {
+                Some(name) => {
+                    let (_, val) = vars
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .with_context(|| format!("no value for placeholder '{{{name}}}'"))?;
+                    out.push_str(&as_abtxt(val));
+                    rest = &rest[name.len() + 2..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+    pub fn to_query(&self, vars: &[(&str, &[u8])], scope: &dyn Scope) -> anyhow::Result<Query> {
+        let mut q = Query::default();
+        q.parse(self.fill(vars)?.as_bytes(), scope)?;
+        Ok(q)
+    }
+}
+
+/// true if every packet the query can select is also selected by the template and the query's limits are within the template's.
+/// Options are ignored.
+pub fn query_satisfies(template: &Query, query: &Query) -> bool {
+    query.predicates.is_subset(&template.predicates)
+}
+
+#[test]
+fn template_satisfies() {
+    let scope = crate::eval::core_scope();
+    let template = QueryTemplate(
+        "group:=:{group}\nprefix:=:/chat\ni_db:<:[u32:100]\ndata_prefix:=:{\"type\"".into(),
+    );
+    assert_eq!(template.placeholders().collect::<Vec<_>>(), ["group"]);
+    assert!(template.fill(&[]).is_err());
+    let group = [1u8; 32];
+    let template = template
+        .to_query(&[("group", group.as_slice())], &scope)
+        .unwrap();
+
+    let check = |stmts: &str| {
+        let mut q = Query::default();
+        q.parse(stmts.as_bytes(), &scope).unwrap();
+        query_satisfies(&template, &q)
+    };
+    let group = format!("group:=:[b:{}]", linkspace_pkt::B64(group));
+    let ok = format!(
+        "{group}\nprefix:=:/chat/room\ni_db:<:[u32:10]\ndata_prefix:=:{{\"type\"\\:\"msg\""
+    );
+    assert!(check(&ok));
+    assert!(!check(&ok.replace("/chat/room", "/other")));
+    assert!(!check(&ok.replace("[u32:10]", "[u32:1000]")));
+    assert!(!check(&ok.replace("i_db:<:[u32:10]\n", "")));
+    assert!(!check(&ok.replace(&group, "group:=:[#:pub]")));
+    assert!(!check(&ok.replace("data_prefix", "data_suffix")));
+
+    // a value can not inject a statement
+    let t = QueryTemplate("prefix:=:/{name}".into());
+    assert_eq!(
+        t.fill(&[("name", b"a\ni_db:<:[u32:0]".as_slice())])
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+    assert_eq!(
+        QueryTemplate::parse_list("a:=:b\n\nc:=:d\ne:=:f\n").len(),
+        2
+    );
+}
@@ -0,0 +1,118 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Authenticated encryption of a byte stream.
+
+The stream is split into frames: [len:u32 be][ciphertext+tag].
+The nonce is the frame counter, so frames can not be dropped, reordered, or replayed.
+An empty frame closes the stream. An EOF without it is an error, i.e. the stream was truncated.
+
+Every key must encrypt only a single stream. Use a different key for each direction.
+**/
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::Hash;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce,
+};
+
+/// Max plaintext bytes per frame
+pub const MAX_FRAME: usize = 1 << 16;
+const TAG_SIZE: usize = 16;
+
+pub struct FrameCipher {
+    cipher: ChaCha20Poly1305,
+    counter: u64,
+}
+impl FrameCipher {
+    pub fn new(key: &Hash) -> FrameCipher {
+        FrameCipher {
+            cipher: ChaCha20Poly1305::new(key.into()),
+            counter: 0,
+        }
+    }
+    fn next_nonce(&mut self) -> io::Result<Nonce> {
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("frame counter exhausted"))?;
+        Ok(nonce)
+    }
+    pub fn write_frame(&mut self, out: &mut dyn Write, data: &[u8]) -> io::Result<()> {
+        assert!(data.len() <= MAX_FRAME);
+        let nonce = self.next_nonce()?;
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, data)
+            .map_err(|_| io::Error::other("encryption failed"))?;
+        out.write_all(&(ciphertext.len() as u32).to_be_bytes())?;
+        out.write_all(&ciphertext)?;
+        out.flush()
+    }
+    /// Returns None for the closing frame.
+    pub fn read_frame(&mut self, inp: &mut dyn Read) -> io::Result<Option<Vec<u8>>> {
+        let mut len = [0; 4];
+        inp.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if !(TAG_SIZE..=MAX_FRAME + TAG_SIZE).contains(&len) {
+            return Err(io::Error::new(ErrorKind::InvalidData, "bad frame length"));
+        }
+        let mut ciphertext = vec![0; len];
+        inp.read_exact(&mut ciphertext)?;
+        let nonce = self.next_nonce()?;
+        let data = self
+            .cipher
+            .decrypt(&nonce, ciphertext.as_slice())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "frame failed to authenticate"))?;
+        Ok((!data.is_empty()).then_some(data))
+    }
+}
+
+/// Encrypt everything read from `inp` until EOF, and close the stream.
+pub fn encrypt_stream(key: &Hash, inp: &mut dyn Read, out: &mut dyn Write) -> io::Result<()> {
+    let mut cipher = FrameCipher::new(key);
+    let mut buf = vec![0; MAX_FRAME];
+    loop {
+        match inp.read(&mut buf) {
+            Ok(0) => return cipher.write_frame(out, &[]),
+            Ok(n) => cipher.write_frame(out, &buf[..n])?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decrypt a stream created by [encrypt_stream] until it is closed.
+pub fn decrypt_stream(key: &Hash, inp: &mut dyn Read, out: &mut dyn Write) -> io::Result<()> {
+    let mut cipher = FrameCipher::new(key);
+    while let Some(data) = cipher.read_frame(inp)? {
+        out.write_all(&data)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_stream() {
+    let key = [7; 32];
+    let data: Vec<u8> = (0..MAX_FRAME * 2 + 10).map(|i| i as u8).collect();
+    let mut sealed = vec![];
+    encrypt_stream(&key, &mut data.as_slice(), &mut sealed).unwrap();
+    let mut opened = vec![];
+    decrypt_stream(&key, &mut sealed.as_slice(), &mut opened).unwrap();
+    assert_eq!(opened, data);
+
+    // truncated before the closing frame
+    let truncated = &sealed[..sealed.len() - (4 + TAG_SIZE)];
+    let err = decrypt_stream(&key, &mut &truncated[..], &mut vec![]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let err = decrypt_stream(&[8; 32], &mut sealed.as_slice(), &mut vec![]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -6,6 +6,8 @@
 use std::fmt::Debug;
 
 pub mod keygen;
+pub mod channel;
+pub mod seal;
 
 pub use k256;
 pub use k256::ecdsa::Error;
@@ -0,0 +1,167 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Seal bytes to a set of public keys.
+
+A random content key encrypts the data with chacha20poly1305.
+The content key is wrapped for every recipient with a key derived from the ECDH secret between a random ephemeral key and the recipient's (schnorr) public key.
+
+[version:u8][ephemeral pubkey:32][#recipients:u16 be]([recipient pubkey:32][wrapped content key:48])*[ciphertext+tag]
+
+Recipient public keys are not hidden.
+**/
+use crate::{Hash, PublicKey, SigningKey};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use k256::schnorr;
+use rand_core::{OsRng, RngCore};
+use thiserror::Error;
+
+pub const SEAL_VERSION: u8 = 1;
+const WRAP_KEY_CONTEXT: &str = "linkspace seal v1 wrap key";
+const WRAPPED_SIZE: usize = 32 + 16;
+const RECIPIENT_SIZE: usize = 32 + WRAPPED_SIZE;
+const HEADER_SIZE: usize = 1 + 32 + 2;
+
+#[derive(Error, Debug)]
+pub enum SealError {
+    #[error("not a sealed message")]
+    BadFormat,
+    #[error("unknown seal version {0}")]
+    Version(u8),
+    #[error("too many recipients")]
+    MaxRecipients,
+    #[error("key is not a recipient")]
+    NotARecipient,
+    #[error("decryption failed")]
+    Decrypt,
+    #[error("invalid public key")]
+    PublicKey(#[from] k256::ecdsa::Error),
+}
+
+/// The ECDH secret between a key and a public key. Both sides derive the same secret.
+pub fn shared_secret(key: &SigningKey, pubkey: &PublicKey) -> Result<Hash, SealError> {
+    let public = schnorr::VerifyingKey::from_bytes(pubkey)?;
+    let shared = k256::ecdh::diffie_hellman(key.0.as_nonzero_scalar(), public.as_affine());
+    Ok((*shared.raw_secret_bytes()).into())
+}
+
+fn wrap_cipher(shared: &Hash, ephemeral: &PublicKey, recipient: &PublicKey) -> ChaCha20Poly1305 {
+    let mut hasher = blake3::Hasher::new_derive_key(WRAP_KEY_CONTEXT);
+    hasher.update(shared);
+    hasher.update(ephemeral);
+    hasher.update(recipient);
+    let key: [u8; 32] = hasher.finalize().into();
+    ChaCha20Poly1305::new(&key.into())
+}
+
+/// Encrypt data such that each recipient can [open] it.
+pub fn seal(recipients: &[PublicKey], data: &[u8]) -> Result<Vec<u8>, SealError> {
+    let count = u16::try_from(recipients.len()).map_err(|_| SealError::MaxRecipients)?;
+    let ephemeral = SigningKey::generate();
+    let ephemeral_pub = ephemeral.pubkey_bytes();
+    let mut content_key = [0; 32];
+    OsRng.fill_bytes(&mut content_key);
+
+    let mut out =
+        Vec::with_capacity(HEADER_SIZE + recipients.len() * RECIPIENT_SIZE + data.len() + 16);
+    out.push(SEAL_VERSION);
+    out.extend_from_slice(&ephemeral_pub);
+    out.extend_from_slice(&count.to_be_bytes());
+    for recipient in recipients {
+        let shared = shared_secret(&ephemeral, recipient)?;
+        // every wrap key is unique, so a zero nonce is never reused
+        let wrapped = wrap_cipher(&shared, &ephemeral_pub, recipient)
+            .encrypt(&Nonce::default(), content_key.as_slice())
+            .map_err(|_| SealError::Decrypt)?;
+        out.extend_from_slice(recipient);
+        out.extend_from_slice(&wrapped);
+    }
+    let ciphertext = ChaCha20Poly1305::new(&content_key.into())
+        .encrypt(
+            &Nonce::default(),
+            Payload {
+                msg: data,
+                aad: &out,
+            },
+        )
+        .map_err(|_| SealError::Decrypt)?;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// The recipients of a sealed message.
+pub fn recipients(sealed: &[u8]) -> Result<Vec<PublicKey>, SealError> {
+    let (_, slots, _) = split(sealed)?;
+    Ok(slots
+        .chunks_exact(RECIPIENT_SIZE)
+        .map(|slot| slot[..32].try_into().unwrap())
+        .collect())
+}
+
+/// Decrypt the result of [seal].
+pub fn open(key: &SigningKey, sealed: &[u8]) -> Result<Vec<u8>, SealError> {
+    let (header, slots, ciphertext) = split(sealed)?;
+    let ephemeral_pub: PublicKey = header[1..33].try_into().unwrap();
+    let pubkey = key.pubkey_bytes();
+    let wrapped = slots
+        .chunks_exact(RECIPIENT_SIZE)
+        .find(|slot| slot[..32] == pubkey)
+        .ok_or(SealError::NotARecipient)?;
+    let shared = shared_secret(key, &ephemeral_pub)?;
+    let content_key = wrap_cipher(&shared, &ephemeral_pub, &pubkey)
+        .decrypt(&Nonce::default(), &wrapped[32..])
+        .map_err(|_| SealError::Decrypt)?;
+    ChaCha20Poly1305::new_from_slice(&content_key)
+        .map_err(|_| SealError::Decrypt)?
+        .decrypt(
+            &Nonce::default(),
+            Payload {
+                msg: ciphertext,
+                aad: &sealed[..header.len() + slots.len()],
+            },
+        )
+        .map_err(|_| SealError::Decrypt)
+}
+
+/// (header, recipient slots, ciphertext)
+type SealParts<'o> = (&'o [u8], &'o [u8], &'o [u8]);
+fn split(sealed: &[u8]) -> Result<SealParts<'_>, SealError> {
+    if sealed.len() < HEADER_SIZE {
+        return Err(SealError::BadFormat);
+    }
+    let (header, rest) = sealed.split_at(HEADER_SIZE);
+    if header[0] != SEAL_VERSION {
+        return Err(SealError::Version(header[0]));
+    }
+    let count = u16::from_be_bytes([header[33], header[34]]) as usize;
+    if rest.len() < count * RECIPIENT_SIZE + 16 {
+        return Err(SealError::BadFormat);
+    }
+    let (slots, ciphertext) = rest.split_at(count * RECIPIENT_SIZE);
+    Ok((header, slots, ciphertext))
+}
+
+#[test]
+pub fn test_seal_open() {
+    let alice = SigningKey::generate();
+    let bob = SigningKey::generate();
+    let eve = SigningKey::generate();
+    let sealed = seal(&[alice.pubkey_bytes(), bob.pubkey_bytes()], b"hello").unwrap();
+    assert_eq!(
+        recipients(&sealed).unwrap(),
+        vec![alice.pubkey_bytes(), bob.pubkey_bytes()]
+    );
+    assert_eq!(open(&alice, &sealed).unwrap(), b"hello");
+    assert_eq!(open(&bob, &sealed).unwrap(), b"hello");
+    assert!(matches!(open(&eve, &sealed), Err(SealError::NotARecipient)));
+
+    let mut tampered = sealed.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(open(&bob, &tampered), Err(SealError::Decrypt)));
+}
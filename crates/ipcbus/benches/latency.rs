@@ -0,0 +1,115 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Cross process write-to-wakeup latency of the ProcBus backends.
+//! The bench re-executes itself as a second process and the two play ping-pong: each waits for the other's value and emits the next.
+//! The one way latency is half the round trip.
+//!
+//! cargo bench -p ipcbus --features futex,inotify --bench latency [ROUNDS]
+//!
+//! Linux 6.18, 1 cpu, 10000 rounds:
+//! ```text
+//!      udp: p50      8.2µs  p90   12.511µs  p99   17.438µs
+//!  inotify: p50    6.642µs  p90   10.366µs  p99   13.956µs
+//!    futex: p50    5.526µs  p90    8.603µs  p99   10.147µs
+//! ```
+use std::{
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+const CHILD_ENV: &str = "IPCBUS_BENCH_CHILD";
+// fixed so repeated runs reuse the same port in the udp registry
+const BUS_ID: u64 = u64::from_be_bytes(*b"lk-bench");
+
+macro_rules! backend {
+    ($bus:ty) => {
+        |path: &Path, child: bool, rounds: u64| -> Vec<Duration> {
+            let bus = <$bus>::new(path).unwrap();
+            bus.init();
+            let wait = |val: u64| {
+                // blocks until the wakeup - the deadline only catches a lost one
+                let deadline = Instant::now() + Duration::from_secs(5);
+                assert!(
+                    bus.wait_for(val, Some(deadline)).is_some(),
+                    "no wakeup for {val}"
+                );
+            };
+            let mut rtt = Vec::with_capacity(rounds as usize);
+            if child {
+                bus.emit(1);
+                for k in 1..=rounds {
+                    wait(2 * k);
+                    bus.emit(2 * k + 1);
+                }
+            } else {
+                wait(1);
+                for k in 1..=rounds {
+                    let start = Instant::now();
+                    bus.emit(2 * k);
+                    wait(2 * k + 1);
+                    rtt.push(start.elapsed());
+                }
+            }
+            rtt
+        }
+    };
+}
+
+fn run(name: &str, child: bool, path: &Path, rounds: u64) -> Vec<Duration> {
+    match name {
+        "udp" => backend!(ipcbus::udp_procbus::ProcBus)(path, child, rounds),
+        #[cfg(feature = "inotify")]
+        "inotify" => backend!(ipcbus::inotify::ProcBus)(path, child, rounds),
+        #[cfg(all(feature = "futex", target_os = "linux"))]
+        "futex" => backend!(ipcbus::futex::ProcBus)(path, child, rounds),
+        _ => panic!("unknown backend {name}"),
+    }
+}
+
+fn main() {
+    let rounds: u64 = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse().ok())
+        .unwrap_or(2000);
+    if let Ok(name) = std::env::var(CHILD_ENV) {
+        let path = std::env::var_os("IPCBUS_BENCH_DIR").unwrap();
+        run(&name, true, Path::new(&path), rounds);
+        return;
+    }
+    let mut backends = vec!["udp"];
+    if cfg!(feature = "inotify") {
+        backends.push("inotify");
+    }
+    if cfg!(all(feature = "futex", target_os = "linux")) {
+        backends.push("futex");
+    }
+    println!("{rounds} rounds, one way latency");
+    for name in backends {
+        let path = std::env::temp_dir().join(format!("ipcbus-bench-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("id"), BUS_ID.to_be_bytes()).unwrap();
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .arg(rounds.to_string())
+            .env(CHILD_ENV, name)
+            .env("IPCBUS_BENCH_DIR", &path)
+            .spawn()
+            .unwrap();
+        let mut rtt = run(name, false, &path, rounds);
+        assert!(child.wait().unwrap().success());
+        let _ = std::fs::remove_dir_all(&path);
+
+        rtt.sort();
+        let pct = |p: usize| rtt[(rtt.len() - 1) * p / 100] / 2;
+        println!(
+            "{name:>8}: p50 {:>10?}  p90 {:>10?}  p99 {:>10?}",
+            pct(50),
+            pct(90),
+            pct(99)
+        );
+    }
+}
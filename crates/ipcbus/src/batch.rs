@@ -0,0 +1,81 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+A summary of a write batch a process can emit with the new log head.
+
+`prev` is the log head before the batch. Following `prev` from a reader's new head back to its old head tells if every batch in between is known.
+The bits are opaque to the bus (linkspace uses them as a bloom filter of the saved packets).
+A reader gets None if a batch is missing, e.g. written by a process that only emits the head.
+**/
+use std::{collections::VecDeque, sync::Mutex};
+
+pub type BatchBits = [u64; 2];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Batch {
+    pub prev: u64,
+    pub end: u64,
+    pub bits: BatchBits,
+}
+
+/// The combined bits of the batches in the range (from,upto]
+pub fn chain_bits(
+    from: u64,
+    upto: u64,
+    mut find: impl FnMut(u64) -> Option<Batch>,
+) -> Option<BatchBits> {
+    let mut bits = [0; 2];
+    let mut at = upto;
+    while at > from {
+        let batch = find(at)?;
+        // a corrupt entry can't loop
+        if batch.prev >= at {
+            return None;
+        }
+        bits[0] |= batch.bits[0];
+        bits[1] |= batch.bits[1];
+        at = batch.prev;
+    }
+    Some(bits)
+}
+
+/// The most recent batches seen by this process
+#[derive(Default)]
+pub struct BatchLog(Mutex<VecDeque<Batch>>);
+impl BatchLog {
+    pub const LEN: usize = 64;
+    pub fn push(&self, batch: Batch) {
+        let mut log = self.0.lock().unwrap();
+        if log.len() == Self::LEN {
+            log.pop_front();
+        }
+        log.push_back(batch);
+    }
+    pub fn bits(&self, from: u64, upto: u64) -> Option<BatchBits> {
+        let log = self.0.lock().unwrap();
+        chain_bits(from, upto, |end| {
+            log.iter().rev().find(|b| b.end == end).copied()
+        })
+    }
+}
+
+#[test]
+fn chain() {
+    let log = BatchLog::default();
+    for (prev, end, bit) in [(0, 10, 1), (10, 15, 2), (20, 30, 4)] {
+        log.push(Batch {
+            prev,
+            end,
+            bits: [bit, 0],
+        });
+    }
+    assert_eq!(log.bits(10, 15), Some([2, 0]));
+    assert_eq!(log.bits(0, 15), Some([3, 0]));
+    assert_eq!(log.bits(15, 15), Some([0, 0]));
+    // the batch ending at 20 is missing
+    assert_eq!(log.bits(10, 30), None);
+    assert_eq!(log.bits(20, 30), Some([4, 0]));
+}
@@ -0,0 +1,252 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Linux only. The log head and a wake sequence live in a shared memory map (ipc.futex in the database directory).
+
+A writer bumps the sequence and wakes every process waiting on it with FUTEX_WAKE.
+Each process has one thread in FUTEX_WAIT that forwards new values to its local listeners.
+There is no registry and no socket, a wake-up costs a single syscall.
+
+The last [RING_LEN] [Batch]es are kept in a ring. Every entry is guarded by a tag (seqlock): odd while being written, 2n+2 once batch n is complete.
+
+```text
+offset 0  : u64 log head
+offset 8  : u32 wake sequence (futex word)
+offset 12 : u32 reserved
+offset 16 : u64 number of batches
+offset 24 : [ u64 tag, u64 prev, u64 end, [u64;2] bits ; RING_LEN ]
+```
+**/
+use std::{
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use event_listener::{Event, EventListener};
+use memmap2::MmapMut;
+
+use crate::batch::{chain_bits, Batch, BatchBits};
+
+pub const RING_LEN: usize = 64;
+const RING_OFFSET: usize = 24;
+const MAP_SIZE: u64 = (RING_OFFSET + RING_LEN * std::mem::size_of::<Entry>()) as u64;
+
+#[repr(C)]
+struct Entry {
+    tag: AtomicU64,
+    prev: AtomicU64,
+    end: AtomicU64,
+    bits: [AtomicU64; 2],
+}
+impl Entry {
+    fn read(&self) -> Option<Batch> {
+        let tag = self.tag.load(Ordering::Acquire);
+        if tag == 0 || tag % 2 == 1 {
+            return None;
+        }
+        let batch = Batch {
+            prev: self.prev.load(Ordering::Relaxed),
+            end: self.end.load(Ordering::Relaxed),
+            bits: [
+                self.bits[0].load(Ordering::Relaxed),
+                self.bits[1].load(Ordering::Relaxed),
+            ],
+        };
+        std::sync::atomic::fence(Ordering::Acquire);
+        (self.tag.load(Ordering::Relaxed) == tag).then_some(batch)
+    }
+}
+
+pub struct ProcBus(Arc<Inner>);
+
+struct Inner {
+    map: MmapMut,
+    // the highest value our listeners were notified of
+    seen: AtomicU64,
+    watch_thread: OnceLock<JoinHandle<()>>,
+    proc: Event,
+}
+
+fn futex(word: &AtomicU32, op: libc::c_int, val: u32) -> libc::c_long {
+    // without FUTEX_PRIVATE_FLAG the kernel keys the futex on the shared page, not our address space
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            op,
+            val,
+            std::ptr::null::<libc::timespec>(),
+        )
+    }
+}
+
+impl ProcBus {
+    pub fn new(path: &Path) -> io::Result<ProcBus> {
+        tracing::debug!("using futex for IPC signals");
+        crate::check_backend(path, Some("ipc.futex"));
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .read(true)
+            .open(path.join("ipc.futex"))?;
+        if file.metadata()?.len() < MAP_SIZE {
+            file.set_len(MAP_SIZE)?;
+        }
+        let map = unsafe {
+            memmap2::MmapOptions::new()
+                .len(MAP_SIZE as usize)
+                .map_mut(&file)?
+        };
+        let bus = ProcBus(Arc::new(Inner {
+            map,
+            seen: Default::default(),
+            watch_thread: Default::default(),
+            proc: Default::default(),
+        }));
+        bus.0.seen.store(bus.val(), Ordering::Relaxed);
+        Ok(bus)
+    }
+
+    fn head(&self) -> &AtomicU64 {
+        unsafe { &*self.0.map.as_ptr().cast() }
+    }
+    fn seq(&self) -> &AtomicU32 {
+        unsafe { &*self.0.map.as_ptr().add(8).cast() }
+    }
+    fn batches(&self) -> &AtomicU64 {
+        unsafe { &*self.0.map.as_ptr().add(16).cast() }
+    }
+    fn ring(&self) -> &[Entry; RING_LEN] {
+        unsafe { &*self.0.map.as_ptr().add(RING_OFFSET).cast() }
+    }
+
+    pub fn val(&self) -> u64 {
+        self.head().load(Ordering::SeqCst)
+    }
+
+    pub fn emit(&self, val: u64) -> u64 {
+        let old = self.head().fetch_max(val, Ordering::SeqCst);
+        if old >= val {
+            return old;
+        }
+        self.seq().fetch_add(1, Ordering::SeqCst);
+        if futex(self.seq(), libc::FUTEX_WAKE, i32::MAX as u32) < 0 {
+            tracing::error!(e=?io::Error::last_os_error(),"IPC futex wake");
+        }
+        tracing::trace!(val, "emit futex");
+        self.notify_local(val);
+        val
+    }
+
+    /// Add the batch to the ring and emit its end
+    pub fn emit_batch(&self, batch: Batch) -> u64 {
+        let n = self.batches().fetch_add(1, Ordering::SeqCst);
+        let entry = &self.ring()[n as usize % RING_LEN];
+        let tag = entry.tag.load(Ordering::Relaxed);
+        // if another writer holds the entry it is left as is and readers see a missing batch
+        if tag % 2 == 0
+            && entry
+                .tag
+                .compare_exchange(tag, 2 * n + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            entry.prev.store(batch.prev, Ordering::Relaxed);
+            entry.end.store(batch.end, Ordering::Relaxed);
+            entry.bits[0].store(batch.bits[0], Ordering::Relaxed);
+            entry.bits[1].store(batch.bits[1], Ordering::Relaxed);
+            entry.tag.store(2 * n + 2, Ordering::Release);
+        }
+        self.emit(batch.end)
+    }
+
+    /// The combined bits of all batches in (from,upto]. None if any batch is unknown
+    pub fn batch_bits(&self, from: u64, upto: u64) -> Option<BatchBits> {
+        chain_bits(from, upto, |end| {
+            self.ring()
+                .iter()
+                .filter_map(Entry::read)
+                .find(|b| b.end == end)
+        })
+    }
+
+    fn notify_local(&self, val: u64) {
+        if self.0.seen.fetch_max(val, Ordering::SeqCst) < val {
+            self.0.proc.notify(usize::MAX);
+        }
+    }
+
+    pub fn init(&self) {
+        self.0.watch_thread.get_or_init(|| {
+            let this = ProcBus(self.0.clone());
+            std::thread::spawn(move || loop {
+                // read the sequence before the value. An emit in between changes the sequence and FUTEX_WAIT returns immediately
+                let seq = this.seq().load(Ordering::SeqCst);
+                this.notify_local(this.val());
+                if futex(this.seq(), libc::FUTEX_WAIT, seq) < 0 {
+                    let e = io::Error::last_os_error();
+                    match e.raw_os_error() {
+                        Some(libc::EAGAIN) | Some(libc::EINTR) => {}
+                        _ => {
+                            tracing::error!(?e, "IPC futex thread stopped");
+                            return;
+                        }
+                    }
+                }
+            })
+        });
+    }
+
+    pub fn next_deadline(&self, deadline: Option<Instant>) -> Option<u64> {
+        let mut listener = EventListener::new();
+        let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
+        listener.as_mut().listen(&self.0.proc);
+        match deadline {
+            Some(d) => {
+                if listener.as_mut().wait_deadline(d).is_none() {
+                    tracing::trace!("Timeout");
+                    return None;
+                }
+            }
+            None => listener.as_mut().wait(),
+        };
+        let val = self.val();
+        tracing::trace!(val, "Wakeup");
+        Some(val)
+    }
+    /// Wait until the value is at least val. Returns None on timeout.
+    /// The listener is registered before the value is checked, so an emit in between is not missed.
+    pub fn wait_for(&self, val: u64, deadline: Option<Instant>) -> Option<u64> {
+        loop {
+            let mut listener = EventListener::new();
+            let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
+            listener.as_mut().listen(&self.0.proc);
+            let current = self.val();
+            if current >= val {
+                return Some(current);
+            }
+            match deadline {
+                Some(d) => listener.as_mut().wait_deadline(d)?,
+                None => listener.as_mut().wait(),
+            };
+        }
+    }
+    pub async fn next_async(&self) -> u64 {
+        let mut listener = EventListener::new();
+        let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
+        listener.as_mut().listen(&self.0.proc);
+        tracing::trace!("Async Wait");
+        listener.await;
+        tracing::trace!("Async Ok");
+        self.val()
+    }
+}
@@ -16,6 +16,7 @@ pub mod multi {
         time::SystemTime,
     };
 
+    use crate::batch::{Batch, BatchBits, BatchLog};
     use event_listener::{Event, EventListener};
     use memmap2::MmapMut;
     use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
@@ -30,10 +31,12 @@ pub mod multi {
         file: File,
         pub proc: Event,
         map: MmapMut,
+        batches: BatchLog,
     }
     impl ProcBus {
         pub fn new(path: &Path) -> io::Result<ProcBus> {
             tracing::debug!("using inotify for IPC signals");
+            crate::check_backend(path, Some("ipc.inotify"));
             let path = path.join("ipc.inotify");
             let file = std::fs::OpenOptions::new()
                 .create(true)
@@ -49,6 +52,7 @@ pub mod multi {
                 path,
                 file,
                 map,
+                batches: Default::default(),
             })))
         }
     }
@@ -64,6 +68,15 @@ pub mod multi {
         pub fn emit(&self, val: u64) -> u64 {
             self._emit::<false>(val)
         }
+        /// Emit the end of the batch. The summary is only known within this process
+        pub fn emit_batch(&self, batch: Batch) -> u64 {
+            self.0.batches.push(batch);
+            self.emit(batch.end)
+        }
+        /// The combined bits of all batches in (from,upto]. None if any batch is unknown
+        pub fn batch_bits(&self, from: u64, upto: u64) -> Option<BatchBits> {
+            self.0.batches.bits(from, upto)
+        }
         pub fn _emit<const SKIP_NOTIFY: bool>(&self, val: u64) -> u64 {
             let at = self.val_ptr().fetch_max(val, Ordering::Relaxed);
             if at == val {
@@ -94,7 +107,11 @@ pub mod multi {
                 std::thread::spawn(move || {
                     let instance = Inotify::init(InitFlags::empty()).unwrap();
                     let _wd = instance
-                        .add_watch(&this.0.path, AddWatchFlags::IN_ACCESS)
+                        .add_watch(
+                            &this.0.path,
+                            // setting the access time is reported as IN_ATTRIB
+                            AddWatchFlags::IN_ACCESS | AddWatchFlags::IN_ATTRIB,
+                        )
                         .unwrap();
                     let mut file = unsafe { File::from_raw_fd(instance.as_fd().as_raw_fd()) };
                     let mut buf = [0; 32];
@@ -106,10 +123,10 @@ pub mod multi {
             });
         }
 
-        pub fn next_d(&self, deadline: Option<Instant>) -> Option<u64> {
-            let mut listener = EventListener::new(&self.0.proc);
+        pub fn next_deadline(&self, deadline: Option<Instant>) -> Option<u64> {
+            let mut listener = EventListener::new();
             let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
-            listener.as_mut().listen();
+            listener.as_mut().listen(&self.0.proc);
             match deadline {
                 Some(d) => {
                     if listener.as_mut().wait_deadline(d).is_none() {
@@ -122,14 +139,31 @@ pub mod multi {
             tracing::trace!("Wakeup");
             Some(self.val_ptr().load(Ordering::SeqCst))
         }
+        /// Wait until the value is at least val. Returns None on timeout.
+        /// The listener is registered before the value is checked, so an emit in between is not missed.
+        pub fn wait_for(&self, val: u64, deadline: Option<Instant>) -> Option<u64> {
+            loop {
+                let mut listener = EventListener::new();
+                let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
+                listener.as_mut().listen(&self.0.proc);
+                let current = self.val();
+                if current >= val {
+                    return Some(current);
+                }
+                match deadline {
+                    Some(d) => listener.as_mut().wait_deadline(d)?,
+                    None => listener.as_mut().wait(),
+                };
+            }
+        }
         pub async fn next_async(&self) -> u64 {
-            let mut listener = EventListener::new(&self.0.proc);
+            let mut listener = EventListener::new();
             let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
-            listener.as_mut().listen();
+            listener.as_mut().listen(&self.0.proc);
             tracing::trace!("Async Wait");
             listener.await;
             tracing::trace!("Async Ok");
-            self.0.val.load(Ordering::SeqCst)
+            self.val_ptr().load(Ordering::SeqCst)
         }
     }
 }
@@ -5,7 +5,16 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 #![feature(maybe_uninit_slice, file_set_times)]
 
-#[cfg(feature = "inotify")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+
+// The ProcBus is picked by feature: futex (linux only), inotify, and udp otherwise.
+#[cfg(all(feature = "futex", target_os = "linux"))]
+pub use futex::ProcBus;
+#[cfg(all(feature = "futex", target_os = "linux"))]
+pub mod futex;
+
+#[cfg(all(feature = "inotify", not(all(feature = "futex", target_os = "linux"))))]
 pub use inotify::ProcBus;
 #[cfg(feature = "inotify")]
 pub mod inotify;
@@ -17,6 +26,21 @@ pub mod udp_procbus;
 #[cfg(not(target_arch = "wasm32"))]
 pub use udp_procbus::*;
 
+/// Processes sharing a database must use the same backend, otherwise they miss each other's wake-ups.
+/// Warn if the directory holds the file of a backend other than own.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn check_backend(dir: &std::path::Path, own: Option<&str>) {
+    for file in ["ipc.futex", "ipc.inotify"] {
+        if Some(file) != own && dir.join(file).exists() {
+            tracing::warn!(
+                ?dir,
+                file,
+                "a process with another ipcbus backend (futex/inotify feature) used this database. Processes with different backends do not wake each other. Remove the file if no such process is running"
+            );
+        }
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 pub mod wasmbus;
 #[cfg(target_arch = "wasm32")]
@@ -13,6 +13,7 @@ use std::{
     time::Instant,
 };
 
+use crate::batch::{Batch, BatchBits, BatchLog};
 pub use crate::udp_multicast::UdpIPC;
 pub use event_listener;
 use event_listener::{Event, EventListener};
@@ -62,11 +63,14 @@ struct Inner {
     listener: OnceLock<JoinHandle<()>>,
     proc: Event,
     bus_id: u64,
+    // batches are not sent over udp (older receivers expect a fixed message size)
+    batches: BatchLog,
 }
 
 impl ProcBus {
     pub fn new(path: &Path) -> std::io::Result<ProcBus> {
         tracing::debug!("using UDP for IPC signals");
+        crate::check_backend(path, None);
         let bus_id = u64::from_be_bytes(
             std::fs::read(path.join("id"))
                 .expect("missing id file")
@@ -83,12 +87,22 @@ impl ProcBus {
             val: Default::default(),
             listener: OnceLock::new(),
             proc: Default::default(),
+            batches: Default::default(),
         })))
     }
 
     pub fn emit(&self, val: u64) -> u64 {
         self._emit::<false>(val)
     }
+    /// Emit the end of the batch. The summary is only known within this process
+    pub fn emit_batch(&self, batch: Batch) -> u64 {
+        self.0.batches.push(batch);
+        self.emit(batch.end)
+    }
+    /// The combined bits of all batches in (from,upto]. None if any batch is unknown
+    pub fn batch_bits(&self, from: u64, upto: u64) -> Option<BatchBits> {
+        self.0.batches.bits(from, upto)
+    }
     pub fn _emit<const SKIP_UDP: bool>(&self, val: u64) -> u64 {
         let mut old = self.0.val.load(Ordering::Relaxed);
         tracing::trace!(old, val, "Emit determine");
@@ -148,9 +162,9 @@ impl ProcBus {
     pub fn next_deadline(&self, deadline: Option<Instant>) -> Option<u64> {
         tracing::trace!(ptr=%format!("{:p}",&self.0.val)
                         ,val=&self.0.val.load(Ordering::Relaxed),"Waiting");
-        let mut listener = EventListener::new(&self.0.proc);
+        let mut listener = EventListener::new();
         let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
-        listener.as_mut().listen();
+        listener.as_mut().listen(&self.0.proc);
         match deadline {
             Some(d) => {
                 if listener.as_mut().wait_deadline(d).is_none() {
@@ -164,10 +178,27 @@ impl ProcBus {
         tracing::trace!(val, "Wakeup");
         Some(val)
     }
+    /// Wait until the value is at least val. Returns None on timeout.
+    /// The listener is registered before the value is checked, so an emit in between is not missed.
+    pub fn wait_for(&self, val: u64, deadline: Option<Instant>) -> Option<u64> {
+        loop {
+            let mut listener = EventListener::new();
+            let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
+            listener.as_mut().listen(&self.0.proc);
+            let current = self.val();
+            if current >= val {
+                return Some(current);
+            }
+            match deadline {
+                Some(d) => listener.as_mut().wait_deadline(d)?,
+                None => listener.as_mut().wait(),
+            };
+        }
+    }
     pub async fn next_async(&self) -> u64 {
-        let mut listener = EventListener::new(&self.0.proc);
+        let mut listener = EventListener::new();
         let mut listener = unsafe { std::pin::Pin::new_unchecked(&mut listener) };
-        listener.as_mut().listen();
+        listener.as_mut().listen(&self.0.proc);
         tracing::trace!("Async Wait");
         listener.await;
         tracing::trace!("Async Ok");
@@ -0,0 +1,241 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Blobs store data larger than [MAX_DATA_SIZE] as a tree of points.
+
+The data is split into datapoints on content defined boundaries (a gear rolling hash - similar to FastCDC).
+An edit only changes the chunks around it, so near identical data shares most of its datapoints.
+The chunks are linked by index linkpoints in DOMAIN:GROUP:/\fblob with up to [BLOB_FANOUT] links each.
+An index linkpoint has a zero create stamp, and its data is the size of its sub tree as a big endian u64.
+The hash of the root index is the hash of the blob.
+
+```text
+root (index)
+chunk: datapoint
+...
+index: index linkpoint
+```
+The chunking parameters are part of the format and do not change.
+**/
+use std::io::Read;
+
+use crate::*;
+
+/// const '/\fblob' spacename
+pub static BLOB_SPACE: RootedStaticSpace<14> = rspace1::<5>(concat_bytes!([255], b"blob"));
+/// A chunk is never cut before this size unless the data ends
+pub const BLOB_MIN_CHUNK: usize = 1 << 13;
+pub const BLOB_MAX_CHUNK: usize = MAX_DATA_SIZE;
+/// Maximum number of links in an index linkpoint
+pub const BLOB_FANOUT: usize = 1024;
+// 15 bits => on average a cut every 32kb after BLOB_MIN_CHUNK
+const CUT_MASK: u64 = ((1 << 15) - 1) << 49;
+
+static GEAR: [u64; 256] = {
+    // splitmix64
+    let mut table = [0u64; 256];
+    let mut x: u64 = u64::from_be_bytes(*b"linkspac");
+    let mut i = 0;
+    while i < 256 {
+        x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// The length of the next chunk. buf must hold at least [BLOB_MAX_CHUNK] bytes unless the data ends.
+pub fn blob_cut(buf: &[u8]) -> usize {
+    let max = buf.len().min(BLOB_MAX_CHUNK);
+    let mut h: u64 = 0;
+    for (i, b) in buf.iter().enumerate().take(max).skip(BLOB_MIN_CHUNK) {
+        h = (h << 1).wrapping_add(GEAR[*b as usize]);
+        if h & CUT_MASK == 0 {
+            return i + 1;
+        }
+    }
+    max
+}
+
+struct BlobTree<'o> {
+    domain: Domain,
+    group: GroupID,
+    // levels[0] links to chunks, levels[n] links to indexes of levels[n-1]
+    levels: Vec<Vec<(Link, u64)>>,
+    out: &'o mut dyn FnMut(NetPktBox) -> LkResult<()>,
+}
+impl<'o> BlobTree<'o> {
+    fn push(&mut self, level: usize, link: Link, size: u64) -> LkResult<()> {
+        if self.levels.len() <= level {
+            self.levels.push(vec![]);
+        }
+        self.levels[level].push((link, size));
+        if self.levels[level].len() == BLOB_FANOUT {
+            self.flush(level)?;
+        }
+        Ok(())
+    }
+    fn flush(&mut self, level: usize) -> LkResult<()> {
+        let entries = std::mem::take(&mut self.levels[level]);
+        let size: u64 = entries.iter().map(|(_, s)| s).sum();
+        let links: Vec<Link> = entries.into_iter().map(|(l, _)| l).collect();
+        let index = lk_linkpoint(
+            &size.to_be_bytes(),
+            self.domain,
+            self.group,
+            &BLOB_SPACE,
+            &links,
+            Some(Stamp::ZERO),
+        )?;
+        let link = Link {
+            tag: ab(b"index"),
+            ptr: index.hash(),
+        };
+        (self.out)(index)?;
+        self.push(level + 1, link, size)
+    }
+    fn finish(mut self) -> LkResult<LkHash> {
+        if self.levels.is_empty() {
+            self.levels.push(vec![]);
+        }
+        let mut level = 0;
+        loop {
+            let top = level + 1 == self.levels.len();
+            if top && level > 0 && self.levels[level].len() == 1 {
+                return Ok(self.levels[level][0].0.ptr);
+            }
+            if top || !self.levels[level].is_empty() {
+                self.flush(level)?;
+            }
+            level += 1;
+        }
+    }
+}
+
+/// Split the data into points. Calls out for every point in an order such that links point to earlier points. Returns the blob hash.
+pub fn lk_blob_points(
+    domain: Domain,
+    group: GroupID,
+    mut reader: impl Read,
+    out: &mut dyn FnMut(NetPktBox) -> LkResult<()>,
+) -> LkResult<LkHash> {
+    let mut tree = BlobTree {
+        domain,
+        group,
+        levels: vec![],
+        out,
+    };
+    let mut buf = Vec::with_capacity(2 * BLOB_MAX_CHUNK);
+    let mut eof = false;
+    loop {
+        if !eof && buf.len() < BLOB_MAX_CHUNK {
+            let mut limit = (&mut reader).take((2 * BLOB_MAX_CHUNK - buf.len()) as u64);
+            eof = limit.read_to_end(&mut buf)? == 0;
+            continue;
+        }
+        if buf.is_empty() {
+            break;
+        }
+        let len = blob_cut(&buf);
+        let chunk = lk_datapoint(&buf[..len])?;
+        let link = Link {
+            tag: ab(b"chunk"),
+            ptr: chunk.hash(),
+        };
+        (tree.out)(chunk)?;
+        tree.push(0, link, len as u64)?;
+        buf.drain(..len);
+    }
+    tree.finish()
+}
+
+#[cfg(feature = "runtime")]
+/// Save the data as a blob. Returns the blob hash
+pub fn lk_blob_write(
+    lk: &Linkspace,
+    domain: Domain,
+    group: GroupID,
+    reader: impl Read,
+) -> LkResult<LkHash> {
+    lk_blob_points(domain, group, reader, &mut |pkt| {
+        lk_save(lk, &pkt)?;
+        Ok(())
+    })
+}
+
+#[cfg(feature = "runtime")]
+/// Read a blob from the database. The reader errors with [std::io::ErrorKind::NotFound] when a point is missing.
+pub fn lk_blob_read(lk: &Linkspace, hash: LkHash) -> LkBlobReader {
+    LkBlobReader {
+        lk: lk.clone(),
+        stack: vec![hash],
+        chunk: vec![],
+        pos: 0,
+    }
+}
+
+#[cfg(feature = "runtime")]
+/// See [lk_blob_read]
+pub struct LkBlobReader {
+    lk: Linkspace,
+    // the hashes to read in reverse order
+    stack: Vec<LkHash>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "runtime")]
+enum Found {
+    Chunk(Vec<u8>),
+    Index(Vec<LkHash>),
+    Other,
+}
+#[cfg(feature = "runtime")]
+impl Read for LkBlobReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::{Error, ErrorKind};
+        loop {
+            if self.pos < self.chunk.len() {
+                let n = out.len().min(self.chunk.len() - self.pos);
+                out[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+                self.pos += n;
+                return Ok(n);
+            }
+            let Some(hash) = self.stack.pop() else {
+                return Ok(0);
+            };
+            let mut found = None;
+            crate::runtime::lk_get_hashes(&self.lk, &[hash], &mut |p| {
+                found = Some(if p.is_datapoint() {
+                    Found::Chunk(p.data().to_vec())
+                } else if p.get_rooted_spacename() == &*BLOB_SPACE {
+                    Found::Index(p.get_links().iter().rev().map(|l| l.ptr).collect())
+                } else {
+                    Found::Other
+                });
+                true
+            })
+            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            match found {
+                None => return Err(Error::new(ErrorKind::NotFound, format!("missing {hash}"))),
+                Some(Found::Chunk(data)) => {
+                    self.chunk = data;
+                    self.pos = 0;
+                }
+                Some(Found::Index(links)) => self.stack.extend(links),
+                Some(Found::Other) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{hash} is not a blob point"),
+                    ))
+                }
+            }
+        }
+    }
+}
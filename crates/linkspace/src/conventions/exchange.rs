@@ -0,0 +1,290 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+The session a group exchange process runs with a peer once connected (e.g. lk-exchange).
+
+[lk_exchange_session] sets up the watches of one side:
+- 'push' sends every packet in GROUP created locally (hop=0) and received after the txlog
+- 'pull-requests' forwards the local pull requests for GROUP (see [crate::conventions::pull]) as keypoints in GROUP
+- 'their-pulls' answers the pull requests of the peer by watching the query and sending the results until the pull is closed
+
+and replies to the status requests 'exchange GROUP connection PUBKEY' and 'exchange GROUP pull PULL_HASH'.
+
+Packets for the peer are passed to the outbox function - at most once per session.
+Moving them to the peer and saving what the peer sends is up to the caller.
+The watches are registered in the runtime. Use a runtime per session and drop it to end the session.
+
+The recv stamp of the last packet pushed to a peer is kept as the create stamp of \[f:exchange\]:\[#:0\]:/txlog/PUBKEY.
+A session pushes what was received after. See [LkExchangeSession::save_txlog].
+**/
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    rc::Rc,
+};
+
+use linkspace_common::prelude::{EXCHANGE_DOMAIN, U32};
+use tracing::debug_span;
+
+use crate::{
+    conventions::{
+        exchange_acl::{lk_exchange_acl_check_pull_query, lk_exchange_acl_parse_pull},
+        status::{lk_status_set, LkStatus},
+    },
+    runtime::{cb::try_cb, lk_get_ref, lk_stop, lk_watch2, vspan},
+    *,
+};
+
+#[derive(Copy, Clone)]
+/// Options for one side of an exchange session
+pub struct LkExchange<'o> {
+    /// signs the forwarded pull requests
+    pub key: &'o SigningKey,
+    /// the group to exchange
+    pub group: GroupID,
+    /// the key of the peer
+    pub their_key: PubKey,
+    /// only answer pull requests accepted by the most recent pull access list signed by this key (see [crate::conventions::exchange_acl])
+    pub acl_key: Option<PubKey>,
+}
+impl<'o> std::fmt::Debug for LkExchange<'o> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LkExchange")
+            .field("key", &self.key.pubkey())
+            .field("group", &self.group)
+            .field("their_key", &self.their_key)
+            .field("acl_key", &self.acl_key)
+            .finish()
+    }
+}
+
+/// Packets for the peer. Every packet is send at most once per session.
+struct Outbox {
+    send: Box<dyn FnMut(&dyn NetPkt) -> LkResult<()>>,
+    sent: HashSet<LkHash>,
+}
+impl Outbox {
+    fn send(&mut self, pkt: &dyn NetPkt) -> LkResult<()> {
+        if self.sent.insert(pkt.hash()) {
+            tracing::debug!(hash=%pkt.hash(), "tx");
+            (self.send)(pkt)?;
+        }
+        Ok(())
+    }
+}
+
+/// The txlog of a session started with [lk_exchange_session]
+#[derive(Debug)]
+pub struct LkExchangeSession {
+    txlog: RootedSpaceBuf,
+    saved: Stamp,
+    tx_at: Rc<Cell<Stamp>>,
+}
+impl LkExchangeSession {
+    /// the recv stamp of the last packet pushed
+    pub fn last_tx(&self) -> Stamp {
+        self.tx_at.get()
+    }
+    /// Save [Self::last_tx] as the txlog if it changed. Returns true if saved.
+    /// A txlog is never pushed again, only save it once the peer has (or will have) the packets.
+    pub fn save_txlog(&mut self, lk: &Linkspace) -> LkResult<bool> {
+        let last_tx = self.tx_at.get();
+        if last_tx == self.saved {
+            return Ok(false);
+        }
+        let pkt = lk_linkpoint(
+            &[],
+            EXCHANGE_DOMAIN,
+            PRIVATE,
+            &self.txlog,
+            &[],
+            Some(last_tx),
+        )?;
+        lk_save(lk, &pkt)?;
+        self.saved = last_tx;
+        Ok(true)
+    }
+}
+
+/// Setup the watches of a session with their_key. See the module documentation.
+pub fn lk_exchange_session(
+    lk: &Linkspace,
+    exchange: LkExchange,
+    outbox: impl FnMut(&dyn NetPkt) -> LkResult<()> + 'static,
+) -> LkResult<LkExchangeSession> {
+    let LkExchange {
+        key,
+        group,
+        their_key,
+        acl_key,
+    } = exchange;
+    let outbox = Rc::new(RefCell::new(Outbox {
+        send: Box::new(outbox),
+        sent: HashSet::new(),
+    }));
+
+    let status = LkStatus {
+        domain: EXCHANGE_DOMAIN,
+        group,
+        objtype: b"connection",
+        instance: Some(their_key.0.as_slice()),
+        qid: b"status-connection",
+    };
+    lk_status_set(lk, status, move |_, domain, group, space, link| {
+        lk_linkpoint(b"OK\n", domain, group, space, &[link], None)
+    })?;
+
+    // send everything created locally since the previous session
+    let txlog = rspace_buf(&[b"txlog", &*their_key]);
+    let mut q = lk_query(&Q);
+    q = lk_query_push(q, "domain", "=", &*EXCHANGE_DOMAIN)?;
+    q = lk_query_push(q, "group", "=", &*PRIVATE)?;
+    q = lk_query_push(q, "spacename", "=", txlog.space_bytes())?;
+    let saved = lk_get_ref(lk, &q, &mut |p| *p.get_create_stamp())?.unwrap_or(Stamp::ZERO);
+    let tx_at = Rc::new(Cell::new(saved));
+    let mut q = lk_query(&Q);
+    q = lk_query_push(q, "group", "=", &*group)?;
+    q = lk_query_push(q, "hop", "=", &*U32::ZERO)?;
+    q = lk_query_push(q, "recv", ">", &*saved)?;
+    q = lk_query_push(q, "", "mode", b"log-asc")?;
+    q = lk_query_push(q, "", "qid", b"push")?;
+    let (out, at) = (outbox.clone(), tx_at.clone());
+    lk_watch2(
+        lk,
+        &q,
+        try_cb(move |pkt: &dyn NetPkt, _: &Linkspace| -> LkResult<()> {
+            out.borrow_mut().send(pkt)?;
+            if let Some(recv) = pkt.recv() {
+                at.set(recv);
+            }
+            Ok(())
+        }),
+        vspan("push"),
+    )?;
+
+    // forward the most recent local pull request per space and all new ones
+    let pull_space = rspace_buf(&[b"pull", &*group]);
+    let mut q = lk_query(&Q);
+    q = lk_query_push(q, "domain", "=", &*EXCHANGE_DOMAIN)?;
+    q = lk_query_push(q, "group", "=", &*PRIVATE)?;
+    q = lk_query_push(q, "prefix", "=", pull_space.space_bytes())?;
+    q = lk_query_push(q, "i_branch", "<", &*U32::from(1))?;
+    q = lk_query_push(q, "", "qid", b"pull-requests")?;
+    let (out, key) = (outbox.clone(), key.clone());
+    lk_watch2(
+        lk,
+        &q,
+        try_cb(move |pkt: &dyn NetPkt, lk: &Linkspace| -> LkResult<()> {
+            let req = lk_keypoint(
+                &key,
+                pkt.data(),
+                EXCHANGE_DOMAIN,
+                group,
+                pkt.get_rooted_spacename(),
+                &[],
+                None,
+            )?;
+            out.borrow_mut().send(&req)?;
+            pull_status(lk, group, their_key, pkt)
+        }),
+        vspan("pull-requests"),
+    )?;
+
+    // answer their (new) pull requests
+    let mut q = lk_query(&Q);
+    q = lk_query_push(q, "domain", "=", &*EXCHANGE_DOMAIN)?;
+    q = lk_query_push(q, "group", "=", &*group)?;
+    q = lk_query_push(q, "prefix", "=", pull_space.space_bytes())?;
+    q = lk_query_push(q, "pubkey", "=", &*their_key)?;
+    q = lk_query_push(q, "i_db", "<", &*U32::ZERO)?;
+    q = lk_query_push(q, "", "qid", b"their-pulls")?;
+    lk_watch2(
+        lk,
+        &q,
+        try_cb(move |pkt: &dyn NetPkt, lk: &Linkspace| -> LkResult<()> {
+            if let Err(e) = serve_pull(lk, group, acl_key, pkt, &outbox) {
+                tracing::warn!(?e, hash=%pkt.hash(), "refused pull");
+            }
+            Ok(())
+        }),
+        vspan("their-pulls"),
+    )?;
+    Ok(LkExchangeSession {
+        txlog,
+        saved,
+        tx_at,
+    })
+}
+
+/// Reply to 'exchange GROUP pull PULL_HASH' for a forwarded pull. A newer pull point for the same space replaces the reply.
+fn pull_status(
+    lk: &Linkspace,
+    group: GroupID,
+    their_key: PubKey,
+    pkt: &dyn NetPkt,
+) -> LkResult<()> {
+    let mut qid = b"status-pull".to_vec();
+    qid.extend_from_slice(pkt.get_rooted_spacename().space_bytes());
+    if pkt.data().is_empty() {
+        lk_stop(lk, &qid, false);
+        return Ok(());
+    }
+    let hash = pkt.hash();
+    let status = LkStatus {
+        domain: EXCHANGE_DOMAIN,
+        group,
+        objtype: b"pull",
+        instance: Some(&*hash),
+        qid: &qid,
+    };
+    let info = format!("OK\nforwarded to {their_key}\n");
+    lk_status_set(lk, status, move |_, domain, group, space, link| {
+        lk_linkpoint(info.as_bytes(), domain, group, space, &[link], None)
+    })
+}
+
+/// Watch the query of a pull request and send the results. A new request for the same space replaces the watch, an empty one closes it.
+fn serve_pull(
+    lk: &Linkspace,
+    group: GroupID,
+    acl_key: Option<PubKey>,
+    pkt: &dyn NetPkt,
+    out: &Rc<RefCell<Outbox>>,
+) -> LkResult<()> {
+    let mut qid = b"pull".to_vec();
+    qid.extend_from_slice(pkt.get_rooted_spacename().space_bytes());
+    if pkt.data().is_empty() {
+        tracing::info!(hash=%pkt.hash(), "closing pull");
+        lk_stop(lk, &qid, false);
+        return Ok(());
+    }
+    // parsed once so the access list checks the query that is served
+    let pull = lk_exchange_acl_parse_pull(pkt)?;
+    if let Some(acl_key) = acl_key {
+        let tag = lk_exchange_acl_check_pull_query(lk, acl_key, &pull)?;
+        anyhow::ensure!(tag.is_some(), "not accepted by the pull access list");
+    }
+    anyhow::ensure!(
+        pull.0.predicates.group.as_eq().map(GroupID::from) == Some(group),
+        "pull request outside of group"
+    );
+    // only the predicates and mode are used, the options of the peer are not
+    let mut query = lk_query(&Q);
+    query.0.predicates = pull.0.predicates.clone();
+    if let Some(mode) = pull.0.mode()? {
+        query.0.add_option("mode", &[mode.to_string().as_bytes()]);
+    }
+    query.0.add_option("qid", &[qid.as_slice()]);
+    tracing::info!(hash=%pkt.hash(), query=%query, "serving pull");
+    let out = out.clone();
+    lk_watch2(
+        lk,
+        &query,
+        try_cb(move |pkt: &dyn NetPkt, _: &Linkspace| out.borrow_mut().send(pkt)),
+        debug_span!("pull", hash=%pkt.hash()),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+Access control lists tell an exchange which queries it should host for a group.
+
+A list is a keypoint in DOMAIN:GROUP:/\fexchange/access/pull (or /push).
+Every link points to a datapoint holding a query template (see [crate::query::lk_query_template]). The tag is the name of the template.
+The placeholders `{domain}` and `{group}` are filled in when checking a query.
+
+```text
+DOMAIN:GROUP:/\fexchange/access/pull
+chat    : ptr to "prefix:=:/chat\ni_db:<:[u32:1000]"
+profile : ptr to "spacename:=:/profile\ni_branch:<:[u32:1]"
+```
+
+The most recent list signed by a trusted key is used.
+Which keys are trusted (e.g. the group operator) is up to the exchange process.
+A query is accepted if it satisfies any template of the list. Without a list nothing is accepted.
+**/
+use anyhow::Context;
+use linkspace_common::prelude::CORE_SCOPE;
+
+use crate::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The kind of access a list grants
+pub enum LkAccess {
+    /// queries the exchange will fetch from its peers
+    Pull,
+    /// queries the exchange will serve to its peers
+    Push,
+}
+impl LkAccess {
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LkAccess::Pull => b"pull",
+            LkAccess::Push => b"push",
+        }
+    }
+}
+
+/// The rooted spacename '/\fexchange/access/(pull|push)'
+pub fn lk_exchange_acl_space(access: LkAccess) -> RootedSpaceBuf {
+    rspace_buf(&[b"\xffexchange", b"access", access.as_bytes()])
+}
+
+/// Create the template datapoints followed by the list keypoint. Prefer [lk_exchange_acl_set]
+pub fn lk_exchange_acl_points(
+    key: &SigningKey,
+    domain: Domain,
+    group: GroupID,
+    access: LkAccess,
+    templates: &[(Tag, &str)],
+) -> LkResult<Vec<NetPktBox>> {
+    let mut pkts = templates
+        .iter()
+        .map(|(_, t)| lk_datapoint(t.as_bytes()))
+        .collect::<LkResult<Vec<_>>>()?;
+    let links: Vec<Link> = templates
+        .iter()
+        .zip(&pkts)
+        .map(|((tag, _), p)| Link {
+            tag: *tag,
+            ptr: p.hash(),
+        })
+        .collect();
+    let space = lk_exchange_acl_space(access);
+    pkts.push(lk_keypoint(key, &[], domain, group, &space, &links, None)?);
+    Ok(pkts)
+}
+
+/// Fill the templates with the domain and group and return the name of the first template the query satisfies
+pub fn lk_exchange_acl_check(
+    templates: &[(Tag, String)],
+    domain: Domain,
+    group: GroupID,
+    query: &Query,
+) -> LkResult<Option<Tag>> {
+    let vars: [(&str, &[u8]); 2] = [("domain", &*domain), ("group", &*group)];
+    for (tag, template) in templates {
+        let template =
+            lk_query_template(template, &vars, ()).with_context(|| format!("template '{tag}'"))?;
+        if lk_query_satisfies(&template, query) {
+            return Ok(Some(*tag));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "runtime")]
+/// Save a new list of templates. Returns the hash of the list keypoint
+pub fn lk_exchange_acl_set(
+    lk: &Linkspace,
+    key: &SigningKey,
+    domain: Domain,
+    group: GroupID,
+    access: LkAccess,
+    templates: &[(Tag, &str)],
+) -> LkResult<LkHash> {
+    use crate::runtime::lk_save_all;
+    let pkts = lk_exchange_acl_points(key, domain, group, access, templates)?;
+    let hash = pkts.last().unwrap().hash();
+    let refs: Vec<&dyn NetPkt> = pkts.iter().map(|p| p as &dyn NetPkt).collect();
+    lk_save_all(lk, &refs)?;
+    Ok(hash)
+}
+
+#[cfg(feature = "runtime")]
+/// Read the templates of the most recent list signed by pubkey. Errors if a template datapoint is missing
+pub fn lk_exchange_acl_get(
+    lk: &Linkspace,
+    domain: Domain,
+    group: GroupID,
+    access: LkAccess,
+    pubkey: PubKey,
+) -> LkResult<Option<Vec<(Tag, String)>>> {
+    use crate::runtime::{lk_get_hashes, lk_get_ref};
+    let space = lk_exchange_acl_space(access);
+    let mut q = lk_query(&Q);
+    q = lk_query_push(q, "domain", "=", &*domain)?;
+    q = lk_query_push(q, "group", "=", &*group)?;
+    q = lk_query_push(q, "spacename", "=", space.space_bytes())?;
+    q = lk_query_push(q, "pubkey", "=", &*pubkey)?;
+    let links = match lk_get_ref(lk, &q, &mut |p| p.get_links().to_vec())? {
+        Some(links) => links,
+        None => return Ok(None),
+    };
+    let hashes: Vec<LkHash> = links.iter().map(|l| l.ptr).collect();
+    let mut found = std::collections::HashMap::new();
+    lk_get_hashes(lk, &hashes, &mut |p| {
+        found.insert(p.hash(), p.data().to_vec());
+        false
+    })?;
+    links
+        .into_iter()
+        .map(|l| {
+            let data = found
+                .get(&l.ptr)
+                .with_context(|| format!("missing template '{}' {}", l.tag, l.ptr))?;
+            Ok((l.tag, String::from_utf8(data.clone())?))
+        })
+        .collect::<LkResult<Vec<_>>>()
+        .map(Some)
+}
+
+#[cfg(feature = "runtime")]
+/// Check a pull point (see [crate::conventions::pull]) against the most recent pull list signed by pubkey.
+/// Returns the name of the accepting template
+pub fn lk_exchange_acl_check_pull(
+    lk: &Linkspace,
+    pubkey: PubKey,
+    pull_point: &dyn NetPkt,
+) -> LkResult<Option<Tag>> {
+    let query = lk_exchange_acl_parse_pull(pull_point)?;
+    lk_exchange_acl_check_pull_query(lk, pubkey, &query)
+}
+
+/// Parse the query of a pull point the way an exchange serves it - with the core scope only.
+pub fn lk_exchange_acl_parse_pull(pull_point: &dyn NetPkt) -> LkResult<Query> {
+    let mut query = lk_query(&Q);
+    query
+        .0
+        .parse(pull_point.data(), &CORE_SCOPE)
+        .context("invalid pull query")?;
+    Ok(query)
+}
+
+#[cfg(feature = "runtime")]
+/// [lk_exchange_acl_check_pull] with the query already parsed by [lk_exchange_acl_parse_pull]
+pub fn lk_exchange_acl_check_pull_query(
+    lk: &Linkspace,
+    pubkey: PubKey,
+    query: &Query,
+) -> LkResult<Option<Tag>> {
+    let group: GroupID = query
+        .0
+        .predicates
+        .group
+        .as_eq()
+        .context("requires exact group predicate")?
+        .into();
+    let domain: Domain = query
+        .0
+        .predicates
+        .domain
+        .as_eq()
+        .context("requires exact domain predicate")?
+        .into();
+    match lk_exchange_acl_get(lk, domain, group, LkAccess::Pull, pubkey)? {
+        Some(templates) => lk_exchange_acl_check(&templates, domain, group, query),
+        None => Ok(None),
+    }
+}
@@ -4,6 +4,13 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+/// store data larger than a datapoint as a tree of points.
+pub mod blob;
+/// the session of an exchange process with a peer.
+#[cfg(feature = "runtime")]
+pub mod exchange;
+/// access control lists for exchange processes.
+pub mod exchange_acl;
 /// utility functions for making pull requests.
 pub mod pull;
 /// utility functions for the private status convention.
@@ -6,6 +6,11 @@ The domain should be conservative with its query.
 Requesting too much can add significant overhead.
 
 You can use [lk_status_watch] to determine if a exchange is active
+
+A pull is closed by saving an empty pull point to the same spacename ([lk_pull_close]).
+[lk_pull_watch] watches the query and closes the pull once the watch stops.
+
+An exchange replies to the status `exchange GROUP pull PULL_HASH` (see [lk_pull_status]) once it forwards the pull.
  **/
 use anyhow::Context;
 use linkspace_common::prelude::EXCHANGE_DOMAIN;
@@ -19,8 +24,92 @@ pub fn lk_pull(lk: &Linkspace, query: &Query) -> LkResult<LkHash> {
     lk_save(lk, &req)?;
     Ok(req.hash())
 }
+#[cfg(feature = "runtime")]
+/// Supersede the pull of the query with an empty pull point. Returns the hash of the close point
+pub fn lk_pull_close(lk: &Linkspace, query: &Query) -> LkResult<LkHash> {
+    let req = lk_pull_close_point(query)?;
+    lk_save(lk, &req)?;
+    Ok(req.hash())
+}
+
+#[cfg(feature = "runtime")]
+/// [lk_pull] and [crate::runtime::lk_watch] the query. The pull is closed when the watch breaks, finishes, or is stopped.
+/// Re-issuing the query with the same qid keeps the new pull open.
+pub fn lk_pull_watch(
+    lk: &Linkspace,
+    query: &Query,
+    cb: impl crate::runtime::cb::PktHandler + 'static,
+) -> LkResult<LkHash> {
+    // watch first - it closes a previous watch with the same qid, whose close point must precede the new pull
+    let watch = crate::runtime::lk_watch(lk, query, ClosePull(cb))?;
+    let hash = lk_pull(lk, query)?;
+    // positive => the callback broke while reading the database and the watch was not registered
+    if watch > 0 {
+        lk_pull_close(lk, query)?;
+    }
+    Ok(hash)
+}
+
+#[cfg(feature = "runtime")]
+struct ClosePull<H>(H);
+#[cfg(feature = "runtime")]
+impl<H: crate::runtime::cb::PktHandler> crate::runtime::cb::PktHandler for ClosePull<H> {
+    fn handle_pkt(&mut self, pkt: &dyn NetPkt, lk: &Linkspace) -> std::ops::ControlFlow<()> {
+        self.0.handle_pkt(pkt, lk)
+    }
+    fn stopped(
+        &mut self,
+        query: Query,
+        lk: &Linkspace,
+        reason: crate::runtime::cb::StopReason,
+        total_calls: u32,
+        watch_calls: u32,
+    ) {
+        use crate::runtime::cb::StopReason;
+        if !matches!(reason, StopReason::Replaced) {
+            if let Err(e) = lk_pull_close(lk, &query) {
+                tracing::warn!(?e, "could not close pull");
+            }
+        }
+        self.0.stopped(query, lk, reason, total_calls, watch_calls)
+    }
+}
+
+#[cfg(feature = "runtime")]
+/// [crate::conventions::status::lk_status_watch] for the status `exchange GROUP pull PULL_HASH` - i.e. if an exchange has forwarded the pull.
+/// pull is the hash returned by [lk_pull]. The status is watched with the qid 'pull-status:QID'
+pub fn lk_pull_status(
+    lk: &Linkspace,
+    query: &Query,
+    pull: LkHash,
+    d_timeout: Stamp,
+    cb: impl crate::runtime::cb::PktHandler + 'static,
+) -> LkResult<bool> {
+    use crate::conventions::status::{lk_status_watch, LkStatus};
+    let (group, _, id) = pull_ids(query)?;
+    let qid = [b"pull-status:", id].concat();
+    let status = LkStatus {
+        domain: EXCHANGE_DOMAIN,
+        group,
+        objtype: b"pull",
+        instance: Some(&*pull),
+        qid: &qid,
+    };
+    lk_status_watch(lk, status, d_timeout, None, cb)
+}
+
 /// Prefer using [lk_pull] - creates a pullpoint from a query
 pub fn lk_pull_point(query: &Query) -> LkResult<NetPktBox> {
+    let data = query.0.to_string();
+    tracing::trace!(data);
+    pull_point(query, data.as_bytes())
+}
+/// Prefer using [lk_pull_close] - creates the empty pullpoint that closes the pull of a query
+pub fn lk_pull_close_point(query: &Query) -> LkResult<NetPktBox> {
+    pull_point(query, &[])
+}
+
+fn pull_ids(query: &Query) -> LkResult<(GroupID, Domain, &[u8])> {
     let group: GroupID = query
         .0
         .predicates
@@ -40,16 +129,11 @@ pub fn lk_pull_point(query: &Query) -> LkResult<NetPktBox> {
         .qid()?
         .flatten()
         .context("missing :qid:... option")?;
-    let data = query.0.to_string();
-    tracing::trace!(data);
+    Ok((group, domain, id))
+}
+fn pull_point(query: &Query, data: &[u8]) -> LkResult<NetPktBox> {
+    let (group, domain, id) = pull_ids(query)?;
     let pull_space = rspace_buf(&[b"pull", &*group, &*domain, id]);
-    let pkt = lk_linkpoint(
-        data.as_bytes(),
-        EXCHANGE_DOMAIN,
-        PRIVATE,
-        &pull_space,
-        &[],
-        None,
-    )?;
+    let pkt = lk_linkpoint(data, EXCHANGE_DOMAIN, PRIVATE, &pull_space, &[], None)?;
     Ok(pkt.as_netbox())
 }
@@ -16,7 +16,8 @@ The current expected formats are:
 
 exchange GROUP process
 exchange GROUP connection PUBKEY
-exchnage GROUP pull PULL PULL_HASH
+exchange GROUP pull PULL_HASH - PULL_HASH is the hash of the local pull point. See [crate::conventions::pull::lk_pull_status]
+exchange GROUP access pull|push - the reply data after "OK\n" is a list of accepted query templates separated by an empty line. See [crate::query::lk_query_template] and [crate::query::lk_query_satisfies].
 
 A request is a packet in the form DOMAIN:[#:0]:/\fstatus/GROUP/type(/instance?) , with no data and no links.
 A reply is of the form DOMAIN:[#:0]/\status/GROUP/type/instance with some data and at least some links.
@@ -109,28 +110,40 @@ pub fn lk_status_overwatch(status: LkStatus, max_age: Stamp) -> LkResult<Query>
     Ok(q)
 }
 
+/// The age of the replies [lk_status_watch] accepts - max_age but at least d_timeout
+pub fn lk_status_max_age(d_timeout: Stamp, max_age: Option<Stamp>) -> Stamp {
+    max_age.map_or(d_timeout, |age| age.max(d_timeout))
+}
+
 #[cfg(feature = "runtime")]
-/// watch for any points matching
+/// watch for any points matching.
+/// Existing replies made since now-max_age (defaults to d_timeout) are passed to cb. A new request is only made if none was made since now-d_timeout.
+/// Returns true if an existing reply was found.
 pub fn lk_status_watch(
     lk: &Linkspace,
     status: LkStatus,
     d_timeout: Stamp,
+    max_age: Option<Stamp>,
     mut cb: impl crate::runtime::cb::PktHandler + 'static,
 ) -> LkResult<bool> {
     use crate::runtime::{lk_get_all, lk_watch2};
     use linkspace_common::prelude::{U16, U32};
     use tracing::debug_span;
 
-    let span = debug_span!("status_poll", ?status, ?d_timeout);
+    let span = debug_span!("status_poll", ?status, ?d_timeout, ?max_age);
     let _ = span.enter();
     let mut ok = false;
     let mut last_request = Stamp::ZERO;
-    let mut query: Query = lk_status_overwatch(status, d_timeout)?;
+    let request_after = now().saturating_sub(d_timeout);
+    let mut query: Query = lk_status_overwatch(status, lk_status_max_age(d_timeout, max_age))?;
     // We want to capture any old request, so we first lk_get_all both requests and replies.
     lk_get_all(lk, &query, &mut |pkt| {
         if pkt.get_links().is_empty() && pkt.data().is_empty() {
-            last_request = *pkt.get_create_stamp();
-            tracing::debug!(pkt=%PktFmt(&pkt),"recently requested");
+            let create = *pkt.get_create_stamp();
+            if create > request_after && create > last_request {
+                last_request = create;
+                tracing::debug!(pkt=%PktFmt(&pkt),"recently requested");
+            }
             false
         } else {
             ok = true;
@@ -156,6 +169,64 @@ pub fn lk_status_watch(
     Ok(ok)
 }
 
+#[cfg(feature = "runtime")]
+/**
+Iterate over the replies of an instance (defaults to 'default') from newest to oldest.
+
+The 'prev' links are followed until a reply links 'init', i.e. the first reply of a process.
+The iteration continues with the most recent reply made before it.
+cb returns true to stop. Returns the number of replies passed to cb.
+
+Together with [lk_status_watch] this distinguishes a process that never ran (no history), ran but stopped (history but no reply), or is running.
+**/
+pub fn lk_status_history(
+    lk: &Linkspace,
+    status: LkStatus,
+    cb: &mut dyn FnMut(&dyn NetPkt) -> bool,
+) -> LkResult<u32> {
+    use crate::runtime::{lk_get_hashes, lk_get_ref};
+    use linkspace_common::prelude::U16;
+    let space = lk_status_space(LkStatus {
+        instance: status.instance.or(Some(b"default")),
+        ..status
+    })?;
+    let mut count = 0;
+    let mut before = Stamp::MAX;
+    loop {
+        let mut q = lk_query(&Q);
+        q = lk_query_push(q, "group", "=", &*PRIVATE)?;
+        q = lk_query_push(q, "domain", "=", &*status.domain)?;
+        q = lk_query_push(q, "spacename", "=", space.space_bytes())?;
+        q = lk_query_push(q, "create", "<", &*before)?;
+        q = lk_query_push(q, "data_size", ">", &*U16::ZERO)?;
+        q = lk_query_push(q, "links_len", ">", &*U16::ZERO)?;
+        let Some(mut reply) = lk_get_ref(lk, &q, &mut |p| p.as_netbox())? else {
+            return Ok(count);
+        };
+        loop {
+            count += 1;
+            if cb(&reply) {
+                return Ok(count);
+            }
+            let prev = match reply.get_links().first() {
+                Some(l) if l.tag == ab(b"prev") => l.ptr,
+                _ => break,
+            };
+            let mut found = None;
+            lk_get_hashes(lk, &[prev], &mut |p| {
+                found = Some(p.as_netbox());
+                true
+            })?;
+            match found {
+                Some(p) if p.get_rooted_spacename() == &*space => reply = p,
+                // the chain is incomplete locally
+                _ => break,
+            }
+        }
+        before = *reply.get_create_stamp();
+    }
+}
+
 fn is_status_reply(status: LkStatus, rspace: &RootedSpace, pkt: &NetPktPtr) -> LkResult<()> {
     anyhow::ensure!(
         *pkt.get_domain() == status.domain
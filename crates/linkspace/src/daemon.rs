@@ -0,0 +1,199 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/**
+A client for `lk daemon`.
+
+The daemon owns the database. A client only needs access to its unix socket (by default $LK_DIR/daemon.sock).
+This suits short lived scripts and sandboxed processes that can not write to the linkspace directory.
+
+Unlike [crate::runtime] there are no callbacks between calls.
+Matches of a watch are queued and read with [lk_daemon_next].
+
+```no_run
+# use linkspace::{*,prelude::*,daemon::*};
+# fn main() -> LkResult{
+let mut d = lk_daemon_connect("/home/me/linkspace/daemon.sock".as_ref())?;
+let q = lk_query_parse(lk_query(&Q), &["domain:=:[a:example]", "group:=:[#:pub]", ":qid:example"], ())?;
+lk_daemon_watch(&mut d, &q)?;
+lk_daemon_save(&mut d, &[&lk_linkpoint(b"hello", ab(b"example"), PUBLIC, &rspace_buf(&[b"a"]), &[], None)?])?;
+while let Some(DaemonEvent::Match { qid, pkt }) = lk_daemon_next(&mut d, None)? {
+    println!("{} {}", String::from_utf8_lossy(&qid), pkt.hash());
+}
+# Ok(()) }
+```
+**/
+use std::{collections::VecDeque, io, os::unix::net::UnixStream, path::Path, time::Duration};
+
+use anyhow::Context;
+use linkspace_common::protocols::daemon::{
+    read_frame, read_frame_body, split_match, write_frame, write_query, Kind,
+};
+pub use linkspace_common::protocols::daemon::{DaemonStatus, SOCKET_NAME};
+
+use crate::{point::lk_read, *};
+
+/// A connection to `lk daemon`
+#[derive(Debug)]
+pub struct LkDaemon {
+    stream: UnixStream,
+    events: VecDeque<DaemonEvent>,
+}
+
+/// A watch event
+#[derive(Debug)]
+pub enum DaemonEvent {
+    /// a packet matched the watch with the qid
+    Match {
+        /// the qid of the watch
+        qid: Vec<u8>,
+        /// the matching packet
+        pkt: NetPktBox,
+    },
+    /// the watch with the qid was stopped (closed or finished)
+    Stopped {
+        /// the qid of the watch
+        qid: Vec<u8>,
+    },
+}
+
+/// Connect to the unix socket of `lk daemon`
+pub fn lk_daemon_connect(socket: &Path) -> io::Result<LkDaemon> {
+    Ok(LkDaemon {
+        stream: UnixStream::connect(socket)?,
+        events: VecDeque::new(),
+    })
+}
+
+impl LkDaemon {
+    fn event(&mut self, kind: Kind, payload: Vec<u8>) -> LkResult<Option<DaemonEvent>> {
+        Ok(match kind {
+            Kind::Match => {
+                let (qid, bytes) = split_match(&payload)?;
+                let (pkt, _) = lk_read(bytes, true)?;
+                Some(DaemonEvent::Match {
+                    qid: qid.to_vec(),
+                    pkt: pkt.as_netbox(),
+                })
+            }
+            Kind::Stopped => Some(DaemonEvent::Stopped { qid: payload }),
+            _ => None,
+        })
+    }
+    /// send a request and read until the reply. Pkt frames are passed to on_pkt, watch events are queued.
+    fn request(
+        &mut self,
+        send: impl FnOnce(&mut UnixStream) -> io::Result<()>,
+        on_pkt: &mut dyn FnMut(&dyn NetPkt),
+    ) -> LkResult<Vec<u8>> {
+        send(&mut self.stream)?;
+        loop {
+            let (kind, payload) =
+                read_frame(&mut self.stream)?.context("daemon closed the connection")?;
+            match kind {
+                Kind::Ok => return Ok(payload),
+                Kind::Err => anyhow::bail!("daemon: {}", String::from_utf8_lossy(&payload)),
+                Kind::Pkt => on_pkt(&*lk_read(&payload, true)?.0),
+                _ => match self.event(kind, payload)? {
+                    Some(e) => self.events.push_back(e),
+                    None => anyhow::bail!("unexpected {kind:?} frame"),
+                },
+            }
+        }
+    }
+}
+fn u32_reply(b: &[u8]) -> LkResult<u32> {
+    Ok(u32::from_be_bytes(
+        b.try_into().context("expected a u32 reply")?,
+    ))
+}
+
+/// Save packets through the daemon. Returns the number of new packets.
+pub fn lk_daemon_save(d: &mut LkDaemon, pkts: &[&dyn NetPkt]) -> LkResult<usize> {
+    let bytes: Vec<Box<[u8]>> = pkts.iter().map(|p| p.byte_segments().to_bytes()).collect();
+    let payload: Vec<&[u8]> = bytes.iter().map(|b| &**b).collect();
+    let reply = d.request(|s| write_frame(s, Kind::Save, &payload), &mut |_| {})?;
+    Ok(u32_reply(&reply)? as usize)
+}
+
+/// Like [crate::runtime::lk_get_all]. Break early if the callback returns true.
+/// The daemon sends all matches, the rest is read and dropped.
+/// Returns the number of callbacks, negative if the callback broke.
+pub fn lk_daemon_get(
+    d: &mut LkDaemon,
+    query: &Query,
+    cb: &mut dyn FnMut(&dyn NetPkt) -> bool,
+) -> LkResult<i32> {
+    let (mut c, mut breaks) = (0u32, false);
+    d.request(|s| write_query(s, Kind::Get, &query.0), &mut |pkt| {
+        if !breaks {
+            c += 1;
+            breaks = cb(pkt);
+        }
+    })?;
+    Ok(if breaks {
+        linkspace_common::saturating_neg_cast(c)
+    } else {
+        linkspace_common::saturating_cast(c)
+    })
+}
+
+/// Register a watch at the daemon. The query requires a ':qid:..' option.
+/// Matches in the database and future matches are queued as [DaemonEvent]s.
+/// Returns the value of [crate::runtime::lk_watch] in the daemon.
+pub fn lk_daemon_watch(d: &mut LkDaemon, query: &Query) -> LkResult<i32> {
+    let reply = d.request(|s| write_query(s, Kind::Watch, &query.0), &mut |_| {})?;
+    Ok(u32_reply(&reply)? as i32)
+}
+
+/// Close watches with the qid (or all qids starting with it if range is true). See [crate::runtime::lk_stop]
+pub fn lk_daemon_stop(d: &mut LkDaemon, qid: &[u8], range: bool) -> LkResult<()> {
+    d.request(
+        |s| write_frame(s, Kind::Stop, &[&[range as u8], qid]),
+        &mut |_| {},
+    )?;
+    Ok(())
+}
+
+/// Get the next watch event. Waits up to timeout (None waits forever). Returns None on timeout.
+pub fn lk_daemon_next(
+    d: &mut LkDaemon,
+    timeout: Option<Duration>,
+) -> LkResult<Option<DaemonEvent>> {
+    if let Some(e) = d.events.pop_front() {
+        return Ok(Some(e));
+    }
+    // only the first byte is read with a timeout. The daemon writes a frame at once.
+    // a zero timeout is refused by the socket
+    d.stream
+        .set_read_timeout(timeout.map(|t| t.max(Duration::from_micros(1))))?;
+    let mut kind = [0];
+    let r = io::Read::read(&mut d.stream, &mut kind);
+    d.stream.set_read_timeout(None)?;
+    match r {
+        Ok(0) => anyhow::bail!("daemon closed the connection"),
+        Ok(_) => {}
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let (kind, payload) = read_frame_body(kind[0], &mut d.stream)?;
+    match d.event(kind, payload)? {
+        Some(e) => Ok(Some(e)),
+        None => anyhow::bail!("unexpected {kind:?} frame"),
+    }
+}
+
+/// Get the [DaemonStatus]
+pub fn lk_daemon_status(d: &mut LkDaemon) -> LkResult<DaemonStatus> {
+    let reply = d.request(|s| write_frame(s, Kind::Status, &[]), &mut |_| {})?;
+    Ok(DaemonStatus::from_bytes(&reply)?)
+}
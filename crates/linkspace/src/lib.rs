@@ -218,7 +218,7 @@ pub mod abe {
     use super::*;
     pub use linkspace_common::pkt::repr::DEFAULT_PKT;
     use linkspace_common::{
-        abe::abtxt::as_abtxt,
+        abe::{abtxt::as_abtxt, eval::ABList},
         prelude::{abtxt::CtrChar, ast::tokenize_abe},
     };
 
@@ -386,6 +386,22 @@ pub mod abe {
             ignore_encoder_err,
         )
     }
+    /**
+    Encode bytes as a '#ab' frame - the binary counterpart of [lk_encode].
+    A frame holds the bytes as-is and is read back by [lk_encode] with the '#ab' option.
+    ```
+    # use linkspace::{*,prelude::*,abe::*};
+    # fn main() -> LkResult{
+    let frame = lk_encode_ab_frame(b"\0\xff");
+    assert_eq!(lk_encode(&frame,"#ab"), r#"[#ab:\0\f]"#);
+    assert_eq!(lk_eval(r#"[#ab:\0\xff]"#,())?, frame);
+    # Ok(())
+    # }
+    ```
+    **/
+    pub fn lk_encode_ab_frame(bytes: impl AsRef<[u8]>) -> Vec<u8> {
+        ABList::from(bytes.as_ref()).to_ab_frame()
+    }
     /// build a custom scope for ABE for use in [varscope]
     pub mod scope {
 
@@ -395,11 +411,18 @@ pub mod abe {
             Option<linkspace_common::runtime::Linkspace>,
         > = std::cell::RefCell::new(None);
 
+        /// Functions and macros added with [lk_register_func] and [lk_register_macro]
+        #[thread_local]
+        static LK_USER_SCOPE: std::cell::RefCell<Option<Rc<UserScope>>> =
+            std::cell::RefCell::new(None);
+
         use core::fmt;
+        use std::{ops::RangeInclusive, rc::Rc};
 
         use anyhow::Context;
         use linkspace_common::abe::eval::Scope;
-        use linkspace_common::prelude::scope::ArgV;
+        use linkspace_common::abe::print_abe;
+        use linkspace_common::prelude::scope::{ArgV, UserScope};
         use linkspace_common::prelude::NetPkt;
 
         use crate::LkResult;
@@ -492,9 +515,8 @@ pub mod abe {
                 .transpose()?
                 .map(EScope);
             Ok(LkScope(InlineScope::Std((
-                udata.pkt.map(|v| pkt_scope(v)),
-                core_scope(),
-                argv,
+                (udata.pkt.map(|v| pkt_scope(v)), core_scope(), argv),
+                user_scope(),
             ))))
         }
 
@@ -527,11 +549,60 @@ pub mod abe {
                 .ok_or_else(|| anyhow::anyhow!("no linkspace instance was set"))
             };
             Ok(LkScope(InlineScope::Std((
-                udata.pkt.map(pkt_scope),
-                lk_scope(get, enable_env),
-                argv,
+                (udata.pkt.map(pkt_scope), lk_scope(get, enable_env), argv),
+                user_scope(),
             ))))
         }
+        fn user_scope() -> Option<Rc<UserScope>> {
+            LK_USER_SCOPE.borrow().clone()
+        }
+        fn with_user_scope<A>(f: impl FnOnce(&mut UserScope) -> A) -> A {
+            let mut user = LK_USER_SCOPE.borrow_mut();
+            let user = user.get_or_insert_with(|| Rc::new(UserScope::new("user")));
+            f(Rc::make_mut(user))
+        }
+
+        /**
+        Add a function to the scope of this thread, e.g. "\[myapp:thread-id:..\]".
+        It is available in [crate::lk_eval], [crate::lk_query_parse], and other functions that use the default [scope].
+        The func receives the evaluated arguments.
+        Builtin functions take precedence. Registering an existing id replaces it.
+
+        ```
+        # use linkspace::abe::{*,scope::*};
+        # fn main() -> linkspace::LkResult{
+        lk_register_func("myapp", "join args with a '-'", 1..=8, |args| Ok(args.join(&b'-')));
+        assert_eq!(lk_eval("[myapp:thread-id:12]", ())?, b"thread-id-12");
+        assert!(lk_unregister("myapp"));
+        # Ok(())}
+        ```
+        **/
+        pub fn lk_register_func(
+            id: &str,
+            help: &str,
+            argc: RangeInclusive<usize>,
+            func: impl Fn(&[&[u8]]) -> LkResult<Vec<u8>> + 'static,
+        ) {
+            with_user_scope(|user| user.add_func(id, help, argc, move |inp, _, _| func(inp).into()))
+        }
+        /**
+        Add a macro to the scope of this thread. e.g. "\[/myapp:..\]".
+        The macro receives its unevaluated arguments as an abe string (starting with ':') and can evaluate it with [crate::lk_eval].
+        **/
+        pub fn lk_register_macro(
+            id: &str,
+            help: &str,
+            mac: impl Fn(&str) -> LkResult<Vec<u8>> + 'static,
+        ) {
+            with_user_scope(|user| {
+                user.add_macro(id, help, move |abe, _| mac(&print_abe(abe)).into())
+            })
+        }
+        /// Remove a function or macro added with [lk_register_func] or [lk_register_macro]
+        pub fn lk_unregister(id: &str) -> bool {
+            with_user_scope(|user| user.remove(id))
+        }
+
         impl<'o> LkScope<'o> {
             pub(crate) fn as_dyn(&self) -> &(dyn Scope + 'o) {
                 match &self.0 {
@@ -548,7 +619,10 @@ pub mod abe {
     }
 }
 
-pub use query::{lk_query, lk_query_parse, lk_query_print, lk_query_push, Query, Q};
+pub use query::{
+    lk_query, lk_query_parse, lk_query_print, lk_query_print_aliases, lk_query_push,
+    lk_query_push_strict, lk_query_satisfies, lk_query_template, Query, Q,
+};
 /// query functions to match points
 pub mod query {
     /**
@@ -614,7 +688,8 @@ pub mod query {
     use anyhow::Context;
     pub use linkspace_common::core::predicate::predicate_type::PredicateType;
     pub use linkspace_common::core::query::KnownOptions;
-    use linkspace_common::prelude::ExtPredicate;
+    use linkspace_common::core::query_template::{query_satisfies, QueryTemplate};
+    use linkspace_common::prelude::{ExtPredicate, ExtendedTestOp};
 
     use crate::abe::scope::UserData;
 
@@ -640,12 +715,30 @@ pub mod query {
         }
         let epre = ExtPredicate {
             kind: field.parse().with_context(|| format!("Field={field}"))?,
-            op: test.parse().with_context(|| format!("Operator={test}"))?,
+            op: ExtendedTestOp::parse_op(test).with_context(|| format!("Operator={test}"))?,
             val: val.to_vec().into(),
         };
         query.0.predicates.add_ext_predicate(epre)?;
         Ok(query)
     }
+    /// [lk_query_push] but returns an error if the field already has any predicate. The query is unchanged on error.
+    /// Use it to template a query from untrusted input.
+    /// Statements can do the same with a strict op - e.g. ```group:=!:[#:pub]```.
+    /// Only single char ops have a strict form, but any op given here (e.g. ">=") is added strictly.
+    pub fn lk_query_push_strict(
+        mut query: Query,
+        field: &str,
+        test: &str,
+        val: &[u8],
+    ) -> LkResult<Query> {
+        let epre = ExtPredicate {
+            kind: field.parse().with_context(|| format!("Field={field}"))?,
+            op: ExtendedTestOp::parse_op(test).with_context(|| format!("Operator={test}"))?,
+            val: val.to_vec().into(),
+        };
+        query.0.predicates.add_ext_predicate_strict(epre)?;
+        Ok(query)
+    }
     /// Add multiple ABE encoded statements to a [Query]
     pub fn lk_query_parse<'o>(
         query: Query,
@@ -654,6 +747,21 @@ pub mod query {
     ) -> LkResult<Query> {
         varscope::lk_query_parse(crate::abe::scope::scope(udata.into())?, query, expr)
     }
+    /// Fill the `{name}` placeholders of a query template with the abtxt encoding of vars and parse the result.
+    /// See [linkspace_common::core::query_template] for the format.
+    pub fn lk_query_template<'o>(
+        template: &str,
+        vars: &[(&str, &[u8])],
+        udata: impl Into<UserData<'o>>,
+    ) -> LkResult<Query> {
+        let stmnts = QueryTemplate(template.to_string()).fill(vars)?;
+        lk_query_parse(lk_query(&Q), &[&stmnts], udata)
+    }
+    /// true if the query does not select more than the template. Options are ignored.
+    /// An exchange uses this to check a pull request against the templates it is willing to serve.
+    pub fn lk_query_satisfies(template: &Query, query: &Query) -> bool {
+        query_satisfies(&template.0, &query.0)
+    }
     /// Clear a [Query] for reuse
     pub fn lk_query_clear(query: &mut Query) {
         //if fields.is_some() || keep_options { todo!()}
@@ -663,6 +771,10 @@ pub mod query {
     pub fn lk_query_print(query: &Query, as_expr: bool) -> String {
         query.0.to_str(as_expr)
     }
+    /// [lk_query_print] with counters as decimal aliases (e.g. `data_size<1000`). Older versions can not parse these.
+    pub fn lk_query_print_aliases(query: &Query) -> String {
+        query.0.to_alias_str()
+    }
 
     /// Compile a [Query] into a function which tests packets to deteremine if they match - WARN - slow and subject to change.
     #[allow(clippy::type_complexity)]
@@ -673,12 +785,16 @@ pub mod query {
     }
 }
 
+#[cfg(feature = "runtime")]
+pub use key::lk_encrypt_for_group;
 #[cfg(feature = "runtime")]
 pub use key::lk_key;
+pub use key::{lk_decrypt, lk_encrypt_for, lk_encrypted_for};
 /// cryptographic key functions for use in [lk_keypoint]
 pub mod key {
     use super::prelude::*;
     use super::LkResult;
+    use linkspace_common::core::crypto::seal;
     use linkspace_common::identity;
 
     /// generate a new key
@@ -709,6 +825,42 @@ pub mod key {
         Ok(linkspace_common::identity::pubkey(key)?.into())
     }
 
+    /** Encrypt data such that only the keys of `pubkeys` can [lk_decrypt] it.
+    Use it as the data of a linkpoint to hide it from exchanges forwarding the group.
+    The pubkeys of group members are usually looked up through LNS e.g. `lk_eval("[@:alice:local]")`.
+    The list of recipient pubkeys is readable by anyone.
+    **/
+    pub fn lk_encrypt_for(pubkeys: &[PubKey], data: &[u8]) -> LkResult<Vec<u8>> {
+        let recipients: Vec<[u8; 32]> = pubkeys.iter().map(|k| k.0).collect();
+        Ok(seal::seal(&recipients, data)?)
+    }
+    /** [lk_encrypt_for] every member of the LNS name `group` - e.g. "team:local" or "[name:team:local]".
+    The members are the pubkeys of the live claims directly below it (alice:team:local, bob:team:local, ..).
+    A member key that was rotated (`lk key rotate`) is sealed to its latest successor.
+    **/
+    #[cfg(feature = "runtime")]
+    pub fn lk_encrypt_for_group(
+        linkspace: &Linkspace,
+        group: &str,
+        data: &[u8],
+    ) -> LkResult<Vec<u8>> {
+        use linkspace_common::{abe::eval::eval, prelude::parse_abe_strict_b, protocols::lns};
+        let scope = super::abe::scope::scope(().into())?;
+        let name: lns::name::Name =
+            eval(&scope.as_dyn(), &parse_abe_strict_b(group.as_bytes())?)?.try_into()?;
+        let members = lns::lookup_members(&linkspace.0, &name)?;
+        anyhow::ensure!(!members.is_empty(), "no members found below {group}");
+        lk_encrypt_for(&members, data)
+    }
+    /// decrypt the result of [lk_encrypt_for]
+    pub fn lk_decrypt(key: &SigningKey, data: &[u8]) -> LkResult<Vec<u8>> {
+        Ok(seal::open(key, data)?)
+    }
+    /// the public keys that can decrypt the result of [lk_encrypt_for]
+    pub fn lk_encrypted_for(data: &[u8]) -> LkResult<Vec<PubKey>> {
+        Ok(seal::recipients(data)?.into_iter().map(B64).collect())
+    }
+
     /** linkspace stored identity
     open (or generate) the key `name` which is also accessible as \[@:name:local\].
     empty name defaults to ( i.e. \[@:me:local\] )
@@ -792,10 +944,15 @@ pub mod runtime {
     }
     */
 
-    /// save a packet. Returns true if new and false if its old.
+    /// save a packet. Returns true if new and false if its old or rejected by a [quota].
     pub fn lk_save(lk: &Linkspace, pkt: &dyn NetPkt) -> std::io::Result<bool> {
         lk.0.env().save_dyn_one(pkt).map(|o| o.is_written())
     }
+    pub use linkspace_common::core::env::misc::SaveState;
+    /// save a packet. Returns [SaveState::Written] if new, [SaveState::Exists] if its old, or [SaveState::Rejected] by a [quota].
+    pub fn lk_save_state(lk: &Linkspace, pkt: &dyn NetPkt) -> std::io::Result<SaveState> {
+        lk.0.env().save_dyn_one(pkt)
+    }
     /// save multiple packets at once - returns the number of new packets written
     pub fn lk_save_all(lk: &Linkspace, pkts: &[&dyn NetPkt]) -> std::io::Result<usize> {
         let (start, excl) = lk_save_all_ext(lk, pkts)?;
@@ -967,6 +1124,30 @@ pub mod runtime {
         }
     }
 
+    /** limit the packets saved from elsewhere (hop > 0) per pubkey, group, or domain.
+
+    A packet over a quota is not saved, see [lk_save_state].
+    The quotas and their usage are kept in the instance. See [linkspace_common::core::env::quota]
+    **/
+    pub mod quota {
+        use super::Linkspace;
+        pub use linkspace_common::core::env::quota::{Quota, QuotaKind};
+
+        /// set the quota for an id of kind (empty id for the default of the kind). An unlimited quota removes it.
+        pub fn lk_quota_set(
+            lk: &Linkspace,
+            kind: QuotaKind,
+            id: &[u8],
+            quota: Quota,
+        ) -> std::io::Result<()> {
+            lk.0.env().quota_set(kind, id, quota)
+        }
+        /// all quotas as (kind, id, quota)
+        pub fn lk_quota_list(lk: &Linkspace) -> std::io::Result<Vec<(QuotaKind, Vec<u8>, Quota)>> {
+            lk.0.env().quota_list()
+        }
+    }
+
     #[cfg(feature = "runtime")]
     /** (rust only) [lk_watch] takes the callback [PktHandler] which are quick to impl with [cb] and [try_cb].
     Other languages should use their own function syntax as argument to lk_watch.
@@ -1059,6 +1240,9 @@ pub mod runtime {
 
 /// A set of functions that adhere to conventions
 pub mod conventions;
+#[cfg(unix)]
+/// A client for `lk daemon`
+pub mod daemon;
 #[cfg(feature = "runtime")]
 pub use crate::conventions::pull::lk_pull;
 
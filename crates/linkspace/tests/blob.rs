@@ -0,0 +1,161 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use common::open;
+use linkspace::{
+    conventions::blob::{
+        lk_blob_points, lk_blob_read, lk_blob_write, BLOB_FANOUT, BLOB_MAX_CHUNK, BLOB_SPACE,
+    },
+    prelude::*,
+};
+
+fn random(len: usize, seed: u64) -> Vec<u8> {
+    // xorshift64
+    let mut x = seed | 1;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+struct Blob {
+    root: LkHash,
+    pkts: HashMap<LkHash, NetPktBox>,
+}
+impl Blob {
+    fn new(data: &[u8]) -> LkResult<Blob> {
+        let mut pkts = HashMap::new();
+        let root = lk_blob_points(ab(b"blob"), PUBLIC, data, &mut |pkt| {
+            // links point to earlier points
+            for l in pkt.get_links() {
+                assert!(pkts.contains_key(&l.ptr));
+            }
+            pkts.insert(pkt.hash(), pkt);
+            Ok(())
+        })?;
+        Ok(Blob { root, pkts })
+    }
+    fn chunks(&self) -> HashSet<LkHash> {
+        self.pkts
+            .values()
+            .filter(|p| p.is_datapoint())
+            .map(|p| p.hash())
+            .collect()
+    }
+    fn read(&self, hash: LkHash, out: &mut Vec<u8>) -> u64 {
+        let pkt = &self.pkts[&hash];
+        if pkt.is_datapoint() {
+            out.extend_from_slice(pkt.data());
+            return pkt.data().len() as u64;
+        }
+        assert_eq!(pkt.get_rooted_spacename(), &*BLOB_SPACE);
+        assert!(pkt.get_links().len() <= BLOB_FANOUT);
+        let size: u64 = pkt.get_links().iter().map(|l| self.read(l.ptr, out)).sum();
+        assert_eq!(pkt.data(), size.to_be_bytes());
+        size
+    }
+    fn data(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.read(self.root, &mut out);
+        out
+    }
+}
+
+#[test]
+fn round_trip() -> LkResult<()> {
+    let empty = Blob::new(&[])?;
+    assert_eq!(empty.data(), b"");
+    assert!(empty.chunks().is_empty());
+
+    let small = random(100, 1);
+    let blob = Blob::new(&small)?;
+    assert_eq!(blob.data(), small);
+    assert_eq!(blob.chunks().len(), 1);
+
+    let big = random(4 * BLOB_MAX_CHUNK, 2);
+    let blob = Blob::new(&big)?;
+    assert_eq!(blob.data(), big);
+    assert!(blob.chunks().len() > 4);
+    Ok(())
+}
+
+#[test]
+fn more_than_fanout_chunks() -> LkResult<()> {
+    // at most BLOB_MAX_CHUNK per chunk
+    let data = random((BLOB_FANOUT + 10) * BLOB_MAX_CHUNK, 3);
+    let blob = Blob::new(&data)?;
+    assert!(blob.chunks().len() > BLOB_FANOUT);
+    assert_eq!(blob.data(), data);
+    // the root links to indexes
+    let root = &blob.pkts[&blob.root];
+    assert!(root.get_links().len() > 1);
+    assert!(root
+        .get_links()
+        .iter()
+        .all(|l| !blob.pkts[&l.ptr].is_datapoint()));
+    Ok(())
+}
+
+#[test]
+fn deterministic() -> LkResult<()> {
+    let data = random(3 * BLOB_MAX_CHUNK, 4);
+    let (a, b) = (Blob::new(&data)?, Blob::new(&data)?);
+    assert_eq!(a.root, b.root);
+    assert_eq!(
+        a.pkts.keys().collect::<HashSet<_>>(),
+        b.pkts.keys().collect()
+    );
+
+    // repeated data shares its chunks
+    let twice = [data.as_slice(), data.as_slice()].concat();
+    let twice = Blob::new(&twice)?;
+    assert!(twice.chunks().len() < 2 * a.chunks().len());
+    Ok(())
+}
+
+#[test]
+fn edit_changes_nearby_chunks() -> LkResult<()> {
+    let data = random(16 * BLOB_MAX_CHUNK, 5);
+    let old = Blob::new(&data)?;
+    let mut edit = data.clone();
+    edit[data.len() / 2] ^= 0xff;
+    let new = Blob::new(&edit)?;
+    assert_ne!(old.root, new.root);
+    assert_eq!(new.data(), edit);
+
+    let (old, new) = (old.chunks(), new.chunks());
+    // the chunk holding the byte changes, and at most the next if its cut moved
+    assert!(new.difference(&old).count() <= 2);
+    assert!(old.difference(&new).count() <= 2);
+    assert!(old.intersection(&new).count() >= old.len() - 2);
+    Ok(())
+}
+
+#[test]
+fn write_read() -> LkResult<()> {
+    let lk = open("write_read");
+    let data = random(3 * BLOB_MAX_CHUNK, 6);
+    let hash = lk_blob_write(&lk, ab(b"blob"), PUBLIC, data.as_slice())?;
+    assert_eq!(hash, Blob::new(&data)?.root);
+    let mut out = vec![];
+    lk_blob_read(&lk, hash).read_to_end(&mut out)?;
+    assert_eq!(out, data);
+
+    let missing = lk_datapoint(b"missing")?.hash();
+    let err = lk_blob_read(&lk, missing)
+        .read_to_end(&mut out)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    Ok(())
+}
@@ -0,0 +1,18 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//! Fixtures shared by the integration tests. Declare with `mod common;`
+use linkspace::prelude::*;
+
+/// Open an empty instance in $TMP/lktests/{test file}/{name}
+pub fn open(name: &str) -> Linkspace {
+    std::env::set_var("LK_FORCE_EMPTY", "true");
+    let dir = std::env::temp_dir()
+        .join("lktests")
+        .join(env!("CARGO_CRATE_NAME"))
+        .join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    lk_open(Some(dir.as_path()), true).unwrap()
+}
@@ -0,0 +1,321 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+/*!
+Deterministic in-process emulation of several instances exchanging a group.
+
+Every [Node] is a linkspace directory with a key.
+[Sim::link] connects two nodes with a simulated link that has a latency (in ticks), a loss rate, and can be partitioned.
+Over a link both ends run the same session as lk-exchange ([lk_exchange_session]):
+- push every packet in the group created locally (hop=0)
+- forward local pull requests as keypoints in the group
+- answer the pull requests of the peer by watching the query, if accepted by the access list of [Node::acl_key]
+- reply to the status 'exchange GROUP process', 'exchange GROUP connection PUBKEY', and 'exchange GROUP pull PULL_HASH'
+
+Nothing runs on its own. [Sim::step] advances one tick: deliver due frames, then process every node and session in a fixed order.
+Loss is drawn from a seeded generator, so a simulation with the same seed and the same actions always takes the same steps.
+
+A lost frame ends the session (as a missing frame breaks the cipher stream of lk-exchange), the frames sent before it still arrive.
+The link reconnects after `reconnect` ticks.
+A session saves its txlog once every frame it sent has arrived, so the new session pushes what was not yet delivered.
+Pulls are served again, the receiver ignores what it already has.
+Unlike lk-exchange frames are not encrypted.
+
+The `emulate/` directory in the repository root has tmux scripts to do the same with real processes.
+*/
+#![allow(dead_code)]
+
+use std::{cell::RefCell, collections::VecDeque, path::PathBuf, rc::Rc};
+
+use linkspace::{
+    consts::{EXCHANGE_DOMAIN, TEST_GROUP},
+    conventions::{
+        exchange::{lk_exchange_session, LkExchange, LkExchangeSession},
+        status::{lk_status_set, LkStatus},
+    },
+    misc::ReroutePkt,
+    point::lk_read,
+    prelude::*,
+    runtime::{lk_get_hashes, lk_save_state, SaveState},
+};
+
+/// index into [Sim::nodes]
+pub type NodeId = usize;
+
+pub struct Node {
+    pub name: String,
+    pub dir: PathBuf,
+    pub key: SigningKey,
+    /// the runtime used by the test. Its status 'exchange GROUP process sim' is set.
+    pub lk: Linkspace,
+    /// only answer pull requests accepted by the pull access list of this key. Used by sessions started after setting it.
+    pub acl_key: Option<PubKey>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LinkOpts {
+    /// ticks between sending and receiving a frame
+    pub latency: u64,
+    /// chance a frame is lost
+    pub loss: f64,
+    /// ticks before a broken session reconnects
+    pub reconnect: u64,
+}
+impl Default for LinkOpts {
+    fn default() -> Self {
+        LinkOpts {
+            latency: 1,
+            loss: 0.0,
+            reconnect: 5,
+        }
+    }
+}
+
+/// One end of a session. Dropping it drops the runtime and thereby its watches.
+struct Session {
+    lk: Linkspace,
+    exchange: LkExchangeSession,
+    /// frames for the peer
+    outbox: Rc<RefCell<VecDeque<Box<[u8]>>>>,
+}
+
+struct Link {
+    ends: [NodeId; 2],
+    opts: LinkOpts,
+    partitioned: bool,
+    reconnect_at: u64,
+    sessions: Option<[Session; 2]>,
+    /// (deliver_at, frame) towards ends[0] and ends[1]
+    wires: [VecDeque<(u64, Box<[u8]>)>; 2],
+}
+impl Link {
+    fn disconnect(&mut self, tick: u64) {
+        self.sessions = None;
+        self.reconnect_at = tick + self.opts.reconnect;
+    }
+}
+
+/// xorshift64*
+struct Rng(u64);
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub struct Sim {
+    pub nodes: Vec<Node>,
+    pub group: GroupID,
+    pub tick: u64,
+    root: PathBuf,
+    links: Vec<Link>,
+    rng: Rng,
+}
+
+impl Sim {
+    /// A simulation exchanging [TEST_GROUP]. name must be unique per test, it is used as the directory in $TMPDIR/lk-emulate
+    pub fn new(name: &str, seed: u64) -> Sim {
+        std::env::set_var("LK_FORCE_EMPTY", "true");
+        let root = std::env::temp_dir().join("lk-emulate").join(name);
+        let _ = std::fs::remove_dir_all(&root);
+        Sim {
+            nodes: vec![],
+            group: *TEST_GROUP,
+            tick: 0,
+            root,
+            links: vec![],
+            // xorshift never leaves 0
+            rng: Rng(seed.max(1)),
+        }
+    }
+
+    pub fn node(&mut self, name: &str) -> LkResult<NodeId> {
+        let dir = self.root.join(name);
+        let lk = lk_open(Some(dir.as_path()), true)?;
+        let status = LkStatus {
+            domain: EXCHANGE_DOMAIN,
+            group: self.group,
+            objtype: b"process",
+            instance: Some(b"sim"),
+            qid: b"status-process",
+        };
+        let info = format!("OK\nsim {name}\n");
+        lk_status_set(&lk, status, move |_, domain, group, space, link| {
+            lk_linkpoint(info.as_bytes(), domain, group, space, &[link], None)
+        })?;
+        self.nodes.push(Node {
+            name: name.to_string(),
+            dir,
+            key: linkspace::key::lk_keygen(),
+            lk,
+            acl_key: None,
+        });
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// Connect two nodes. The sessions start on the next step.
+    pub fn link(&mut self, a: NodeId, b: NodeId, opts: LinkOpts) {
+        self.links.push(Link {
+            ends: [a, b],
+            opts,
+            partitioned: false,
+            reconnect_at: self.tick,
+            sessions: None,
+            wires: Default::default(),
+        });
+    }
+
+    /// Cut (or heal) the links between a and b. Cutting drops the sessions and frames in flight.
+    pub fn partition(&mut self, a: NodeId, b: NodeId, partitioned: bool) {
+        let tick = self.tick;
+        for link in self.links.iter_mut() {
+            if link.ends == [a, b] || link.ends == [b, a] {
+                link.partitioned = partitioned;
+                link.disconnect(tick);
+                link.wires = Default::default();
+                link.reconnect_at = tick;
+            }
+        }
+    }
+
+    /// true if both ends of the link between a and b have a session
+    pub fn connected(&self, a: NodeId, b: NodeId) -> bool {
+        self.links
+            .iter()
+            .any(|l| (l.ends == [a, b] || l.ends == [b, a]) && l.sessions.is_some())
+    }
+
+    pub fn has(&self, node: NodeId, hash: LkHash) -> LkResult<bool> {
+        Ok(lk_get_hashes(&self.nodes[node].lk, &[hash], &mut |_| true)? != 0)
+    }
+
+    /// Advance a single tick
+    pub fn step(&mut self) -> LkResult<()> {
+        let tick = self.tick;
+        for i in 0..self.links.len() {
+            let link = &self.links[i];
+            if !link.partitioned && link.sessions.is_none() && tick >= link.reconnect_at {
+                let [a, b] = link.ends;
+                let sessions = [self.session(a, b)?, self.session(b, a)?];
+                self.links[i].sessions = Some(sessions);
+            }
+        }
+        for link in self.links.iter_mut() {
+            for (side, wire) in link.wires.iter_mut().enumerate() {
+                let node = &self.nodes[link.ends[side]];
+                while wire.front().map_or(false, |(at, _)| *at <= tick) {
+                    let (_, frame) = wire.pop_front().unwrap();
+                    receive(&node.lk, self.group, &frame)?;
+                }
+            }
+        }
+        for link in self.links.iter_mut() {
+            let Some(sessions) = &mut link.sessions else {
+                continue;
+            };
+            for (side, session) in sessions.iter_mut().enumerate() {
+                // everything it pushed has arrived
+                if link.wires[1 - side].is_empty() {
+                    session.exchange.save_txlog(&session.lk)?;
+                }
+            }
+        }
+        for node in &self.nodes {
+            lk_process(&node.lk);
+        }
+        for link in self.links.iter_mut() {
+            let Some(sessions) = &link.sessions else {
+                continue;
+            };
+            let mut lost = false;
+            for (side, session) in sessions.iter().enumerate() {
+                lk_process(&session.lk);
+                let frames = std::mem::take(&mut *session.outbox.borrow_mut());
+                for frame in frames {
+                    if link.opts.loss > 0.0 && self.rng.next_f64() < link.opts.loss {
+                        lost = true;
+                        break;
+                    }
+                    link.wires[1 - side].push_back((tick + link.opts.latency, frame));
+                }
+                if lost {
+                    break;
+                }
+            }
+            if lost {
+                link.disconnect(tick);
+            }
+        }
+        self.tick += 1;
+        Ok(())
+    }
+
+    /// Step until done returns true. Returns the number of steps taken or an error after max steps.
+    pub fn run_until(
+        &mut self,
+        max: u64,
+        mut done: impl FnMut(&Sim) -> LkResult<bool>,
+    ) -> LkResult<u64> {
+        for i in 0..max {
+            if done(self)? {
+                return Ok(i);
+            }
+            self.step()?;
+        }
+        if done(self)? {
+            return Ok(max);
+        }
+        anyhow::bail!("not done after {max} steps (tick {})", self.tick)
+    }
+
+    pub fn run(&mut self, steps: u64) -> LkResult<()> {
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Open the session of `node` with `peer` and setup the exchange watches
+    fn session(&self, node: NodeId, peer: NodeId) -> LkResult<Session> {
+        let Node {
+            dir, key, acl_key, ..
+        } = &self.nodes[node];
+        let lk = lk_open(Some(dir.as_path()), false)?;
+        let outbox = Rc::new(RefCell::new(VecDeque::new()));
+        let exchange = LkExchange {
+            key,
+            group: self.group,
+            their_key: self.nodes[peer].key.pubkey(),
+            acl_key: *acl_key,
+        };
+        let out = outbox.clone();
+        let exchange = lk_exchange_session(&lk, exchange, move |pkt| {
+            out.borrow_mut().push_back(pkt.byte_segments().to_bytes());
+            Ok(())
+        })?;
+        Ok(Session {
+            lk,
+            exchange,
+            outbox,
+        })
+    }
+}
+
+fn receive(lk: &Linkspace, group: GroupID, frame: &[u8]) -> LkResult<()> {
+    let (pkt, _) = lk_read(frame, false)?;
+    if !pkt.is_datapoint() && *pkt.get_group() != group {
+        tracing::warn!(hash=%pkt.hash(), "ignoring packet outside of group");
+        return Ok(());
+    }
+    let mut pkt = ReroutePkt::new(&*pkt);
+    pkt.net_header = pkt.net_header.hop();
+    if lk_save_state(lk, &pkt)? == SaveState::Rejected {
+        tracing::warn!(hash=%pkt.hash(), "rejected by quota");
+    }
+    Ok(())
+}
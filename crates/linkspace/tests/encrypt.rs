@@ -0,0 +1,64 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use common::open;
+use linkspace::{key::lk_keygen, prelude::*};
+
+/// the local claim NAME:team:local naming pubkey
+fn member_claim(name: &[u8], pubkey: PubKey) -> LkResult<NetPktBox> {
+    let mut tag = ab(b"pubkey@");
+    tag[0..8].copy_from_slice(&Stamp::MAX.0);
+    let space = rspace_buf(&[b"claims", b"local", b"team", name]);
+    lk_linkpoint(
+        &[],
+        ab(b"lns"),
+        PRIVATE,
+        &space,
+        &[Link { tag, ptr: pubkey }],
+        Some(Stamp::ZERO),
+    )
+}
+
+#[test]
+fn encrypt_for_group_members() -> LkResult<()> {
+    let lk = open("encrypt_for_group_members");
+    let [alice, bob, bob_next, eve] = std::array::from_fn(|_| lk_keygen());
+    lk_save(&lk, &member_claim(b"alice", alice.pubkey())?)?;
+    lk_save(&lk, &member_claim(b"bob", bob.pubkey())?)?;
+    // bob's key was rotated
+    let rotate = Link {
+        tag: ab(b"rotate-to"),
+        ptr: bob_next.pubkey(),
+    };
+    let rotation = lk_keypoint(
+        &bob,
+        &[],
+        ab(b"lns"),
+        PUBLIC,
+        &rspace_buf(&[b"rotate"]),
+        &[rotate],
+        None,
+    )?;
+    lk_save(&lk, &rotation)?;
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    lk_process(&lk);
+
+    let sealed = lk_encrypt_for_group(&lk, "team:local", b"hello")?;
+    let mut recipients = lk_encrypted_for(&sealed)?;
+    recipients.sort();
+    let mut members = vec![alice.pubkey(), bob_next.pubkey()];
+    members.sort();
+    assert_eq!(recipients, members);
+
+    assert_eq!(lk_decrypt(&alice, &sealed)?, b"hello");
+    assert_eq!(lk_decrypt(&bob_next, &sealed)?, b"hello");
+    assert!(lk_decrypt(&bob, &sealed).is_err(), "rotated key");
+    assert!(lk_decrypt(&eve, &sealed).is_err(), "not a member");
+
+    assert!(lk_encrypt_for_group(&lk, "other:local", b"hello").is_err());
+    Ok(())
+}
@@ -0,0 +1,99 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use common::open;
+use linkspace::{
+    abe::{lk_encode_ab_frame, lk_try_encode},
+    prelude::*,
+};
+
+fn point(data: &[u8], links: &[Link]) -> LkResult<NetPktBox> {
+    lk_linkpoint(data, ab(b"walk"), PUBLIC, &rspace_buf(&[b"x"]), links, None)
+}
+
+#[test]
+fn walk() -> LkResult<()> {
+    let lk = open("walk");
+    let end = lk_datapoint(b"end")?;
+    let mid = point(
+        b"mid",
+        &[Link {
+            tag: ab(b"next"),
+            ptr: end.hash(),
+        }],
+    )?;
+    let start = point(
+        b"start",
+        &[
+            Link {
+                tag: ab(b"other"),
+                ptr: end.hash(),
+            },
+            Link {
+                tag: ab(b"prev"),
+                ptr: mid.hash(),
+            },
+        ],
+    )?;
+    for pkt in [&end, &mid, &start] {
+        lk_save(&lk, pkt)?;
+    }
+    lk_process(&lk);
+    let start: &dyn NetPkt = &start;
+
+    // multiple hops
+    assert_eq!(lk_eval("[walk:prev:next]", start)?, end.hash().0);
+    assert_eq!(lk_eval("[/walk:prev:next:[data]]", start)?, b"end");
+    assert_eq!(lk_eval("[/walk:prev:[data]]", start)?, b"mid");
+
+    // missing tag
+    assert!(lk_eval("[walk:nope]", start).is_err());
+    assert!(lk_eval("[walk:prev:nope]", start).is_err());
+    assert!(lk_eval("[/walk:prev:nope:[data]]", start).is_err());
+
+    // missing packet
+    let unsaved = lk_datapoint(b"unsaved")?;
+    let dangling = point(
+        b"dangling",
+        &[Link {
+            tag: ab(b"prev"),
+            ptr: unsaved.hash(),
+        }],
+    )?;
+    let dangling: &dyn NetPkt = &dangling;
+    // a single hop only reads the link of the packet in scope
+    assert_eq!(lk_eval("[walk:prev]", dangling)?, unsaved.hash().0);
+    assert!(lk_eval("[walk:prev:next]", dangling).is_err());
+    assert!(lk_eval("[/walk:prev:[data]]", dangling).is_err());
+
+    // no packet in scope
+    assert!(lk_eval("[walk:prev]", ()).is_err());
+    Ok(())
+}
+
+#[test]
+fn ab_frames() -> LkResult<()> {
+    let frame = lk_eval(r#"[#ab:hello:\0\xff[u8:58]]"#, ())?;
+    assert_eq!(frame, [&b"\0#ab"[..], b"b\x05hello:b\x03\0\xff:"].concat());
+    // lk_encode prints a frame back into the expression that produced it
+    let txt = lk_encode(&frame, "#ab");
+    assert_eq!(txt, r#"[#ab:hello:\0\f\:]"#);
+    assert_eq!(lk_eval(&txt, ())?, frame);
+
+    let frame = lk_encode_ab_frame(b"a:b\n");
+    assert_eq!(lk_eval(&lk_encode(&frame, "#ab"), ())?, frame);
+    assert_eq!(lk_eval(r#"[#ab:a\:b\n]"#, ())?, frame);
+
+    // not a frame - falls through to the next option
+    assert_eq!(lk_try_encode(b"hello", "#ab/", false)?, "hello");
+    assert!(lk_try_encode(b"hello", "#ab", false).is_err());
+    assert_eq!(lk_encode(b"hello", "#ab"), "hello");
+    // a '/' separated frame has no [#ab:..] expression
+    let slashed = [&b"\0#ab"[..], b"b\x01a/b\x01b"].concat();
+    assert!(lk_try_encode(&slashed, "#ab", false).is_err());
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod emulate;
+
+use std::{cell::RefCell, rc::Rc};
+
+use emulate::{LinkOpts, Sim};
+use linkspace::{
+    consts::EXCHANGE_DOMAIN,
+    conventions::{
+        exchange_acl::{lk_exchange_acl_set, LkAccess},
+        pull::lk_pull_status,
+        status::{lk_status_watch, LkStatus},
+    },
+    prelude::*,
+    runtime::cb::cb,
+};
+
+fn point(sim: &Sim, data: &[u8]) -> LkResult<NetPktBox> {
+    lk_linkpoint(
+        data,
+        ab(b"emulate"),
+        sim.group,
+        &rspace_buf(&[b"x"]),
+        &[],
+        None,
+    )
+}
+
+fn pull_query(sim: &Sim, qid: &str) -> LkResult<Query> {
+    let q = lk_query_push(lk_query(&Q), "domain", "=", &*ab(b"emulate"))?;
+    let q = lk_query_push(q, "group", "=", &*sim.group)?;
+    lk_query_push(q, "", "qid", qid.as_bytes())
+}
+
+#[test]
+fn a_pulls_from_b_through_c() -> LkResult<()> {
+    let mut sim = Sim::new("a_pulls_from_b_through_c", 1);
+    let (a, b, c) = (sim.node("a")?, sim.node("b")?, sim.node("c")?);
+    let opts = LinkOpts {
+        latency: 3,
+        ..Default::default()
+    };
+    sim.link(a, c, opts);
+    sim.link(c, b, opts);
+
+    let pkt = point(&sim, b"from b")?;
+    lk_save(&sim.nodes[b].lk, &pkt)?;
+    // b pushes to c, but c only pushes what it created
+    sim.run_until(20, |sim| sim.has(c, pkt.hash()))?;
+    sim.run(20)?;
+    assert!(!sim.has(a, pkt.hash())?);
+
+    lk_pull(&sim.nodes[a].lk, &pull_query(&sim, "from-b")?)?;
+    sim.run_until(20, |sim| sim.has(a, pkt.hash()))?;
+
+    // the pull keeps delivering new matches
+    let next = point(&sim, b"from b again")?;
+    lk_save(&sim.nodes[b].lk, &next)?;
+    sim.run_until(20, |sim| sim.has(a, next.hash()))?;
+    Ok(())
+}
+
+#[test]
+fn acl_refuses_pulls() -> LkResult<()> {
+    let mut sim = Sim::new("acl_refuses_pulls", 1);
+    let (a, b, c) = (sim.node("a")?, sim.node("b")?, sim.node("c")?);
+    let op = linkspace::key::lk_keygen();
+    lk_exchange_acl_set(
+        &sim.nodes[c].lk,
+        &op,
+        ab(b"emulate"),
+        sim.group,
+        LkAccess::Pull,
+        &[(ab(b"x"), "domain:=:{domain}\ngroup:=:{group}\nprefix:=:/x")],
+    )?;
+    sim.nodes[c].acl_key = Some(op.pubkey());
+    sim.link(a, c, LinkOpts::default());
+    sim.link(c, b, LinkOpts::default());
+
+    let pkt = point(&sim, b"from b")?;
+    lk_save(&sim.nodes[b].lk, &pkt)?;
+    sim.run_until(10, |sim| sim.has(c, pkt.hash()))?;
+
+    // broader than the template
+    lk_pull(&sim.nodes[a].lk, &pull_query(&sim, "all")?)?;
+    sim.run(20)?;
+    assert!(!sim.has(a, pkt.hash())?);
+
+    let x = rspace_buf(&[b"x"]);
+    let x = lk_query_push(pull_query(&sim, "x")?, "prefix", "=", x.space_bytes())?;
+    lk_pull(&sim.nodes[a].lk, &x)?;
+    sim.run_until(20, |sim| sim.has(a, pkt.hash()))?;
+    Ok(())
+}
+
+#[test]
+fn partition_delays_until_healed() -> LkResult<()> {
+    let mut sim = Sim::new("partition_delays_until_healed", 1);
+    let (a, b) = (sim.node("a")?, sim.node("b")?);
+    sim.link(a, b, LinkOpts::default());
+    sim.run_until(5, |sim| Ok(sim.connected(a, b)))?;
+
+    sim.partition(a, b, true);
+    let pkt = point(&sim, b"during partition")?;
+    lk_save(&sim.nodes[b].lk, &pkt)?;
+    sim.run(50)?;
+    assert!(!sim.has(a, pkt.hash())?);
+
+    sim.partition(a, b, false);
+    sim.run_until(10, |sim| sim.has(a, pkt.hash()))?;
+    Ok(())
+}
+
+#[test]
+fn lossy_link_is_deterministic() -> LkResult<()> {
+    let run = |name: &str| -> LkResult<u64> {
+        let mut sim = Sim::new(name, 0x5eed);
+        let (a, b) = (sim.node("a")?, sim.node("b")?);
+        sim.link(
+            a,
+            b,
+            LinkOpts {
+                latency: 2,
+                loss: 0.05,
+                reconnect: 3,
+            },
+        );
+        let pkts = (0..20u8)
+            .map(|i| point(&sim, &[i]))
+            .collect::<LkResult<Vec<_>>>()?;
+        for pkt in &pkts {
+            lk_save(&sim.nodes[b].lk, pkt)?;
+        }
+        sim.run_until(1000, |sim| {
+            for pkt in &pkts {
+                if !sim.has(a, pkt.hash())? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })
+    };
+    // the content differs (create stamps) but the same seed loses the same frames
+    assert_eq!(run("lossy_link_0")?, run("lossy_link_1")?);
+    Ok(())
+}
+
+#[test]
+fn status_replies() -> LkResult<()> {
+    let mut sim = Sim::new("status_replies", 1);
+    let (a, b) = (sim.node("a")?, sim.node("b")?);
+    sim.link(a, b, LinkOpts::default());
+    let lk = sim.nodes[a].lk.clone();
+    let second = Stamp::new(1_000_000);
+
+    let process = Rc::new(RefCell::new(None));
+    let status = LkStatus {
+        domain: EXCHANGE_DOMAIN,
+        group: sim.group,
+        objtype: b"process",
+        instance: Some(b"sim"),
+        qid: b"process",
+    };
+    let reply = process.clone();
+    lk_status_watch(
+        &lk,
+        status,
+        second,
+        None,
+        cb(move |pkt: &dyn NetPkt, _: &Linkspace| {
+            *reply.borrow_mut() = Some(pkt.data().to_vec());
+            true
+        }),
+    )?;
+    sim.run_until(5, |_| Ok(process.borrow().is_some()))?;
+    assert_eq!(process.borrow().as_deref(), Some(&b"OK\nsim a\n"[..]));
+
+    let forwarded = Rc::new(RefCell::new(None));
+    let query = pull_query(&sim, "status")?;
+    let pull = lk_pull(&lk, &query)?;
+    let reply = forwarded.clone();
+    lk_pull_status(
+        &lk,
+        &query,
+        pull,
+        second,
+        cb(move |pkt: &dyn NetPkt, _: &Linkspace| {
+            *reply.borrow_mut() = Some(pkt.data().to_vec());
+            true
+        }),
+    )?;
+    sim.run_until(10, |_| Ok(forwarded.borrow().is_some()))?;
+    let expect = format!("OK\nforwarded to {}\n", sim.nodes[b].key.pubkey());
+    assert_eq!(forwarded.borrow().as_deref(), Some(expect.as_bytes()));
+    Ok(())
+}
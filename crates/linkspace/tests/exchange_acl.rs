@@ -0,0 +1,129 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use common::open;
+use linkspace::{
+    conventions::{
+        exchange_acl::{
+            lk_exchange_acl_check, lk_exchange_acl_check_pull, lk_exchange_acl_set, LkAccess,
+        },
+        pull::lk_pull_point,
+    },
+    key::lk_keygen,
+    prelude::*,
+};
+
+const CHAT: &str = "domain:=:{domain}\ngroup:=:{group}\nprefix:=:/chat\ni_db:<:[u32:100]";
+const PROFILE: &str = "domain:=:{domain}\ngroup:=:{group}\nprefix:=:/profile";
+
+fn query(stmnts: &str) -> LkResult<Query> {
+    let q = lk_query_parse(lk_query(&Q), &["domain:=:acl\ngroup:=:[#:pub]", stmnts], ())?;
+    lk_query_push(q, "", "qid", b"acl")
+}
+
+#[test]
+fn check_templates() -> LkResult<()> {
+    let templates = [
+        (ab(b"chat"), CHAT.to_string()),
+        (ab(b"profile"), PROFILE.to_string()),
+    ];
+    let check =
+        |stmnts: &str| lk_exchange_acl_check(&templates, ab(b"acl"), PUBLIC, &query(stmnts)?);
+    assert_eq!(
+        check("prefix:=:/chat/room\ni_db:<:[u32:10]")?,
+        Some(ab(b"chat"))
+    );
+    assert_eq!(check("prefix:=:/profile")?, Some(ab(b"profile")));
+    // broader than every template
+    assert_eq!(check("prefix:=:/chat")?, None);
+    assert_eq!(check("i_db:<:[u32:10]")?, None);
+    // another domain or group
+    let other = lk_exchange_acl_check(
+        &templates,
+        ab(b"other"),
+        PUBLIC,
+        &query("prefix:=:/profile")?,
+    )?;
+    assert_eq!(other, None);
+    Ok(())
+}
+
+#[test]
+fn check_pull() -> LkResult<()> {
+    let lk = open("check_pull");
+    let (op, stranger) = (lk_keygen(), lk_keygen());
+    let chat = lk_pull_point(&query("prefix:=:/chat/room\ni_db:<:[u32:10]")?)?;
+    let profile = lk_pull_point(&query("prefix:=:/profile")?)?;
+    let broad = lk_pull_point(&query("prefix:=:/chat")?)?;
+
+    // without a list nothing is accepted
+    assert_eq!(lk_exchange_acl_check_pull(&lk, op.pubkey(), &chat)?, None);
+
+    lk_exchange_acl_set(
+        &lk,
+        &op,
+        ab(b"acl"),
+        PUBLIC,
+        LkAccess::Pull,
+        &[(ab(b"chat"), CHAT)],
+    )?;
+    lk_exchange_acl_set(
+        &lk,
+        &stranger,
+        ab(b"acl"),
+        PUBLIC,
+        LkAccess::Pull,
+        &[(ab(b"all"), "domain:=:{domain}\ngroup:=:{group}")],
+    )?;
+    assert_eq!(
+        lk_exchange_acl_check_pull(&lk, op.pubkey(), &chat)?,
+        Some(ab(b"chat"))
+    );
+    assert_eq!(
+        lk_exchange_acl_check_pull(&lk, op.pubkey(), &profile)?,
+        None
+    );
+    // the list signed by the wrong key is not used
+    assert_eq!(lk_exchange_acl_check_pull(&lk, op.pubkey(), &broad)?, None);
+    assert_eq!(
+        lk_exchange_acl_check_pull(&lk, stranger.pubkey(), &broad)?,
+        Some(ab(b"all"))
+    );
+    let unknown = lk_keygen();
+    assert_eq!(
+        lk_exchange_acl_check_pull(&lk, unknown.pubkey(), &chat)?,
+        None
+    );
+
+    // a newer list supersedes the old one
+    lk_exchange_acl_set(
+        &lk,
+        &op,
+        ab(b"acl"),
+        PUBLIC,
+        LkAccess::Pull,
+        &[(ab(b"profile"), PROFILE)],
+    )?;
+    assert_eq!(lk_exchange_acl_check_pull(&lk, op.pubkey(), &chat)?, None);
+    assert_eq!(
+        lk_exchange_acl_check_pull(&lk, op.pubkey(), &profile)?,
+        Some(ab(b"profile"))
+    );
+    assert_eq!(lk_exchange_acl_check_pull(&lk, op.pubkey(), &broad)?, None);
+
+    // a push list does not grant pulls
+    lk_exchange_acl_set(
+        &lk,
+        &op,
+        ab(b"acl"),
+        PUBLIC,
+        LkAccess::Push,
+        &[(ab(b"chat"), CHAT)],
+    )?;
+    assert_eq!(lk_exchange_acl_check_pull(&lk, op.pubkey(), &chat)?, None);
+    Ok(())
+}
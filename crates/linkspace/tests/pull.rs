@@ -0,0 +1,56 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use common::open;
+use linkspace::{
+    consts::EXCHANGE_DOMAIN,
+    conventions::pull::lk_pull_watch,
+    prelude::*,
+    runtime::{cb::cb, lk_get_all},
+};
+
+/// the hash and data of the most recent pull point for the qid
+fn latest_pull(lk: &Linkspace, qid: &[u8]) -> LkResult<(LkHash, Vec<u8>)> {
+    lk_process(lk);
+    let space = rspace_buf(&[b"pull", &*PUBLIC, &*ab(b"pull"), qid]);
+    let q = lk_query_push(lk_query(&Q), "domain", "=", &*EXCHANGE_DOMAIN)?;
+    let q = lk_query_push(q, "group", "=", &*PRIVATE)?;
+    let q = lk_query_push(q, "spacename", "=", space.space_bytes())?;
+    let mut latest: Option<(Stamp, LkHash, Vec<u8>)> = None;
+    lk_get_all(lk, &q, &mut |p| {
+        let create = *p.get_create_stamp();
+        if latest.as_ref().map_or(true, |(c, _, _)| *c < create) {
+            latest = Some((create, p.hash(), p.data().to_vec()));
+        }
+        false
+    })?;
+    let (_, hash, data) = latest.expect("no pull point");
+    Ok((hash, data))
+}
+
+#[test]
+fn pull_again_with_same_qid() -> LkResult<()> {
+    let lk = open("pull_again_with_same_qid");
+    let q = lk_query_parse(lk_query(&Q), &["domain:=:pull\ngroup:=:[#:pub]"], ())?;
+    let q = lk_query_push(q, "", "qid", b"again")?;
+
+    let first = lk_pull_watch(&lk, &q, cb(|_: &dyn NetPkt, _: &Linkspace| false))?;
+    assert_eq!(latest_pull(&lk, b"again")?.0, first);
+
+    // closing the first watch does not close the second pull
+    let second = lk_pull_watch(&lk, &q, cb(|_: &dyn NetPkt, _: &Linkspace| false))?;
+    let (latest, data) = latest_pull(&lk, b"again")?;
+    assert_eq!(latest, second);
+    assert!(!data.is_empty());
+
+    // stopping the watch closes the pull
+    lk_stop(&lk, b"again", false);
+    let (latest, data) = latest_pull(&lk, b"again")?;
+    assert_ne!(latest, second);
+    assert!(data.is_empty());
+    Ok(())
+}
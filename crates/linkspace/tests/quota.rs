@@ -0,0 +1,76 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use common::open;
+use linkspace::{
+    misc::ReroutePkt,
+    prelude::*,
+    query::lk_hash_query,
+    runtime::{
+        lk_save_state,
+        quota::{lk_quota_list, lk_quota_set, Quota, QuotaKind},
+        SaveState,
+    },
+};
+
+fn point(domain: &[u8], data: &[u8]) -> NetPktBox {
+    lk_linkpoint(data, ab(domain), PUBLIC, &rspace_buf(&[b"a"]), &[], None).unwrap()
+}
+/// the packet as received from elsewhere
+fn remote(pkt: &NetPktBox) -> ReroutePkt<&NetPktBox> {
+    let mut pkt = ReroutePkt::new(pkt);
+    pkt.net_header = pkt.net_header.hop();
+    pkt
+}
+
+#[test]
+fn rejected() -> LkResult<()> {
+    let lk = open("rejected");
+    let reject_all = Quota {
+        pkts_per_sec: Some(0),
+        bytes_per_day: None,
+    };
+    lk_quota_set(&lk, QuotaKind::Group, &PUBLIC.0, reject_all)?;
+    assert_eq!(
+        lk_quota_list(&lk)?,
+        [(QuotaKind::Group, PUBLIC.0.to_vec(), reject_all)]
+    );
+
+    let pkt = point(b"quota-test", b"from elsewhere");
+    assert_eq!(lk_save_state(&lk, &remote(&pkt))?, SaveState::Rejected);
+    assert!(!lk_save(&lk, &remote(&pkt))?);
+    assert!(lk_get(&lk, &lk_hash_query(pkt.hash()))?.is_none());
+    // a local packet is not counted, and once saved it exists
+    assert_eq!(lk_save_state(&lk, &pkt)?, SaveState::Written);
+    assert_eq!(lk_save_state(&lk, &remote(&pkt))?, SaveState::Exists);
+
+    // an unlimited quota removes it
+    lk_quota_set(&lk, QuotaKind::Group, &PUBLIC.0, Quota::default())?;
+    assert!(lk_quota_list(&lk)?.is_empty());
+    let pkt = point(b"quota-test", b"admitted");
+    assert_eq!(lk_save_state(&lk, &remote(&pkt))?, SaveState::Written);
+    Ok(())
+}
+
+#[test]
+fn bytes_per_day() -> LkResult<()> {
+    let lk = open("bytes_per_day");
+    let first = point(b"quota-test", b"first");
+    let quota = Quota {
+        pkts_per_sec: None,
+        bytes_per_day: Some(first.size() as u64),
+    };
+    // the default of every domain without its own quota
+    lk_quota_set(&lk, QuotaKind::Domain, &[], quota)?;
+    assert_eq!(lk_save_state(&lk, &remote(&first))?, SaveState::Written);
+    // the usage is kept in the instance across saves
+    let second = point(b"quota-test", b"second");
+    assert_eq!(lk_save_state(&lk, &remote(&second))?, SaveState::Rejected);
+    let other = point(b"other", b"other");
+    assert_eq!(lk_save_state(&lk, &remote(&other))?, SaveState::Written);
+    Ok(())
+}
@@ -0,0 +1,147 @@
+// Copyright Anton Sol
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+mod common;
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use common::open;
+use linkspace::{
+    conventions::status::{
+        lk_status_history, lk_status_request, lk_status_set, lk_status_watch, LkStatus,
+    },
+    prelude::*,
+    runtime::cb::cb,
+};
+
+fn status(qid: &[u8]) -> LkStatus<'_> {
+    LkStatus {
+        domain: ab(b"status-test"),
+        group: PUBLIC,
+        objtype: b"process",
+        instance: None,
+        qid,
+    }
+}
+
+fn sleep(millis: u64) {
+    std::thread::sleep(Duration::from_millis(millis))
+}
+
+/// Request a status and process the replies
+fn request(lk: &Linkspace) -> LkResult<()> {
+    sleep(1);
+    lk_save(lk, &lk_status_request(status(b"request"))?)?;
+    lk_process(lk);
+    Ok(())
+}
+
+/// Start a process that replies to status requests. Stopped with lk_stop(qid)
+fn start(lk: &Linkspace, qid: &[u8]) -> LkResult<()> {
+    lk_status_set(lk, status(qid), |_, domain, group, space, link| {
+        lk_linkpoint(b"OK\n", domain, group, space, &[link], None)
+    })?;
+    lk_process(lk);
+    Ok(())
+}
+
+/// The first link tag of every reply in the history
+fn history(lk: &Linkspace) -> LkResult<Vec<Tag>> {
+    let mut tags = vec![];
+    let count = lk_status_history(lk, status(b"history"), &mut |pkt| {
+        tags.push(pkt.get_links()[0].tag);
+        false
+    })?;
+    assert_eq!(count as usize, tags.len());
+    Ok(tags)
+}
+
+/// Watch the status and return if an existing reply was found and the replies passed to cb
+fn watch(
+    lk: &Linkspace,
+    qid: &[u8],
+    d_timeout: Stamp,
+    max_age: Option<Stamp>,
+) -> LkResult<(bool, Rc<RefCell<Vec<NetPktBox>>>)> {
+    let replies = Rc::new(RefCell::new(vec![]));
+    let found = replies.clone();
+    let existing = lk_status_watch(
+        lk,
+        status(qid),
+        d_timeout,
+        max_age,
+        cb(move |pkt: &dyn NetPkt, _: &Linkspace| {
+            found.borrow_mut().push(pkt.as_netbox());
+            false
+        }),
+    )?;
+    Ok((existing, replies))
+}
+
+const MS: u64 = 1000;
+const HOUR: Stamp = Stamp::new(3600 * 1000 * MS);
+
+#[test]
+fn never_ran() -> LkResult<()> {
+    let lk = open("never_ran");
+    assert!(history(&lk)?.is_empty());
+    let (existing, replies) = watch(&lk, b"watch", Stamp::new(10 * MS), Some(HOUR))?;
+    assert!(!existing);
+    sleep(20);
+    lk_process(&lk);
+    assert!(replies.borrow().is_empty());
+    // the request made by the watch is not a reply
+    assert!(history(&lk)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn ran_but_stopped() -> LkResult<()> {
+    let lk = open("ran_but_stopped");
+    let (init, prev) = (ab(b"init"), ab(b"prev"));
+    start(&lk, b"first")?;
+    request(&lk)?;
+    lk_stop(&lk, b"first", false);
+    // a request after stopping gets no reply
+    request(&lk)?;
+    assert_eq!(history(&lk)?, [prev, init]);
+
+    start(&lk, b"second")?;
+    request(&lk)?;
+    request(&lk)?;
+    lk_stop(&lk, b"second", false);
+    // the chain of the second process back to its init, then the chain of the first
+    assert_eq!(history(&lk)?, [prev, prev, init, prev, init]);
+
+    sleep(50);
+    let (existing, replies) = watch(&lk, b"recent", Stamp::new(20 * MS), None)?;
+    assert!(!existing, "no reply within the timeout");
+    assert!(replies.borrow().is_empty());
+    let (existing, replies) = watch(&lk, b"ever", Stamp::new(20 * MS), Some(HOUR))?;
+    assert!(existing, "max_age includes stopped processes");
+    assert_eq!(replies.borrow().len(), 5);
+    sleep(40);
+    lk_process(&lk);
+    assert_eq!(replies.borrow().len(), 5, "no new reply");
+    Ok(())
+}
+
+#[test]
+fn running() -> LkResult<()> {
+    let lk = open("running");
+    start(&lk, b"process")?;
+    sleep(100);
+    // the init reply is too old, the watch requests a new one
+    let (existing, replies) = watch(&lk, b"watch", Stamp::new(50 * MS), None)?;
+    assert!(!existing);
+    lk_process(&lk);
+    lk_process(&lk);
+    let replies = replies.borrow();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].data(), b"OK\n");
+    assert_eq!(replies[0].get_links()[0].tag, ab(b"prev"));
+    assert_eq!(history(&lk)?, [ab(b"prev"), ab(b"init")]);
+    Ok(())
+}
@@ -12,4 +12,7 @@ export ADDR=${ADDR:-0.0.0.0}
 export PORT=${PORT:-9090}
 export IO=${IO:-./chatroom.io.sh}
 
+# 'lk serve-ws --listen $ADDR:$PORT --path-space --origin $PAGE_ORIGIN $*' does the same without websocat
+# (serve-ws rejects browsers on other origins, PAGE_ORIGIN is where the chat page is served from)
+
 websocat --binary -E -e ws-listen:$ADDR:$PORT sh-c:"$IO $*"
@@ -82,6 +82,38 @@ pub fn lk_key_decrypt(id: &str, password: &[u8]) -> Result<SigningKey> {
     Ok(SigningKey(identity::decrypt(id, password)?))
 }
 
+// lk_encrypt_for_group resolves the members through LNS and requires a linkspace instance - the web build has none.
+#[wasm_bindgen]
+pub fn lk_encrypt_for(pubkeys: &js_sys::Array, data: &JsValue) -> Result<Box<[u8]>> {
+    let pubkeys: Vec<PubKey> = pubkeys
+        .iter()
+        .map(|k| -> Result<PubKey, JsErr> {
+            Ok(PubKey::try_fit_bytes_or_b64(&bytelike(&k)?)
+                .ok()
+                .ok_or("invalid pubkey")?)
+        })
+        .try_collect()?;
+    linkspace::key::lk_encrypt_for(&pubkeys, &bytelike(data)?)
+        .map_err(|e| JsError::new(&format!("{e:#?}")))
+        .map(|v| v.into_boxed_slice())
+}
+/// the public keys that can decrypt the result of lk_encrypt_for
+#[wasm_bindgen]
+pub fn lk_encrypted_for(data: &[u8]) -> Result<js_sys::Array> {
+    let pubkeys =
+        linkspace::key::lk_encrypted_for(data).map_err(|e| JsError::new(&format!("{e:#?}")))?;
+    Ok(pubkeys
+        .iter()
+        .map(|k| JsValue::from(Uint8Array::from(&k.0[..])))
+        .collect())
+}
+#[wasm_bindgen]
+pub fn lk_decrypt(key: &SigningKey, data: &[u8]) -> Result<Box<[u8]>> {
+    linkspace::key::lk_decrypt(&key.0, data)
+        .map_err(|e| JsError::new(&format!("{e:#?}")))
+        .map(|v| v.into_boxed_slice())
+}
+
 #[wasm_bindgen]
 extern "C" {
 
@@ -336,6 +368,42 @@ pub fn lk_eval(
         .map_err(|e| JsError::new(&format!("{e:#?}")))
         .map(|v| v.into_boxed_slice())
 }
+fn call_bytes(func: &js_sys::Function, arg: &JsValue) -> anyhow::Result<Vec<u8>> {
+    let result = func
+        .call1(&JsValue::NULL, arg)
+        .map_err(|e| anyhow::anyhow!("{}", JsErr::from(e)))?;
+    Ok(bytelike(&result)
+        .map_err(|e| anyhow::anyhow!("{e}"))?
+        .to_vec())
+}
+#[wasm_bindgen]
+pub fn lk_register_func(
+    id: &str,
+    func: js_sys::Function,
+    help: Option<String>,
+    max_args: Option<usize>,
+) {
+    let help = help.unwrap_or_default();
+    let argc = 0..=max_args.unwrap_or(8);
+    linkspace::abe::scope::lk_register_func(id, &help, argc, move |args| {
+        let args: js_sys::Array = args
+            .iter()
+            .map(|a| JsValue::from(js_sys::Uint8Array::from(*a)))
+            .collect();
+        call_bytes(&func, &args)
+    })
+}
+#[wasm_bindgen]
+pub fn lk_register_macro(id: &str, func: js_sys::Function, help: Option<String>) {
+    let help = help.unwrap_or_default();
+    linkspace::abe::scope::lk_register_macro(id, &help, move |abe| {
+        call_bytes(&func, &JsValue::from_str(abe))
+    })
+}
+#[wasm_bindgen]
+pub fn lk_unregister(id: &str) -> bool {
+    linkspace::abe::scope::lk_unregister(id)
+}
 #[wasm_bindgen]
 pub fn lk_eval2str(
     expr: &str,
@@ -362,6 +430,10 @@ pub fn lk_encode(bytes: &[u8], options: Option<String>) -> String {
     linkspace::lk_encode(bytes, options.as_deref().unwrap_or(""))
 }
 #[wasm_bindgen]
+pub fn lk_encode_ab_frame(bytes: &[u8]) -> Box<[u8]> {
+    linkspace::abe::lk_encode_ab_frame(bytes).into_boxed_slice()
+}
+#[wasm_bindgen]
 pub fn blake3_hash(bytes: &[u8]) -> Box<[u8]> {
     Box::new(*blake3::hash(bytes).as_bytes())
 }
@@ -108,6 +108,27 @@ def lk_eval2str(abe:str,pkt:Pkt|None=None,argv:list[bytes|str] | None = None, lo
     """ lk_eval that attempts to cast the result of lk_eval as a utf-8 string"""
     ...
 
+def lk_register_func(id:str, func:Callable[[list[bytes]],bytes|str], help:str="", max_args:int=8):
+    """
+    Add a function to the scope of this thread. e.g. lk_eval("[myapp:thread-id:12]").
+    Used by lk_eval, lk_query_parse and any other function that evaluates abe.
+    Builtin functions take precedence. Registering an existing id replaces it.
+    Args:
+        func: called with the evaluated arguments
+    """
+    ...
+
+def lk_register_macro(id:str, func:Callable[[str],bytes|str], help:str=""):
+    """
+    Add a macro to the scope of this thread. e.g. lk_eval("[/myapp:..]").
+    func is called with the unevaluated arguments as an abe string (starting with ':')
+    """
+    ...
+
+def lk_unregister(id:str) -> bool:
+    """ remove a function or macro added with lk_register_func or lk_register_macro """
+    ...
+
 def lk_encode(
         bytes:bytes,
         opts:str|None) -> str:
@@ -123,6 +144,10 @@ def lk_encode(
     """
     ...
 
+def lk_encode_ab_frame(bytes:bytes) -> bytes:
+    """ Encode bytes as a binary '#ab' frame. lk_encode(frame,"#ab") prints it as [#ab:..] """
+    ...
+
 
 def lk_get(lk:Linkspace,query:Query) -> Pkt | None:
     """Get the first result from the database."""
@@ -172,6 +197,25 @@ def lk_key(lk:Linkspace,password:bytes|None=None,name:str|None=None,create:bool=
     ...
 
 def lk_keygen() -> SigningKey: ...
+def lk_encrypt_for(pubkeys:list[bytes|str], data:bytes|str) -> bytes:
+    """
+    Encrypt data such that only the keys of pubkeys can lk_decrypt it.
+    The pubkeys of group members are usually looked up through LNS e.g. lk_eval("[@:alice:local]").
+    The list of recipient pubkeys is readable by anyone.
+    """
+    ...
+def lk_encrypt_for_group(lk:Linkspace, group:str, data:bytes|str) -> bytes:
+    """
+    lk_encrypt_for every member of the LNS name group (e.g. "team:local").
+    The members are the pubkeys of the live claims directly below it (alice:team:local, bob:team:local, ..).
+    """
+    ...
+def lk_encrypted_for(data:bytes) -> list[bytes]:
+    """
+    The pubkeys that can lk_decrypt the result of lk_encrypt_for.
+    """
+    ...
+def lk_decrypt(key:SigningKey, data:bytes) -> bytes: ...
 def lk_keyopen(enckey:str,password:bytes) -> SigningKey: ...
 def lk_enckey(key:SigningKey, password:bytes) -> str: ...
 def lk_list_watches(*args, **kwargs) -> Any: ...
@@ -232,6 +276,25 @@ def lk_query_push(q:Query, field:str,op:str,val:bytes):
     See the guide or rust docs for a full list of predicates and options.
     """
     ...
+def lk_query_push_strict(q:Query, field:str,op:str,val:bytes):
+    """
+    lk_query_push but raises an error if the field already has a predicate.
+    Use it to add predicates to a query from an untrusted source.
+    Any op (e.g. ">=") is added strictly. In statements only single char ops have a strict form ('<!'), '>=!' is an error.
+    """
+    ...
+def lk_query_template(template:str, vars:dict[str,bytes|str]|None=None,
+                      pkt:Pkt|None=None, argv:list[bytes|str]|None=None) -> Query:
+    """
+    Fill the {name} placeholders of template with the abtxt encoding of vars and parse it into a new query.
+    Evaluated like lk_query_parse.
+    """
+    ...
+def lk_query_satisfies(template:Query, q:Query) -> bool:
+    """
+    True if q does not select more than the template. Options are ignored.
+    """
+    ...
 def lk_query_print(q:Query, expr: bool = False) -> str:
     """
     Print the query as a list of statements. Can be used in lk_query_parse.
@@ -241,6 +304,10 @@ def lk_query_print(q:Query, expr: bool = False) -> str:
     """
     ...
 
+def lk_query_print_aliases(q:Query) -> str:
+    """ lk_query_print(q,True) with counters as decimal aliases like 'data_size<1000'. Older versions can not parse these. """
+    ...
+
 
 def lk_save(lk:Linkspace, pkt:Pkt) -> bool:
     """
@@ -249,7 +316,15 @@ def lk_save(lk:Linkspace, pkt:Pkt) -> bool:
         lk:
         pkt:
     Returns:
-        True if packet is new, False if already exists.
+        True if packet is new, False if already exists or is rejected by a quota.
+    """
+    ...
+
+def lk_save_state(lk:Linkspace, pkt:Pkt) -> str:
+    """
+    lk_save that tells why a packet was not saved.
+    Returns:
+        "Written" if new, "Exists" if already saved, or "Rejected" if it exceeds a quota (see lk_quota_set)
     """
     ...
 
@@ -299,12 +374,53 @@ def lk_pull(lk: Linkspace, query: Query):
         query: Must have a qid set
     """
     ...
+def lk_pull_close(lk: Linkspace, query: Query) -> bytes:
+    """
+    Close the pull of query by saving an empty pull point to the same spacename.
+    Returns the hash of the close point.
+    """
+    ...
+def lk_pull_watch(lk: Linkspace, query: Query,
+            on_match: Callable[[Pkt],bool|None],
+            on_close: Callable[[Pkt],Any] | None = None,
+            on_err: Callable[[Pkt],Any] | None = None,
+            ) -> bytes:
+    """
+    lk_pull and lk_watch the query. The pull is closed once the watch breaks, finishes, or is stopped with lk_stop.
+    Returns the hash of the pull point.
+    """
+    ...
+def lk_pull_status(lk: Linkspace, query: Query, pull: bytes, timeout: bytes,
+                   callback: Callable[[Pkt],Any] | None = None) -> bool:
+    """
+    lk_status_watch the status 'exchange GROUP pull PULL_HASH', i.e. if an exchange has forwarded the pull.
+    pull is the hash returned by lk_pull. The watch uses the qid b"pull-status:"+qid
+    """
+    ...
+def lk_exchange_acl_set(lk: Linkspace, key: SigningKey, access: str, templates: list[tuple[bytes,str]], group: bytes|None = None, domain: bytes|None = None) -> bytes:
+    """
+    Save a list of (name,query template) an exchange accepts to
+         DOMAIN:GROUP:/\\fexchange/access/(pull|push)
+    Returns the hash of the list keypoint.
+    """
+    ...
+def lk_exchange_acl_get(lk: Linkspace, access: str, pubkey: bytes, group: bytes|None = None, domain: bytes|None = None) -> list[tuple[bytes,str]] | None:
+    """
+    Read the templates of the most recent list signed by pubkey.
+    """
+    ...
+def lk_exchange_acl_check_pull(lk: Linkspace, pubkey: bytes, pull: Pkt) -> bytes | None:
+    """
+    Check a pull point against the most recent pull list signed by pubkey. Returns the name of the accepting template.
+    """
+    ...
 
 def lk_status_watch(lk:Linkspace,qid:bytes,objtype:bytes,
                    timeout:bytes, 
                    instace : bytes | None = None ,
                    callback:Callable[[Pkt],Any] | None = None,
-                   group:bytes|None = None,domain:bytes|None=None
+                   group:bytes|None = None,domain:bytes|None=None,
+                   max_age:bytes|None = None
                    ) -> bool:
     """
     status_set and status_poll are a convention to communicate between two processes over the [#:0] group about a (group,domain,obj_type, ?instace).
@@ -340,11 +456,48 @@ def lk_status_watch(lk:Linkspace,qid:bytes,objtype:bytes,
         objtype: a agreed upon name for the status.
         instance: a specific instance for the objtype
         callback: Receives the status packets made with lk_status_set. Return True to stop early.
+        max_age: also accept existing replies made since now-max_age. e.g. to check if a process has ever set the status.
     
     Returns: true if any lk_status_set has replied. 
     """
     ...
 
+def lk_status_history(lk:Linkspace,objtype:bytes,
+                      callback:Callable[[Pkt],Any],
+                      instance : bytes | None = None ,
+                      group:bytes|None = None,domain:bytes|None=None
+                      ) -> int:
+    """
+    Iterate over the replies of an instance (defaults to b"default") from newest to oldest by following their 'prev' links.
+    Together with lk_status_watch this tells apart a process that never ran, ran and stopped, or is running.
+
+    Args:
+        callback: Receives the replies. Return True to stop early.
+
+    Returns: the number of replies passed to callback.
+    """
+    ...
+
+def lk_quota_set(lk:Linkspace, kind:str, id:bytes|None=None,
+                 pkts_per_sec:int|None=None, bytes_per_day:int|None=None) -> None:
+    """
+    Limit the packets saved from elsewhere (hop > 0) under a pubkey, group, or domain.
+    A packet over a quota is not saved, lk_save_state returns "Rejected".
+
+    Args:
+        kind: "pubkey", "group", or "domain"
+        id: The pubkey, group, or domain. None sets the default of every id of the kind without its own quota.
+        pkts_per_sec: None is unlimited, 0 rejects every packet
+        bytes_per_day: None is unlimited, 0 rejects every packet
+    Without either limit the quota is removed.
+    """
+    ...
+
+def lk_quota_list(lk:Linkspace) -> list[tuple[str,bytes,int|None,int|None]]:
+    """ All quotas as (kind, id, pkts_per_sec, bytes_per_day) """
+    ...
+
+
 
 def lk_status_set(lk:Linkspace,qid:bytes,
                   objtype:bytes,
@@ -26,7 +26,7 @@ use ::linkspace::{
 };
 use pyo3::{
     prelude::*,
-    types::{PyBytes, PyTuple},
+    types::{PyBytes, PyDict, PyTuple},
 };
 mod pynetpkt;
 use pynetpkt::Pkt;
@@ -192,6 +192,40 @@ pub fn lk_key_decrypt(_py: Python, id: &str, password: &[u8]) -> anyhow::Result<
     Ok(SigningKey(linkspace_rs::key::lk_key_decrypt(id, password)?))
 }
 
+#[pyfunction]
+pub fn lk_encrypt_for<'a>(
+    py: Python<'a>,
+    pubkeys: Vec<&PyAny>,
+    data: &PyAny,
+) -> anyhow::Result<&'a PyBytes> {
+    let pubkeys: Vec<PubKey> = pubkeys
+        .into_iter()
+        .map(|k| -> anyhow::Result<PubKey> { Ok(PubKey::try_fit_bytes_or_b64(bytelike(k)?)?) })
+        .try_collect()?;
+    let sealed = linkspace_rs::key::lk_encrypt_for(&pubkeys, bytelike(data)?)?;
+    Ok(PyBytes::new(py, &sealed))
+}
+#[pyfunction]
+pub fn lk_encrypt_for_group<'a>(
+    py: Python<'a>,
+    lk: &Linkspace,
+    group: &str,
+    data: &PyAny,
+) -> anyhow::Result<&'a PyBytes> {
+    let sealed = linkspace_rs::key::lk_encrypt_for_group(&lk.0, group, bytelike(data)?)?;
+    Ok(PyBytes::new(py, &sealed))
+}
+#[pyfunction]
+pub fn lk_encrypted_for<'a>(py: Python<'a>, data: &[u8]) -> anyhow::Result<Vec<&'a PyBytes>> {
+    let pubkeys = linkspace_rs::key::lk_encrypted_for(data)?;
+    Ok(pubkeys.iter().map(|k| PyBytes::new(py, &k.0)).collect())
+}
+#[pyfunction]
+pub fn lk_decrypt<'a>(py: Python<'a>, key: &SigningKey, data: &[u8]) -> anyhow::Result<&'a PyBytes> {
+    let data = linkspace_rs::key::lk_decrypt(&key.0, data)?;
+    Ok(PyBytes::new(py, &data))
+}
+
 #[pyfunction]
 pub fn lk_key(
     lk: &Linkspace,
@@ -288,6 +322,31 @@ pub fn lk_eval<'a>(
     Ok(PyBytes::new(py, &bytes))
 }
 #[pyfunction]
+#[pyo3(signature =(id,func,help="",max_args=8))]
+pub fn lk_register_func(id: &str, func: PyFunc, help: &str, max_args: usize) {
+    linkspace_rs::abe::scope::lk_register_func(id, help, 0..=max_args, move |args| {
+        Python::with_gil(|py| {
+            let args: Vec<&PyBytes> = args.iter().map(|a| PyBytes::new(py, a)).collect();
+            let result = func.call1(py, (args,))?;
+            Ok(bytelike(result.as_ref(py))?.to_vec())
+        })
+    })
+}
+#[pyfunction]
+#[pyo3(signature =(id,func,help=""))]
+pub fn lk_register_macro(id: &str, func: PyFunc, help: &str) {
+    linkspace_rs::abe::scope::lk_register_macro(id, help, move |abe| {
+        Python::with_gil(|py| {
+            let result = func.call1(py, (abe,))?;
+            Ok(bytelike(result.as_ref(py))?.to_vec())
+        })
+    })
+}
+#[pyfunction]
+pub fn lk_unregister(id: &str) -> bool {
+    linkspace_rs::abe::scope::lk_unregister(id)
+}
+#[pyfunction]
 pub fn lk_eval2str(
     expr: &str,
     pkt: Option<&Pkt>,
@@ -316,6 +375,10 @@ pub fn lk_eval2str(
 pub fn lk_encode(bytes: &[u8], options: Option<&str>) -> anyhow::Result<String> {
     Ok(linkspace_rs::abe::lk_encode(bytes, options.unwrap_or("")))
 }
+#[pyfunction]
+pub fn lk_encode_ab_frame<'a>(py: Python<'a>, bytes: &[u8]) -> &'a PyBytes {
+    PyBytes::new(py, &linkspace_rs::abe::lk_encode_ab_frame(bytes))
+}
 #[pyclass(unsendable)]
 #[derive(Clone)]
 #[repr(transparent)]
@@ -338,6 +401,11 @@ pub fn lk_save(runtime: &Linkspace, pkt: &Pkt) -> anyhow::Result<bool> {
     let pkts = [pkt.0.netpktptr() as &dyn NetPkt];
     Ok(linkspace_rs::runtime::lk_save_all(&runtime.0, &pkts)? > 0)
 }
+#[pyfunction]
+pub fn lk_save_state(runtime: &Linkspace, pkt: &Pkt) -> anyhow::Result<String> {
+    let state = linkspace_rs::runtime::lk_save_state(&runtime.0, pkt.0.netpktptr())?;
+    Ok(state.to_string())
+}
 // TODO: fix double allocation
 #[pyfunction]
 pub fn lk_save_all(runtime: &Linkspace, pkts: &PyAny) -> anyhow::Result<usize> {
@@ -416,16 +484,61 @@ pub fn lk_query_parse(
     Ok(Query(query))
 }
 #[pyfunction]
+#[pyo3(signature =(template,vars=None,pkt=None,argv=None))]
+pub fn lk_query_template(
+    template: &str,
+    vars: Option<&PyDict>,
+    pkt: Option<&Pkt>,
+    argv: Option<&PyAny>,
+) -> anyhow::Result<Query> {
+    let argv: Vec<&[u8]> = argv
+        .map(|v| v.iter()?.take(9).map(|v| bytelike(v?)).try_collect())
+        .transpose()?
+        .unwrap_or_default();
+    let vars: Vec<(&str, &[u8])> = vars
+        .map(|d| {
+            d.iter()
+                .map(|(k, v)| Ok::<_, PyErr>((k.extract::<&str>()?, bytelike(v)?)))
+                .try_collect()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let udata = UserData {
+        argv: Some(&argv),
+        pkt: pptr(pkt),
+    };
+    let query = linkspace_rs::lk_query_template(template, &vars, udata)?;
+    Ok(Query(query))
+}
+#[pyfunction]
 pub fn lk_query_push(query: Query, field: &str, op: &str, bytes: &PyAny) -> LkResult<Query> {
     let q = linkspace_rs::lk_query_push(query.0, field, op, bytelike(bytes)?)?;
     Ok(Query(q))
 }
 #[pyfunction]
+pub fn lk_query_push_strict(
+    query: Query,
+    field: &str,
+    op: &str,
+    bytes: &PyAny,
+) -> LkResult<Query> {
+    let q = linkspace_rs::lk_query_push_strict(query.0, field, op, bytelike(bytes)?)?;
+    Ok(Query(q))
+}
+#[pyfunction]
+pub fn lk_query_satisfies(template: &Query, query: &Query) -> bool {
+    linkspace_rs::lk_query_satisfies(&template.0, &query.0)
+}
+#[pyfunction]
 #[pyo3(signature =(query,as_expr=false))]
 pub fn lk_query_print(query: &Query, as_expr: bool) -> String {
     linkspace_rs::lk_query_print(&query.0, as_expr)
 }
 #[pyfunction]
+pub fn lk_query_print_aliases(query: &Query) -> String {
+    linkspace_rs::lk_query_print_aliases(&query.0)
+}
+#[pyfunction]
 pub fn lk_query_clear(query: &mut Query) {
     linkspace_rs::query::lk_query_clear(&mut query.0)
 }
@@ -592,9 +705,11 @@ pub fn lk_status_watch(
     callback: Option<PyFunc>,
     group: Option<&[u8]>,
     domain: Option<&[u8]>,
+    max_age: Option<&[u8]>,
 ) -> anyhow::Result<bool> {
     use linkspace_rs::conventions::status::*;
     let timeout = Stamp::try_from(timeout)?;
+    let max_age = max_age.map(Stamp::try_from).transpose()?;
     let group = grp_arg(group)?;
     let domain = domain_arg(domain)?;
     let status_ctx = LkStatus {
@@ -609,7 +724,35 @@ pub fn lk_status_watch(
         on_close: None,
         on_err: None,
     };
-    lk_status_watch(&lk.0, status_ctx, timeout, handler)
+    lk_status_watch(&lk.0, status_ctx, timeout, max_age, handler)
+}
+#[pyfunction]
+pub fn lk_status_history(
+    py: Python,
+    lk: &Linkspace,
+    objtype: &[u8],
+    callback: PyFunc,
+    instance: Option<&[u8]>,
+    group: Option<&[u8]>,
+    domain: Option<&[u8]>,
+) -> anyhow::Result<u32> {
+    use linkspace_rs::conventions::status::*;
+    let status_ctx = LkStatus {
+        domain: domain_arg(domain)?,
+        group: grp_arg(group)?,
+        objtype,
+        instance,
+        qid: &[],
+    };
+    let mut cb_err = Ok(());
+    let count = lk_status_history(&lk.0, status_ctx, &mut |pkt: &dyn NetPkt| {
+        let pkt = Pkt::from_dyn(pkt);
+        let mut breaks = false;
+        cb_err = call_cont_py(py, &callback, (pkt,)).map(|c| breaks = c);
+        breaks || cb_err.is_err()
+    });
+    cb_err?;
+    count
 }
 
 #[pyfunction]
@@ -617,6 +760,120 @@ pub fn lk_pull<'o>(py: Python<'o>, lk: &Linkspace, query: &Query) -> anyhow::Res
     let hash = linkspace_rs::conventions::pull::lk_pull(&lk.0, &query.0)?;
     Ok(PyBytes::new(py, &hash.0))
 }
+#[pyfunction]
+pub fn lk_pull_close<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+    query: &Query,
+) -> anyhow::Result<&'o PyBytes> {
+    let hash = linkspace_rs::conventions::pull::lk_pull_close(&lk.0, &query.0)?;
+    Ok(PyBytes::new(py, &hash.0))
+}
+#[pyfunction]
+pub fn lk_pull_watch<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+    query: &Query,
+    on_match: Option<PyFunc>,
+    on_close: Option<PyFunc>,
+    on_err: Option<PyFunc>,
+) -> anyhow::Result<&'o PyBytes> {
+    let watch_handler = PyPktStreamHandler {
+        on_match,
+        on_close,
+        on_err,
+    };
+    let hash = linkspace_rs::conventions::pull::lk_pull_watch(&lk.0, &query.0, watch_handler)?;
+    Ok(PyBytes::new(py, &hash.0))
+}
+#[pyfunction]
+pub fn lk_pull_status(
+    lk: &Linkspace,
+    query: &Query,
+    pull: &[u8],
+    timeout: &[u8],
+    callback: Option<PyFunc>,
+) -> anyhow::Result<bool> {
+    let pull = LkHash::try_fit_bytes_or_b64(pull)?;
+    let timeout = Stamp::try_from(timeout)?;
+    let handler = PyPktStreamHandler {
+        on_match: callback,
+        on_close: None,
+        on_err: None,
+    };
+    linkspace_rs::conventions::pull::lk_pull_status(&lk.0, &query.0, pull, timeout, handler)
+}
+
+use linkspace_rs::conventions::exchange_acl::LkAccess;
+fn access_arg(access: &str) -> anyhow::Result<LkAccess> {
+    match access {
+        "pull" => Ok(LkAccess::Pull),
+        "push" => Ok(LkAccess::Push),
+        _ => anyhow::bail!("access must be 'pull' or 'push'"),
+    }
+}
+#[pyfunction]
+pub fn lk_exchange_acl_set<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+    key: &SigningKey,
+    access: &str,
+    templates: Vec<(&[u8], &str)>,
+    group: Option<&[u8]>,
+    domain: Option<&[u8]>,
+) -> anyhow::Result<&'o PyBytes> {
+    let templates: Vec<(Tag, &str)> = templates
+        .into_iter()
+        .map(|(tag, t)| -> anyhow::Result<(Tag, &str)> {
+            Ok((Tag::try_fit_byte_slice(tag)?, t))
+        })
+        .try_collect()?;
+    let hash = linkspace_rs::conventions::exchange_acl::lk_exchange_acl_set(
+        &lk.0,
+        &key.0,
+        domain_arg(domain)?,
+        grp_arg(group)?,
+        access_arg(access)?,
+        &templates,
+    )?;
+    Ok(PyBytes::new(py, &hash.0))
+}
+#[pyfunction]
+pub fn lk_exchange_acl_get<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+    access: &str,
+    pubkey: &[u8],
+    group: Option<&[u8]>,
+    domain: Option<&[u8]>,
+) -> anyhow::Result<Option<Vec<(&'o PyBytes, String)>>> {
+    let list = linkspace_rs::conventions::exchange_acl::lk_exchange_acl_get(
+        &lk.0,
+        domain_arg(domain)?,
+        grp_arg(group)?,
+        access_arg(access)?,
+        PubKey::try_fit_bytes_or_b64(pubkey)?,
+    )?;
+    Ok(list.map(|l| {
+        l.into_iter()
+            .map(|(tag, t)| (PyBytes::new(py, &tag.0), t))
+            .collect()
+    }))
+}
+#[pyfunction]
+pub fn lk_exchange_acl_check_pull<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+    pubkey: &[u8],
+    pull: &Pkt,
+) -> anyhow::Result<Option<&'o PyBytes>> {
+    let tag = linkspace_rs::conventions::exchange_acl::lk_exchange_acl_check_pull(
+        &lk.0,
+        PubKey::try_fit_bytes_or_b64(pubkey)?,
+        &pull.0,
+    )?;
+    Ok(tag.map(|t| PyBytes::new(py, &t.0)))
+}
 
 #[pyclass]
 #[pyo3(get_all)]
@@ -633,6 +890,56 @@ pub fn lk_info(lk: &Linkspace) -> anyhow::Result<LkInfo> {
     })
 }
 
+fn quota_kind(kind: &str) -> anyhow::Result<linkspace_rs::runtime::quota::QuotaKind> {
+    use linkspace_rs::runtime::quota::QuotaKind;
+    Ok(match kind {
+        "pubkey" => QuotaKind::PubKey,
+        "group" => QuotaKind::Group,
+        "domain" => QuotaKind::Domain,
+        e => anyhow::bail!("unknown quota kind {e:?} - expected pubkey, group, or domain"),
+    })
+}
+#[pyfunction]
+#[pyo3(signature=(lk,kind,id=None,pkts_per_sec=None,bytes_per_day=None))]
+pub fn lk_quota_set(
+    lk: &Linkspace,
+    kind: &str,
+    id: Option<&[u8]>,
+    pkts_per_sec: Option<u32>,
+    bytes_per_day: Option<u64>,
+) -> anyhow::Result<()> {
+    use linkspace_rs::runtime::quota::*;
+    let quota = Quota {
+        pkts_per_sec,
+        bytes_per_day,
+    };
+    lk_quota_set(&lk.0, quota_kind(kind)?, id.unwrap_or_default(), quota)?;
+    Ok(())
+}
+#[pyfunction]
+pub fn lk_quota_list<'o>(
+    py: Python<'o>,
+    lk: &Linkspace,
+) -> anyhow::Result<Vec<(&'static str, &'o PyBytes, Option<u32>, Option<u64>)>> {
+    use linkspace_rs::runtime::quota::*;
+    Ok(lk_quota_list(&lk.0)?
+        .into_iter()
+        .map(|(kind, id, quota)| {
+            let kind = match kind {
+                QuotaKind::PubKey => "pubkey",
+                QuotaKind::Group => "group",
+                QuotaKind::Domain => "domain",
+            };
+            (
+                kind,
+                PyBytes::new(py, &id),
+                quota.pkts_per_sec,
+                quota.bytes_per_day,
+            )
+        })
+        .collect())
+}
+
 /** linkspace python bindings. follows the linkspace api (https://www.linkspace.dev/guide/index.html)
 **/
 #[pymodule]
@@ -664,20 +971,33 @@ fn linkspace(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(crate::lk_keygen, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_key_encrypt, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_key_decrypt, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_encrypt_for, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_encrypt_for_group, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_encrypted_for, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_decrypt, m)?)?;
 
     m.add_function(wrap_pyfunction!(crate::lk_eval, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_eval2str, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_register_func, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_register_macro, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_unregister, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_encode, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_encode_ab_frame, m)?)?;
 
     m.add_function(wrap_pyfunction!(crate::lk_query, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_hash_query, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_query_parse, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_query_push, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_query_push_strict, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_query_template, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_query_satisfies, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_query_print, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_query_print_aliases, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_query_clear, m)?)?;
 
     m.add_function(wrap_pyfunction!(crate::lk_open, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_save, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_save_state, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_save_all, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_save_all_ext, m)?)?;
 
@@ -696,8 +1016,17 @@ fn linkspace(py: Python, m: &PyModule) -> PyResult<()> {
 
     m.add_function(wrap_pyfunction!(crate::lk_key, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_pull, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_pull_close, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_pull_watch, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_pull_status, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_exchange_acl_set, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_exchange_acl_get, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_exchange_acl_check_pull, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_status_watch, m)?)?;
     m.add_function(wrap_pyfunction!(crate::lk_status_set, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_status_history, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_quota_set, m)?)?;
+    m.add_function(wrap_pyfunction!(crate::lk_quota_list, m)?)?;
 
     m.add_function(wrap_pyfunction!(crate::b64, m)?)?;
     m.add_function(wrap_pyfunction!(crate::space, m)?)?;